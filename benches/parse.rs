@@ -0,0 +1,56 @@
+// Throughput of the parser on a file of 10 000 statements, run with `cargo bench`.
+// A change is measured against the commit before it by saving a baseline there and
+// comparing to it after, copying this file over if the older commit does not have it:
+//
+//   git checkout <before> && cargo bench --bench parse -- --save-baseline before
+//   git checkout <after> && cargo bench --bench parse -- --baseline before
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use fin_swift_rs::{split_messages, MessageReader, MT940};
+use std::hint::black_box;
+
+const MESSAGES: usize = 10_000;
+
+fn file() -> String {
+    let messages = [
+        include_str!("../src/test_messages/basic_test_1.txt"),
+        include_str!("../src/test_messages/basic_test_2.txt"),
+        include_str!("../src/test_messages/basic_test_3.txt"),
+        include_str!("../src/test_messages/basic_test_4.txt"),
+    ];
+
+    messages
+        .iter()
+        .cycle()
+        .take(MESSAGES)
+        .map(|x| x.trim_end())
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+fn parse(c: &mut Criterion) {
+    let file = file();
+
+    // a run over the file took seconds with the regex tokenizer, 10 runs are plenty
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(file.len() as u64));
+    group.sample_size(10);
+
+    group.bench_function("MT940::new", |b| {
+        b.iter(|| {
+            for message in split_messages(&file) {
+                black_box(MT940::new(message).unwrap());
+            }
+        })
+    });
+    group.bench_function("MessageReader", |b| {
+        b.iter(|| {
+            for message in MessageReader::new(file.as_bytes()) {
+                black_box(message.unwrap().mt940().unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,162 @@
+use crate::reader::{read_error, Message, MessageSplitter, DEFAULT_MAX_MESSAGE_LEN};
+use eyre::Result;
+use futures_core::Stream;
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+const BUFFER_LEN: usize = 8 * 1024;
+
+// The messages of an async reader one at a time, split the same way as by
+// `MessageReader`. A reader that is not `Unpin` can be passed as `Box::pin(reader)`
+pub struct MessageStream<R> {
+    reader: R,
+    splitter: MessageSplitter,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> MessageStream<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_max_message_len(reader, DEFAULT_MAX_MESSAGE_LEN)
+    }
+
+    pub fn with_max_message_len(reader: R, max_message_len: usize) -> Self {
+        Self {
+            reader,
+            splitter: MessageSplitter::new(max_message_len),
+            buffer: vec![0; BUFFER_LEN],
+            done: false,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for MessageStream<R> {
+    type Item = Result<Message>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(message) = this.splitter.next_message() {
+                return Poll::Ready(Some(message));
+            }
+
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            let mut buffer = ReadBuf::new(&mut this.buffer);
+
+            match Pin::new(&mut this.reader).poll_read(cx, &mut buffer) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) if e.kind() == ErrorKind::Interrupted => continue,
+                Poll::Ready(Err(e)) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(read_error(e))));
+                }
+                Poll::Ready(Ok(())) if buffer.filled().is_empty() => {
+                    this.splitter.finish();
+                    this.done = true;
+                }
+                Poll::Ready(Ok(())) => {
+                    this.splitter.push(buffer.filled());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::split_messages;
+    use crate::mt940::MT940;
+    use std::future::poll_fn;
+
+    // Hands out the data a few bytes at a time and is not ready every other poll, as a
+    // socket would be
+    struct ChunkedReader {
+        data: Vec<u8>,
+        chunk_len: usize,
+        ready: bool,
+    }
+
+    impl AsyncRead for ChunkedReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buffer: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            self.ready = !self.ready;
+
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+
+            let len = self.chunk_len.min(self.data.len()).min(buffer.remaining());
+            buffer.put_slice(&self.data[..len]);
+            self.data.drain(..len);
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    async fn collect<R: AsyncRead + Unpin>(mut stream: MessageStream<R>) -> Vec<Result<Message>> {
+        let mut messages = vec![];
+
+        while let Some(message) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            messages.push(message);
+        }
+
+        messages
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_message_stream() -> Result<()> {
+        let first = include_str!("./test_messages/basic_test_1.txt");
+        let second = include_str!("./test_messages/basic_test_4.txt");
+        let data = format!("{first}\r\n{second}\n{first}");
+
+        for chunk_len in [1, 2, 3, 5, 64, 100_000] {
+            let stream = MessageStream::new(ChunkedReader {
+                data: data.clone().into_bytes(),
+                chunk_len,
+                ready: false,
+            });
+            let messages = block_on(collect(stream))
+                .into_iter()
+                .collect::<Result<Vec<_>>>()?;
+
+            assert_eq!(messages.len(), 3);
+
+            for (message, expected) in messages.iter().zip(split_messages(&data)) {
+                assert_eq!(message.mt940()?, MT940::new(expected)?);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_stream_in_memory() {
+        let data = b"{1:F01\xff}\n{1:F01ASNBNL21XXXX0000000000}".as_slice();
+        let messages = block_on(collect(MessageStream::new(data)));
+
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .starts_with("message at byte 0 is not valid UTF-8"));
+        assert_eq!(messages[1].as_ref().unwrap().offset, 9);
+        assert!(messages[1].as_ref().unwrap().mt940().is_err());
+    }
+}
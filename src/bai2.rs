@@ -0,0 +1,776 @@
+use crate::mt940::MT940;
+use crate::statement::{Entry, EntryStatus, Statement, StatementBalance, StatementSource};
+use crate::utils::{Balance, BalanceType, CreditDebit, TransactionType, TransactionTypeMarker};
+use crate::xml::currency_from_code;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use eyre::{eyre, Result};
+use iso_currency::Currency;
+
+// BAI2 detail type codes of the SWIFT transaction types that have one. Anything
+// else is written as a miscellaneous credit (399) or debit (699)
+const CREDIT_TYPE_CODES: [(&str, &str); 3] = [("INT", "354"), ("LBX", "115"), ("TRF", "195")];
+const DEBIT_TYPE_CODES: [(&str, &str); 4] = [
+    ("CHK", "475"),
+    ("DDT", "451"),
+    ("TRF", "495"),
+    ("CHG", "698"),
+];
+
+// Summary and status type codes of the account identifier (03) record
+const OPENING_LEDGER: &str = "010";
+const CLOSING_LEDGER: &str = "015";
+const CLOSING_AVAILABLE: &str = "045";
+const TOTAL_CREDITS: &str = "100";
+const TOTAL_DEBITS: &str = "400";
+const NON_MONETARY: &str = "890";
+
+// The file header (01) and group header (02) fields that a statement does not carry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bai2Header {
+    pub sender: String,
+    pub receiver: String,
+    pub file_id: String,
+    pub creation_date_time: NaiveDateTime,
+}
+
+// Converts an MT940 into a BAI2 file with the sending bank as originator.
+// The file header needs a creation time which MT940 does not carry, so it is passed in
+pub fn bai2_from_mt940(mt940: &MT940, creation_date_time: NaiveDateTime) -> Result<String> {
+    let header = Bai2Header {
+        sender: mt940.basic.source_address.bic(),
+        receiver: mt940.application.destination_address.bic(),
+        file_id: mt940.text.tag_20.transaction_reference_number.to_string(),
+        creation_date_time,
+    };
+
+    bai2_from_statements(&[Statement::from(mt940)], &header)
+}
+
+// One group per statement, dated on the closing balance. BAI2 is a daily report
+// with no entry date per detail, only a value date when it differs from the as-of date
+pub fn bai2_from_statements(statements: &[Statement], header: &Bai2Header) -> Result<String> {
+    let mut records = vec![format!(
+        "01,{},{},{},{},{},,,2/",
+        header.sender,
+        header.receiver,
+        header.creation_date_time.format("%y%m%d"),
+        header.creation_date_time.format("%H%M"),
+        header.file_id
+    )];
+    let mut file_total = 0;
+
+    for statement in statements {
+        let currency = statement.currency;
+        let as_of_date = as_of_date(statement, header);
+        let modifier = match &statement.closing_balance {
+            Some(x) if x.balance_type == BalanceType::Intermediary => "1",
+            _ => "2",
+        };
+
+        let group_start = records.len();
+        records.push(format!(
+            "02,{},{},1,{},,{},{modifier}/",
+            header.receiver,
+            header.sender,
+            as_of_date.format("%y%m%d"),
+            currency.code()
+        ));
+
+        let account_start = records.len();
+        let mut account_total = 0;
+        let mut summary = vec![];
+
+        let balances = [
+            (
+                OPENING_LEDGER,
+                statement.opening_balance.as_ref().map(|x| &x.balance_data),
+            ),
+            (
+                CLOSING_LEDGER,
+                statement.closing_balance.as_ref().map(|x| &x.balance_data),
+            ),
+            (
+                CLOSING_AVAILABLE,
+                statement.closing_available_balance.as_ref(),
+            ),
+        ];
+        for (type_code, balance) in balances {
+            if let Some(balance) = balance {
+                let amount = minor_units(currency, balance.signed_amount());
+                account_total += amount;
+                summary.push(format!("{type_code},{amount},,"));
+            }
+        }
+
+        let (credits, debits): (Vec<&Entry>, Vec<&Entry>) = statement
+            .entries
+            .iter()
+            .partition(|x| x.signed_amount() >= 0.0);
+        for (type_code, entries) in [(TOTAL_CREDITS, &credits), (TOTAL_DEBITS, &debits)] {
+            let amount: i64 = entries
+                .iter()
+                .map(|x| minor_units(currency, x.amount))
+                .sum();
+            account_total += amount;
+            summary.push(format!("{type_code},{amount},{},", entries.len()));
+        }
+
+        records.push(format!(
+            "03,{},{},{}/",
+            statement.account,
+            currency.code(),
+            summary.join(",")
+        ));
+
+        for entry in &statement.entries {
+            let amount = minor_units(currency, entry.amount);
+            account_total += amount;
+
+            let funds_type = if entry.value_date == as_of_date {
+                "0".to_string()
+            } else {
+                format!("V,{},", entry.value_date.format("%y%m%d"))
+            };
+            let customer_reference = match entry.account_owner_reference.as_str() {
+                "NONREF" => "",
+                x => x,
+            };
+
+            let mut lines = entry.information.as_deref().unwrap_or_default().lines();
+            let record = format!(
+                "16,{},{amount},{funds_type},{},{customer_reference}",
+                type_code(entry),
+                entry
+                    .account_servicing_institution_reference
+                    .as_deref()
+                    .unwrap_or_default(),
+            );
+
+            match lines.next() {
+                Some(text) => records.push(format!("{record},{text}")),
+                None => records.push(format!("{record}/")),
+            }
+            records.extend(lines.map(|x| format!("88,{x}")));
+        }
+
+        records.push(format!(
+            "49,{account_total},{}/",
+            records.len() - account_start + 1
+        ));
+        records.push(format!(
+            "98,{account_total},1,{}/",
+            records.len() - group_start + 1
+        ));
+        file_total += account_total;
+    }
+
+    records.push(format!(
+        "99,{file_total},{},{}/",
+        statements.len(),
+        records.len() + 1
+    ));
+
+    Ok(records.join("\n") + "\n")
+}
+
+// The BAI2 detail type code of a statement line, the direction follows the sign so
+// that reversals end up on the side they are booked on
+pub fn type_code(entry: &Entry) -> &'static str {
+    let identification_code = match entry.transaction_type_marker {
+        TransactionTypeMarker::SwiftTransfer => "TRF",
+        _ => entry.identification_code.as_str(),
+    };
+
+    let (codes, miscellaneous): (&[(&str, &'static str)], _) = if entry.signed_amount() >= 0.0 {
+        (&CREDIT_TYPE_CODES, "399")
+    } else {
+        (&DEBIT_TYPE_CODES, "699")
+    };
+
+    codes
+        .iter()
+        .find(|(x, _)| *x == identification_code)
+        .map(|(_, x)| *x)
+        .unwrap_or(miscellaneous)
+}
+
+// Reads every account of a BAI2 file into a statement. The control totals and record
+// counts of the account (49), group (98) and file (99) trailers are checked
+pub fn statements_from_bai2(data: &str) -> Result<Vec<Statement>> {
+    let mut statements = vec![];
+
+    let mut file: Option<FileHeader> = None;
+    let mut group: Option<GroupHeader> = None;
+    let mut account: Option<Bai2Account> = None;
+
+    let mut file_totals = Totals::default();
+    let mut group_totals = Totals::default();
+
+    for (record, physical_records) in logical_records(data)? {
+        file_totals.records += physical_records;
+        group_totals.records += physical_records;
+        if let Some(account) = account.as_mut() {
+            account.totals.records += physical_records;
+        }
+
+        let record_code = record.get(..2).unwrap_or_default();
+        let fields = record.get(3..).unwrap_or_default().trim_end_matches('/');
+
+        match record_code {
+            "01" => {
+                let fields: Vec<&str> = fields.split(',').collect();
+                let version = fields.get(7).copied().unwrap_or_default();
+                if version != "2" {
+                    return Err(eyre!("BAI2 version '{version}' is not supported"));
+                }
+
+                file = Some(FileHeader {
+                    file_id: field(&fields, 4)?.to_string(),
+                    creation_date_time: date(field(&fields, 2)?)?
+                        .and_time(time(field(&fields, 3)?)?),
+                });
+            }
+            "02" => {
+                let fields: Vec<&str> = fields.split(',').collect();
+                let currency = match fields.get(5).copied().unwrap_or_default() {
+                    "" => None,
+                    x => Some(currency_from_code(x)?),
+                };
+
+                group_totals = Totals {
+                    records: 1,
+                    ..Totals::default()
+                };
+                group = Some(GroupHeader {
+                    as_of_date: date(field(&fields, 3)?)?,
+                    currency,
+                    interim: matches!(fields.get(6).copied(), Some("1" | "3")),
+                });
+            }
+            "03" => {
+                let group = group
+                    .as_ref()
+                    .ok_or_else(|| eyre!("BAI2 account record outside of a group"))?;
+                account = Some(Bai2Account::new(fields, group, file.as_ref())?);
+            }
+            "16" => {
+                let (group, account) = match (group.as_ref(), account.as_mut()) {
+                    (Some(group), Some(account)) => (group, account),
+                    _ => return Err(eyre!("BAI2 transaction detail outside of an account")),
+                };
+                // the text runs to the end of the record and may contain commas and slashes
+                account.detail(record.get(3..).unwrap_or_default(), group)?;
+            }
+            "49" => {
+                let account = account
+                    .take()
+                    .ok_or_else(|| eyre!("BAI2 account trailer without an account"))?;
+                account.totals.check("account", fields)?;
+
+                group_totals.amount += account.totals.amount;
+                group_totals.accounts += 1;
+                statements.push(account.statement);
+            }
+            "98" => {
+                group_totals.check("group", fields)?;
+                file_totals.amount += group_totals.amount;
+                file_totals.accounts += 1;
+                group = None;
+            }
+            "99" => {
+                file_totals.check("file", fields)?;
+                return Ok(statements);
+            }
+            _ => return Err(eyre!("unexpected BAI2 record code `{record_code}`")),
+        }
+    }
+
+    Err(eyre!("BAI2 file trailer (99) not found"))
+}
+
+struct FileHeader {
+    file_id: String,
+    creation_date_time: NaiveDateTime,
+}
+
+struct GroupHeader {
+    as_of_date: NaiveDate,
+    currency: Option<Currency>,
+    interim: bool,
+}
+
+// The running control total and record count of an account, group or file.
+// A group counts its accounts and a file its groups
+#[derive(Default)]
+struct Totals {
+    amount: i64,
+    accounts: usize,
+    records: usize,
+}
+
+impl Totals {
+    fn check(&self, name: &str, fields: &str) -> Result<()> {
+        let fields: Vec<&str> = fields.split(',').collect();
+
+        let amount = field(&fields, 0)?.parse::<i64>()?;
+        if amount != self.amount {
+            return Err(eyre!(
+                "BAI2 {name} control total {amount} does not match the sum {}",
+                self.amount
+            ));
+        }
+
+        // the account trailer has no number of accounts
+        let records_field = if name == "account" {
+            1
+        } else {
+            let accounts = field(&fields, 1)?.parse::<usize>()?;
+            if accounts != self.accounts {
+                return Err(eyre!(
+                    "BAI2 {name} trailer counts {accounts} accounts or groups but {} were found",
+                    self.accounts
+                ));
+            }
+            2
+        };
+
+        let records = field(&fields, records_field)?.parse::<usize>()?;
+        if records != self.records {
+            return Err(eyre!(
+                "BAI2 {name} trailer counts {records} records but {} were found",
+                self.records
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+struct Bai2Account {
+    statement: Statement,
+    totals: Totals,
+}
+
+impl Bai2Account {
+    fn new(fields: &str, group: &GroupHeader, file: Option<&FileHeader>) -> Result<Self> {
+        let mut fields = fields.split(',');
+
+        let account = fields.next().unwrap_or_default().to_string();
+        let currency = match fields.next().unwrap_or_default() {
+            "" => group.currency.ok_or_else(|| {
+                eyre!("BAI2 account {account} has no currency and neither has its group")
+            })?,
+            x => currency_from_code(x)?,
+        };
+
+        let mut statement = Statement {
+            source: StatementSource::Bai2,
+            reference: file.map(|x| x.file_id.clone()).unwrap_or_default(),
+            account,
+            currency,
+            statement_number: None,
+            sequence_number: None,
+            creation_date_time: file.map(|x| x.creation_date_time),
+            opening_balance: None,
+            closing_balance: None,
+            closing_available_balance: None,
+            forward_available_balances: vec![],
+            entries: vec![],
+            information: None,
+        };
+        let mut totals = Totals {
+            records: 1,
+            ..Totals::default()
+        };
+
+        while let Some(type_code) = fields.next().filter(|x| !x.is_empty()) {
+            let amount = fields.next().unwrap_or_default();
+            let _item_count = fields.next();
+            funds_type(&mut fields)?;
+
+            if amount.is_empty() {
+                continue;
+            }
+            let amount = amount.parse::<i64>()?;
+            totals.amount += amount;
+
+            let balance = || Balance {
+                credit_or_debit: if amount < 0 {
+                    CreditDebit::Debit
+                } else {
+                    CreditDebit::Credit
+                },
+                date: group.as_of_date,
+                currency,
+                amount: from_minor_units(currency, amount.abs()),
+            };
+
+            match type_code {
+                OPENING_LEDGER => {
+                    statement.opening_balance = Some(StatementBalance {
+                        balance_type: BalanceType::Final,
+                        balance_data: balance(),
+                    })
+                }
+                CLOSING_LEDGER => {
+                    statement.closing_balance = Some(StatementBalance {
+                        balance_type: if group.interim {
+                            BalanceType::Intermediary
+                        } else {
+                            BalanceType::Final
+                        },
+                        balance_data: balance(),
+                    })
+                }
+                CLOSING_AVAILABLE => statement.closing_available_balance = Some(balance()),
+                _ => {}
+            }
+        }
+
+        Ok(Self { statement, totals })
+    }
+
+    // `16,<type code>,<amount>,<funds type>,<bank reference>,<customer reference>,<text>`
+    fn detail(&mut self, record: &str, group: &GroupHeader) -> Result<()> {
+        let currency = self.statement.currency;
+        let mut rest = record;
+        let mut next = || {
+            let (field, remainder) = rest.split_once(',').unwrap_or((rest, ""));
+            rest = remainder;
+            field.trim_end_matches('/')
+        };
+
+        let type_code = next();
+        let amount = match next() {
+            "" => 0,
+            x => x.parse::<i64>()?,
+        };
+        self.totals.amount += amount;
+
+        let mut funds_fields = std::iter::from_fn(|| Some(next()));
+        let value_date = funds_type(&mut funds_fields)?;
+        let bank_reference = funds_fields.next().unwrap_or_default();
+
+        let (customer_reference, text) = match rest.split_once(',') {
+            Some((reference, text)) => (reference, Some(text)),
+            None => (rest.trim_end_matches('/'), None),
+        };
+
+        let debit_or_credit = match type_code.parse::<u32>() {
+            Ok(100..=399) => CreditDebit::Credit,
+            Ok(400..=699) => CreditDebit::Debit,
+            _ if type_code == NON_MONETARY => return Ok(()),
+            _ => {
+                return Err(eyre!(
+                    "BAI2 type code '{type_code}' is neither a credit nor a debit"
+                ))
+            }
+        };
+
+        let codes: &[(&str, &str)] = match debit_or_credit {
+            CreditDebit::Credit => &CREDIT_TYPE_CODES,
+            _ => &DEBIT_TYPE_CODES,
+        };
+        let identification_code = codes
+            .iter()
+            .find(|(_, x)| *x == type_code)
+            .map(|(x, _)| *x)
+            .unwrap_or("MSC");
+
+        self.statement.entries.push(Entry {
+            status: EntryStatus::Booked,
+            value_date: value_date.unwrap_or(group.as_of_date),
+            entry_date: group.as_of_date,
+            debit_or_credit,
+            funds_code: None,
+            amount: from_minor_units(currency, amount),
+            transaction_type_marker: TransactionTypeMarker::NonSwiftTransfer,
+            transaction_type: TransactionType::try_from(identification_code).ok(),
+            identification_code: identification_code.to_string(),
+            bank_transaction_code: None,
+            account_owner_reference: match customer_reference {
+                "" => "NONREF".to_string(),
+                x => x.to_string(),
+            },
+            account_servicing_institution_reference: Some(bank_reference)
+                .filter(|x| !x.is_empty())
+                .map(str::to_string),
+            supplementary_details: None,
+            information: text.filter(|x| !x.is_empty()).map(str::to_string),
+        });
+
+        Ok(())
+    }
+}
+
+// Joins continuation (88) records onto the record they continue. A record cut at a
+// field ends in `/` and continues with the next field, a cut text continues on a new line.
+// Returns each logical record with the number of physical records it was made of
+fn logical_records(data: &str) -> Result<Vec<(String, usize)>> {
+    let mut records: Vec<(String, usize)> = vec![];
+
+    // trailing blanks can be part of a text, so only line endings are dropped
+    for line in data
+        .lines()
+        .map(|x| x.trim_end_matches('\r'))
+        .filter(|x| !x.trim().is_empty())
+    {
+        match line.strip_prefix("88,") {
+            Some(continuation) => {
+                let (record, count) = records
+                    .last_mut()
+                    .ok_or_else(|| eyre!("BAI2 continuation record without a record"))?;
+
+                match record.strip_suffix('/') {
+                    Some(cut) => *record = format!("{cut},{continuation}"),
+                    None => *record = format!("{record}\n{continuation}"),
+                }
+                *count += 1;
+            }
+            None => records.push((line.to_string(), 1)),
+        }
+    }
+
+    Ok(records)
+}
+
+// Skips the funds type and its availability fields, returning the value date of a `V`
+fn funds_type<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Result<Option<NaiveDate>> {
+    match fields.next().unwrap_or_default() {
+        "V" => {
+            let value_date = date(fields.next().unwrap_or_default())?;
+            let _value_time = fields.next();
+            Ok(Some(value_date))
+        }
+        "S" => {
+            fields.by_ref().take(3).for_each(drop);
+            Ok(None)
+        }
+        "D" => {
+            // each distribution is an availability in days and an amount
+            let count = fields.next().unwrap_or_default();
+            let distribution_fields = count
+                .parse::<usize>()
+                .ok()
+                .and_then(|x| x.checked_mul(2))
+                .ok_or_else(|| {
+                    eyre!("BAI2 distribution count is either missing or the value '{count}' is not valid")
+                })?;
+
+            fields.by_ref().take(distribution_fields).for_each(drop);
+            Ok(None)
+        }
+        _ => Ok(None),
+    }
+}
+
+fn as_of_date(statement: &Statement, header: &Bai2Header) -> NaiveDate {
+    statement
+        .closing_balance
+        .as_ref()
+        .map(|x| x.balance_data.date)
+        .or_else(|| statement.entries.iter().map(|x| x.entry_date).max())
+        .unwrap_or_else(|| header.creation_date_time.date())
+}
+
+fn field<'a>(fields: &[&'a str], index: usize) -> Result<&'a str> {
+    fields
+        .get(index)
+        .copied()
+        .ok_or_else(|| eyre!("BAI2 record is missing field {}", index + 1))
+}
+
+fn date(input: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(input, "%y%m%d")
+        .map_err(|_| eyre!("BAI2 date is either missing or the value '{input}' is not valid"))
+}
+
+// `2400` and `9999` both mean the end of the day
+fn time(input: &str) -> Result<NaiveTime> {
+    match input {
+        "" | "2400" | "9999" => Ok(NaiveTime::from_hms_opt(23, 59, 59).unwrap_or_default()),
+        _ => NaiveTime::parse_from_str(input, "%H%M")
+            .map_err(|_| eyre!("BAI2 time is either missing or the value '{input}' is not valid")),
+    }
+}
+
+fn minor_units(currency: Currency, amount: f64) -> i64 {
+    (amount * 10f64.powi(exponent(currency))).round() as i64
+}
+
+fn from_minor_units(currency: Currency, amount: i64) -> f64 {
+    amount as f64 / 10f64.powi(exponent(currency))
+}
+
+fn exponent(currency: Currency) -> i32 {
+    i32::from(currency.exponent().unwrap_or(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn creation_date_time() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2009, 10, 1)
+            .and_then(|x| x.and_hms_opt(8, 0, 0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_bai2_from_mt940() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let bai2 = bai2_from_mt940(&mt940, creation_date_time())?;
+        let records: Vec<&str> = bai2.lines().collect();
+
+        assert_eq!(
+            records[0],
+            "01,ASNBNL21,ASNBNL21,091001,0800,3996-11-11111111,,,2/"
+        );
+        assert_eq!(records[1], "02,ASNBNL21,ASNBNL21,1,090930,,EUR,2/");
+        assert_eq!(
+            records[2],
+            "03,DABADKKK/111111-11111111,EUR,010,5448404,,,015,5312694,,,045,5318931,,,\
+             100,23,1,,400,135733,5,/"
+        );
+        assert_eq!(
+            records[3],
+            "16,354,23,V,091001,,,Interest,For your inform. IBAN no.: FI1111111111111111"
+        );
+        assert_eq!(
+            records[4],
+            "88,DABADKKK                                                 "
+        );
+        assert_eq!(
+            records[7],
+            "16,699,58392,V,090925,,1234,1110030403010139,11100304030101391234"
+        );
+        assert!(bai2.contains("\n16,699,5500,V,090929,,,0000000000000269,"));
+
+        let trailers = &records[records.len() - 3..];
+        assert_eq!(
+            trailers,
+            ["49,16351541,19/", "98,16351541,1,21/", "99,16351541,1,23/"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_bai2_round_trip() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let statement = Statement::from(&mt940);
+        let bai2 = bai2_from_mt940(&mt940, creation_date_time())?;
+
+        let statements = statements_from_bai2(&bai2)?;
+        assert_eq!(statements.len(), 1);
+
+        let read = &statements[0];
+        assert_eq!(read.source, StatementSource::Bai2);
+        assert_eq!(read.reference, statement.reference);
+        assert_eq!(read.account, statement.account);
+        assert_eq!(read.currency, statement.currency);
+        assert_eq!(read.creation_date_time, Some(creation_date_time()));
+        assert_eq!(
+            read.opening_balance.as_ref().map(|x| x.balance_data.amount),
+            Some(54484.04)
+        );
+        assert_eq!(read.closing_balance, statement.closing_balance);
+        assert_eq!(
+            read.closing_available_balance,
+            statement.closing_available_balance
+        );
+        assert_eq!(read.entries.len(), statement.entries.len());
+
+        for (read, entry) in read.entries.iter().zip(&statement.entries) {
+            assert_eq!(read.signed_amount(), entry.signed_amount());
+            assert_eq!(read.value_date, entry.value_date);
+            assert_eq!(read.account_owner_reference, entry.account_owner_reference);
+            assert_eq!(
+                read.account_servicing_institution_reference,
+                entry.account_servicing_institution_reference
+            );
+            assert_eq!(read.information, entry.information);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bai2_type_codes() -> Result<()> {
+        let bai2 = "01,BANKUS33,CUSTOMER,230405,2400,FILE1,80,,2/\n\
+                    02,CUSTOMER,BANKUS33,1,230404,,USD,2/\n\
+                    03,123456789,,010,-10000,,,015,+890000,,/\n\
+                    16,354,100000,0,BANKREF1,,INTEREST\n\
+                    16,495,200000,S,100000,100000,0,,CUSTREF2/\n\
+                    16,451,10000,D,2,0,5000,1,5000,BANKREF3,CUSTREF3,ACH DEBIT\n\
+                    88,UTILITY CO\n\
+                    16,890,,Z,,,NOTE/\n\
+                    16,175,1000000/\n\
+                    49,2190000,8/\n\
+                    98,2190000,1,10/\n\
+                    99,2190000,1,12/\n";
+
+        let statements = statements_from_bai2(bai2)?;
+        let statement = &statements[0];
+        let entries = &statement.entries;
+
+        assert_eq!(statement.currency, Currency::USD);
+        assert_eq!(
+            statement
+                .opening_balance
+                .as_ref()
+                .map(|x| x.balance_data.signed_amount()),
+            Some(-100.0)
+        );
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].transaction_type, Some(TransactionType::INT));
+        assert_eq!(entries[0].debit_or_credit, CreditDebit::Credit);
+        assert_eq!(
+            entries[0]
+                .account_servicing_institution_reference
+                .as_deref(),
+            Some("BANKREF1")
+        );
+        assert_eq!(entries[0].account_owner_reference, "NONREF");
+        assert_eq!(entries[0].information.as_deref(), Some("INTEREST"));
+        assert_eq!(entries[1].transaction_type_code(), "NTRF");
+        assert_eq!(entries[1].signed_amount(), -2000.0);
+        assert_eq!(entries[1].account_owner_reference, "CUSTREF2");
+        assert_eq!(entries[2].transaction_type, Some(TransactionType::DDT));
+        assert_eq!(
+            entries[2].information.as_deref(),
+            Some("ACH DEBIT\nUTILITY CO")
+        );
+        assert_eq!(entries[3].transaction_type_code(), "NMSC");
+        assert_eq!(entries[3].amount, 10000.0);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "BAI2 account control total 1 does not match the sum 2")]
+    fn test_bai2_control_total() {
+        let bai2 = "01,BANK,CUSTOMER,230405,1200,FILE1,,,2/\n\
+                    02,CUSTOMER,BANK,1,230404,,USD,2/\n\
+                    03,123456789,,010,2,,/\n\
+                    49,1,2/\n";
+
+        statements_from_bai2(bai2).unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "BAI2 distribution count is either missing or the value '18446744073709551615' is not valid"
+    )]
+    fn test_bai2_distribution_count() {
+        let bai2 = "01,BANK,CUSTOMER,230405,1200,FILE1,,,2/\n\
+                    02,CUSTOMER,BANK,1,230404,,USD,2/\n\
+                    03,123456789,USD,010,0,,/\n\
+                    16,175,100,D,18446744073709551615,1,100/\n";
+
+        statements_from_bai2(bai2).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "BAI2 version '3' is not supported")]
+    fn test_bai2_version() {
+        statements_from_bai2("01,BANK,CUSTOMER,230405,1200,FILE1,,,3/\n").unwrap();
+    }
+}
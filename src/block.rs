@@ -1,18 +1,22 @@
+use crate::profile::{generic, BankProfile, TransactionDetails};
+use crate::spec::{validate, validate_layout};
 use crate::tag::{
     AccountIdentification, BankingPriority, BookedFunds, ClosingAvailableBalance,
-    InformationToAccountOwner, MessageUserReference, OpeningBalance, PaymentControlsInformation,
-    PaymentReleaseInformationReceiver, RelatedReference, SanctionsScreeningInformation,
-    ServiceIdentifier, ServiceTypeIdentifier, StatementLine, StatementNumber,
-    TransactionReferenceNumber, Validation,
+    ForwardAvailableBalance, InformationToAccountOwner, InstructionCode, MessageUserReference,
+    OpeningBalance, Party, PaymentControlsInformation, PaymentReleaseInformationReceiver,
+    RegulatoryReporting, RelatedReference, RemittanceInformation, SanctionsScreeningInformation,
+    SenderToReceiverInformation, ServiceIdentifier, ServiceTypeIdentifier, StatementLine,
+    StatementNumber, TransactionReferenceNumber, Validation, ValueDateCurrencyAmount,
 };
 use crate::utils::{
-    naive_date_time_from_swift_date_time, AddressInformation, ApplicationId, BalanceType,
-    LogicalTerminalAddress, MessageInputReference, ServiceId, SwiftType, IO,
+    float_from_swift_amount, naive_date_time_from_swift_date_time, owned, AddressInformation,
+    ApplicationId, BalanceType, BankOperationCode, CurrencyAmount, DetailsOfCharges,
+    LogicalTerminalAddress, MessageInputReference, ParseContext, ServiceId, SwiftType, IO,
 };
 use chrono::NaiveDateTime;
 use eyre::{eyre, Result};
-use regex::Regex;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use uuid::Uuid;
 
 // https://www.paiementor.com/swift-mt-message-block-1-basic-header-description
@@ -20,7 +24,7 @@ use uuid::Uuid;
 
 // Block 1
 // Fundamental reference for any particular message
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Basic<'a> {
     pub application_id: ApplicationId,
     pub service_id: ServiceId,
@@ -31,11 +35,17 @@ pub struct Basic<'a> {
 
 impl<'a> Basic<'a> {
     pub fn new(block_data: &'a str) -> Result<Self> {
-        let application_id = ApplicationId::try_from(&block_data[..1])?;
-        let service_id = ServiceId::try_from(&block_data[1..3])?;
-        let source_address = LogicalTerminalAddress::new(&block_data[3..15])?;
-        let session_number = block_data[15..19].parse::<u32>()?;
-        let sequence_number = block_data[19..].parse::<u32>()?;
+        let invalid =
+            || eyre!("Basic Header is either missing or the value '{block_data}' is not valid");
+        let field = |start: usize, end: usize| block_data.get(start..end).ok_or_else(invalid);
+
+        let application_id = ApplicationId::try_from(field(0, 1)?)?;
+        let service_id = ServiceId::try_from(field(1, 3)?)?;
+        let source_address = LogicalTerminalAddress::new(field(3, 15)?)?;
+        let session_number = field(15, 19)?.parse::<u32>().map_err(|_| invalid())?;
+        let sequence_number = field(19, block_data.len())?
+            .parse::<u32>()
+            .map_err(|_| invalid())?;
 
         Ok(Self {
             application_id,
@@ -45,58 +55,71 @@ impl<'a> Basic<'a> {
             sequence_number,
         })
     }
+
+    pub fn into_owned(self) -> Basic<'static> {
+        Basic {
+            application_id: self.application_id,
+            service_id: self.service_id,
+            source_address: self.source_address.into_owned(),
+            session_number: self.session_number,
+            sequence_number: self.sequence_number,
+        }
+    }
 }
 
 // Block 2
 // Information about the message itself
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Application<'a> {
     pub input_output_id: IO,
     pub message_type: SwiftType,
     pub destination_address: LogicalTerminalAddress<'a>,
-    pub priority: Option<&'a str>,
-    pub delivery_monitoring: Option<&'a str>,
-    pub obsolescence_period: Option<&'a str>,
+    pub priority: Option<Cow<'a, str>>,
+    pub delivery_monitoring: Option<Cow<'a, str>>,
+    pub obsolescence_period: Option<Cow<'a, str>>,
 }
 
 impl<'a> Application<'a> {
     pub fn new(block_data: &'a str) -> Result<Self> {
-        let input_output_id = IO::try_from(&block_data[..1])?;
-        let message_type = SwiftType::try_from(&block_data[1..4])?;
+        let invalid = || {
+            eyre!("Application Header is either missing or the value '{block_data}' is not valid")
+        };
+        let field = |start: usize, end: usize| block_data.get(start..end).ok_or_else(invalid);
 
-        let mut priority = None;
-        let mut delivery_monitoring = None;
-        let mut obsolescence_period = None;
+        let input_output_id = IO::try_from(field(0, 1)?)?;
+        let message_type = SwiftType::try_from(field(1, 4)?)?;
+        let destination_address = LogicalTerminalAddress::new(field(4, 16)?)?;
 
-        if block_data.len() >= 16 {
-            priority = Some(&block_data[16..17]);
-        }
-
-        if block_data.len() >= 18 {
-            delivery_monitoring = Some(&block_data[17..18]);
-        }
-
-        if block_data.len() >= 21 {
-            obsolescence_period = Some(&block_data[18..]);
-        }
-
-        let destination_address = LogicalTerminalAddress::new(&block_data[4..16])?;
+        let priority = block_data.get(16..17);
+        let delivery_monitoring = block_data.get(17..18);
+        let obsolescence_period = block_data.get(18..).filter(|_| block_data.len() >= 21);
 
         Ok(Self {
             input_output_id,
             message_type,
             destination_address,
-            priority,
-            delivery_monitoring,
-            obsolescence_period,
+            priority: priority.map(Cow::Borrowed),
+            delivery_monitoring: delivery_monitoring.map(Cow::Borrowed),
+            obsolescence_period: obsolescence_period.map(Cow::Borrowed),
         })
     }
+
+    pub fn into_owned(self) -> Application<'static> {
+        Application {
+            input_output_id: self.input_output_id,
+            message_type: self.message_type,
+            destination_address: self.destination_address.into_owned(),
+            priority: self.priority.map(owned),
+            delivery_monitoring: self.delivery_monitoring.map(owned),
+            obsolescence_period: self.obsolescence_period.map(owned),
+        }
+    }
 }
 
 // Block 3
 // Allows users to provide their own reference
 // https://www.paiementor.com/swift-mt-message-block-3-user-header-description/
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct User<'a> {
     pub tag_103: Option<ServiceIdentifier<'a>>,
     pub tag_113: Option<BankingPriority<'a>>,
@@ -111,10 +134,13 @@ pub struct User<'a> {
     pub tag_165: Option<PaymentReleaseInformationReceiver<'a>>,
     pub tag_433: Option<SanctionsScreeningInformation<'a>>,
     pub tag_434: Option<PaymentControlsInformation<'a>>,
+    // tags that are not part of the format, only collected in lenient mode
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown_tags: Vec<UnknownTag<'a>>,
 }
 
 impl<'a> User<'a> {
-    pub fn new(block_data: &'a str) -> Result<Self> {
+    pub fn new(block_data: &'a str, context: &mut ParseContext) -> Result<Self> {
         let mut service_identifier = None;
         let mut banking_priority = None;
         let mut message_user_reference = None;
@@ -128,13 +154,13 @@ impl<'a> User<'a> {
         let mut payment_release_information_receiver = None;
         let mut sanctions_screening_information = None;
         let mut payment_controls_information = None;
+        let mut unknown_tags = vec![];
 
-        let block_start: Vec<usize> = block_data.match_indices('{').map(|(i, _)| i + 1).collect();
-        let block_end: Vec<usize> = block_data.match_indices('}').map(|(i, _)| i).collect();
-        let block_segments = block_start.iter().zip(block_end.iter());
+        let block_start = block_data.match_indices('{').map(|(i, _)| i + 1);
+        let block_end = block_data.match_indices('}').map(|(i, _)| i);
 
-        for (start, end) in block_segments {
-            let section = &block_data[*start..*end];
+        for (start, end) in block_start.zip(block_end) {
+            let section = &block_data[start..end];
             let index = section
                 .chars()
                 .position(|c| c == ':')
@@ -144,25 +170,26 @@ impl<'a> User<'a> {
 
             match tag {
                 "103" => {
-                    service_identifier = Some(ServiceIdentifier::new(value));
+                    service_identifier = Some(ServiceIdentifier::new(value)?);
                 }
                 "113" => {
-                    banking_priority = Some(BankingPriority::new(value));
+                    banking_priority = Some(BankingPriority::new(value)?);
                 }
                 "108" => {
-                    message_user_reference = Some(MessageUserReference::new(value));
+                    message_user_reference = Some(MessageUserReference::new(value)?);
                 }
                 "119" => {
                     validation = Some(Validation::new(value)?);
                 }
                 "423" => {
-                    balance_checkpoint_date = Some(naive_date_time_from_swift_date_time(value)?);
+                    balance_checkpoint_date =
+                        Some(naive_date_time_from_swift_date_time(value, context)?);
                 }
                 "106" => {
-                    message_input_reference = Some(MessageInputReference::new(value)?);
+                    message_input_reference = Some(MessageInputReference::new(value, context)?);
                 }
                 "424" => {
-                    related_reference = Some(RelatedReference::new(value));
+                    related_reference = Some(RelatedReference::new(value)?);
                 }
                 "111" => {
                     service_type_identifier = Some(ServiceTypeIdentifier::new(value));
@@ -185,7 +212,8 @@ impl<'a> User<'a> {
                     payment_controls_information = Some(PaymentControlsInformation::new(value));
                 }
                 _ => {
-                    return Err(eyre!("unexpected tag `{tag}` in User block"));
+                    context.recover(eyre!("unexpected tag `{tag}` in User block"))?;
+                    unknown_tags.push(UnknownTag::new(tag, value));
                 }
             }
         }
@@ -204,82 +232,141 @@ impl<'a> User<'a> {
             tag_165: payment_release_information_receiver,
             tag_433: sanctions_screening_information,
             tag_434: payment_controls_information,
+            unknown_tags,
         })
     }
+
+    pub fn into_owned(self) -> User<'static> {
+        User {
+            tag_103: self.tag_103.map(ServiceIdentifier::into_owned),
+            tag_113: self.tag_113.map(BankingPriority::into_owned),
+            tag_108: self.tag_108.map(MessageUserReference::into_owned),
+            tag_119: self.tag_119,
+            tag_423: self.tag_423,
+            tag_106: self.tag_106.map(MessageInputReference::into_owned),
+            tag_424: self.tag_424.map(RelatedReference::into_owned),
+            tag_111: self.tag_111.map(ServiceTypeIdentifier::into_owned),
+            tag_121: self.tag_121,
+            tag_115: self.tag_115.map(AddressInformation::into_owned),
+            tag_165: self
+                .tag_165
+                .map(PaymentReleaseInformationReceiver::into_owned),
+            tag_433: self.tag_433.map(SanctionsScreeningInformation::into_owned),
+            tag_434: self.tag_434.map(PaymentControlsInformation::into_owned),
+            unknown_tags: self
+                .unknown_tags
+                .into_iter()
+                .map(UnknownTag::into_owned)
+                .collect(),
+        }
+    }
 }
 
 // Block 4
 // Contains the text of the message
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Text<'a> {
     pub tag_20: TransactionReferenceNumber<'a>,
     pub tag_25: AccountIdentification<'a>,
     pub tag_28c: StatementNumber,
     pub tag_60: OpeningBalance,
-    pub tag_61: Vec<StatementLine<'a>>,
+    pub transactions: Vec<Transaction<'a>>,
     pub tag_62: BookedFunds,
     pub tag_64: Option<ClosingAvailableBalance>,
+    pub tag_65: Vec<ForwardAvailableBalance>,
+    // statement level information, i.e. any :86: that does not follow a :61:
+    pub tag_86: Vec<InformationToAccountOwner<'a>>,
+    // tags that are not part of the format, only collected in lenient mode
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown_tags: Vec<UnknownTag<'a>>,
+}
+
+// A :61: statement line paired with the :86: that follows it. This is normally
+// at most one tag, but some banks repeat :86: for every line of the narrative
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transaction<'a> {
+    pub tag_61: StatementLine<'a>,
     pub tag_86: Vec<InformationToAccountOwner<'a>>,
+    // the :86: decoded by the bank profile, if the bank structures it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<TransactionDetails>,
 }
 
 impl<'a> Text<'a> {
-    pub fn new(block_data: &'a str) -> Result<Self> {
+    pub fn new(block_data: &'a str, context: &mut ParseContext) -> Result<Self> {
         let mut txn_ref_num = None;
         let mut account_id = None;
         let mut statement_num = None;
         let mut opening_balance = None;
         let mut booked_funds = None;
-        let mut statement_line: Vec<StatementLine> = vec![];
+        let mut transactions: Vec<Transaction> = vec![];
         let mut information_to_account_owner: Vec<InformationToAccountOwner> = vec![];
         let mut closing_available_balance = None;
+        let mut forward_available_balance = vec![];
+        let mut unknown_tags = vec![];
 
-        let tag_regex = Regex::new(r"(?m)(?:(\d{2}|\d{2}[A-Z]):.+)")?;
-
-        for tag in tag_regex.captures_iter(block_data) {
-            let block_key = tag
-                .get(1)
-                .ok_or_else(|| eyre!("block does not contain a key"))?
-                .as_str();
-            let block_data = tag
-                .get(0)
-                .ok_or_else(|| eyre!("block does not contain a value"))?
-                .as_str();
-            let value = block_data[block_key.len()..block_data.len()]
-                .trim_matches(|c| c == ':' || c == '\r');
+        for field in Field::tokenize_with(block_data, &context.profile)? {
+            let block_key = field.tag;
+            let value = field.value;
 
             match block_key {
                 "20" => {
-                    txn_ref_num = Some(TransactionReferenceNumber::new(value));
+                    txn_ref_num = Some(TransactionReferenceNumber::new(value)?);
                 }
                 "25" => {
-                    account_id = Some(AccountIdentification::new(value));
+                    account_id = Some(AccountIdentification::new(value)?);
                 }
                 "28C" => {
                     statement_num = Some(StatementNumber::new(value)?);
                 }
                 "60F" => {
-                    opening_balance = Some(OpeningBalance::new(BalanceType::Final, value)?);
+                    opening_balance =
+                        Some(OpeningBalance::new(BalanceType::Final, value, context)?);
                 }
                 "60M" => {
-                    opening_balance = Some(OpeningBalance::new(BalanceType::Intermediary, value)?);
+                    opening_balance = Some(OpeningBalance::new(
+                        BalanceType::Intermediary,
+                        value,
+                        context,
+                    )?);
                 }
                 "62F" => {
-                    booked_funds = Some(BookedFunds::new(BalanceType::Final, value)?);
+                    booked_funds = Some(BookedFunds::new(BalanceType::Final, value, context)?);
                 }
                 "62M" => {
-                    booked_funds = Some(BookedFunds::new(BalanceType::Intermediary, value)?);
+                    booked_funds =
+                        Some(BookedFunds::new(BalanceType::Intermediary, value, context)?);
                 }
                 "61" => {
-                    statement_line.push(StatementLine::new(value)?);
+                    transactions.push(Transaction {
+                        tag_61: StatementLine::new(value, context)?,
+                        tag_86: vec![],
+                        details: None,
+                    });
                 }
                 "86" => {
-                    information_to_account_owner.push(InformationToAccountOwner::new(value));
+                    validate_layout(block_key, value).or_else(|e| context.recover(e))?;
+                    let information = InformationToAccountOwner::new(value);
+
+                    // once the closing balance has been seen any :86: belongs to the statement
+                    match transactions.last_mut() {
+                        Some(transaction) if booked_funds.is_none() => {
+                            transaction.tag_86.push(information);
+                        }
+                        _ => {
+                            information_to_account_owner.push(information);
+                        }
+                    }
                 }
                 "64" => {
-                    closing_available_balance = Some(ClosingAvailableBalance::new(value)?);
+                    closing_available_balance = Some(ClosingAvailableBalance::new(value, context)?);
+                }
+                "65" => {
+                    forward_available_balance.push(ForwardAvailableBalance::new(value, context)?);
                 }
                 _ => {
-                    return Err(eyre!("unexpected block key `{block_key}` in Basic block"));
+                    context.recover(eyre!("unexpected block key `{block_key}` in Basic block"))?;
+                    unknown_tags.push(UnknownTag::new(block_key, value));
                 }
             };
         }
@@ -294,24 +381,445 @@ impl<'a> Text<'a> {
             opening_balance.ok_or_else(|| eyre!("missing opening balance (tag 60"))?;
         let booked_funds = booked_funds.ok_or_else(|| eyre!("missing booked funds (tag 62"))?;
 
+        // the funds code of a statement line is the third character of the statement currency
+        let currency = opening_balance.balance_data.currency;
+
+        for transaction in &transactions {
+            if let Some(funds_code) = transaction.tag_61.funds_code {
+                if currency.code().chars().nth(2) != Some(funds_code) {
+                    context.recover(eyre!(
+                        "funds code '{funds_code}' does not match the statement currency '{}'",
+                        currency.code()
+                    ))?;
+                }
+            }
+        }
+
+        for transaction in &mut transactions {
+            transaction.details = transaction
+                .information()
+                .and_then(|x| context.profile.information_format.decode(&x));
+        }
+
         Ok(Self {
             tag_20: txn_ref_num,
             tag_25: account_id,
             tag_28c: statement_num,
             tag_60: opening_balance,
-            tag_61: statement_line,
+            transactions,
             tag_62: booked_funds,
             tag_64: closing_available_balance,
+            tag_65: forward_available_balance,
             tag_86: information_to_account_owner,
+            unknown_tags,
+        })
+    }
+
+    pub fn into_owned(self) -> Text<'static> {
+        Text {
+            tag_20: self.tag_20.into_owned(),
+            tag_25: self.tag_25.into_owned(),
+            tag_28c: self.tag_28c,
+            tag_60: self.tag_60,
+            transactions: self
+                .transactions
+                .into_iter()
+                .map(Transaction::into_owned)
+                .collect(),
+            tag_62: self.tag_62,
+            tag_64: self.tag_64,
+            tag_65: self.tag_65,
+            tag_86: self
+                .tag_86
+                .into_iter()
+                .map(InformationToAccountOwner::into_owned)
+                .collect(),
+            unknown_tags: self
+                .unknown_tags
+                .into_iter()
+                .map(UnknownTag::into_owned)
+                .collect(),
+        }
+    }
+}
+
+impl<'a> Transaction<'a> {
+    // the narrative of all :86: tags of the transaction, one line per line of the message
+    pub fn information(&self) -> Option<String> {
+        if self.tag_86.is_empty() {
+            return None;
+        }
+
+        Some(
+            self.tag_86
+                .iter()
+                .map(|x| x.information_to_account_owner.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+
+    pub fn into_owned(self) -> Transaction<'static> {
+        Transaction {
+            tag_61: self.tag_61.into_owned(),
+            tag_86: self
+                .tag_86
+                .into_iter()
+                .map(InformationToAccountOwner::into_owned)
+                .collect(),
+            details: self.details,
+        }
+    }
+}
+
+// Block 4 of an MT103
+// Single customer credit transfer
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CustomerCreditTransfer<'a> {
+    pub tag_20: TransactionReferenceNumber<'a>,
+    pub tag_13c: Vec<Cow<'a, str>>,
+    pub tag_23b: BankOperationCode,
+    pub tag_23e: Vec<InstructionCode<'a>>,
+    pub tag_26t: Option<Cow<'a, str>>,
+    pub tag_32a: ValueDateCurrencyAmount,
+    pub tag_33b: Option<CurrencyAmount>,
+    pub tag_36: Option<f64>,
+    // ordering customer
+    pub tag_50: Party<'a>,
+    // ordering institution
+    pub tag_52: Option<Party<'a>>,
+    // sender's and receiver's correspondent
+    pub tag_53: Option<Party<'a>>,
+    pub tag_54: Option<Party<'a>>,
+    // intermediary and account with institution
+    pub tag_56: Option<Party<'a>>,
+    pub tag_57: Option<Party<'a>>,
+    // beneficiary customer
+    pub tag_59: Party<'a>,
+    pub tag_70: Option<RemittanceInformation<'a>>,
+    pub tag_71a: DetailsOfCharges,
+    pub tag_71f: Vec<CurrencyAmount>,
+    pub tag_71g: Option<CurrencyAmount>,
+    pub tag_72: Option<SenderToReceiverInformation<'a>>,
+    pub tag_77b: Option<RegulatoryReporting<'a>>,
+    // tags that are not part of the format, only collected in lenient mode
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown_tags: Vec<UnknownTag<'a>>,
+}
+
+impl<'a> CustomerCreditTransfer<'a> {
+    pub fn new(block_data: &'a str, context: &mut ParseContext) -> Result<Self> {
+        let mut txn_ref_num = None;
+        let mut time_indication = vec![];
+        let mut bank_operation_code = None;
+        let mut instruction_code = vec![];
+        let mut transaction_type_code = None;
+        let mut value_date_currency_amount = None;
+        let mut instructed_amount = None;
+        let mut exchange_rate = None;
+        let mut ordering_customer = None;
+        let mut ordering_institution = None;
+        let mut senders_correspondent = None;
+        let mut receivers_correspondent = None;
+        let mut intermediary = None;
+        let mut account_with_institution = None;
+        let mut beneficiary_customer = None;
+        let mut remittance_information = None;
+        let mut details_of_charges = None;
+        let mut senders_charges = vec![];
+        let mut receivers_charges = None;
+        let mut sender_to_receiver_information = None;
+        let mut regulatory_reporting = None;
+        let mut unknown_tags = vec![];
+
+        for field in Field::tokenize_with(block_data, &context.profile)? {
+            let block_key = field.tag;
+            let value = field.value;
+            let option = block_key.chars().nth(2);
+
+            match block_key {
+                "20" => {
+                    txn_ref_num = Some(TransactionReferenceNumber::new(value)?);
+                }
+                "13C" => {
+                    validate(block_key, value)?;
+                    time_indication.push(value);
+                }
+                "23B" => {
+                    bank_operation_code = Some(BankOperationCode::try_from(value)?);
+                }
+                "23E" => {
+                    instruction_code.push(InstructionCode::new(value)?);
+                }
+                "26T" => {
+                    validate(block_key, value)?;
+                    transaction_type_code = Some(value);
+                }
+                "32A" => {
+                    value_date_currency_amount =
+                        Some(ValueDateCurrencyAmount::new(value, context)?);
+                }
+                "33B" => {
+                    instructed_amount = Some(CurrencyAmount::new(value)?);
+                }
+                "36" => {
+                    validate(block_key, value)?;
+                    exchange_rate = Some(float_from_swift_amount(value)?);
+                }
+                "50A" | "50F" | "50K" => {
+                    ordering_customer = Some(Party::new(option, value)?);
+                }
+                "52A" | "52D" => {
+                    ordering_institution = Some(Party::new(option, value)?);
+                }
+                "53A" | "53B" | "53D" => {
+                    senders_correspondent = Some(Party::new(option, value)?);
+                }
+                "54A" | "54B" | "54D" => {
+                    receivers_correspondent = Some(Party::new(option, value)?);
+                }
+                "56A" | "56D" => {
+                    intermediary = Some(Party::new(option, value)?);
+                }
+                "57A" | "57B" | "57D" => {
+                    account_with_institution = Some(Party::new(option, value)?);
+                }
+                "59" | "59A" | "59F" => {
+                    beneficiary_customer = Some(Party::new(option, value)?);
+                }
+                "70" => {
+                    remittance_information = Some(RemittanceInformation::new(value)?);
+                }
+                "71A" => {
+                    details_of_charges = Some(DetailsOfCharges::try_from(value)?);
+                }
+                "71F" => {
+                    senders_charges.push(CurrencyAmount::new(value)?);
+                }
+                "71G" => {
+                    receivers_charges = Some(CurrencyAmount::new(value)?);
+                }
+                "72" => {
+                    sender_to_receiver_information = Some(SenderToReceiverInformation::new(value)?);
+                }
+                "77B" => {
+                    regulatory_reporting = Some(RegulatoryReporting::new(value)?);
+                }
+                _ => {
+                    context.recover(eyre!("unexpected block key `{block_key}` in Text block"))?;
+                    unknown_tags.push(UnknownTag::new(block_key, value));
+                }
+            };
+        }
+
+        Ok(Self {
+            tag_20: txn_ref_num
+                .ok_or_else(|| eyre!("missing transaction reference number (tag 20)"))?,
+            tag_13c: time_indication.into_iter().map(Cow::Borrowed).collect(),
+            tag_23b: bank_operation_code
+                .ok_or_else(|| eyre!("missing bank operation code (tag 23B)"))?,
+            tag_23e: instruction_code,
+            tag_26t: transaction_type_code.map(Cow::Borrowed),
+            tag_32a: value_date_currency_amount
+                .ok_or_else(|| eyre!("missing value date, currency and amount (tag 32A)"))?,
+            tag_33b: instructed_amount,
+            tag_36: exchange_rate,
+            tag_50: ordering_customer
+                .ok_or_else(|| eyre!("missing ordering customer (tag 50a)"))?,
+            tag_52: ordering_institution,
+            tag_53: senders_correspondent,
+            tag_54: receivers_correspondent,
+            tag_56: intermediary,
+            tag_57: account_with_institution,
+            tag_59: beneficiary_customer
+                .ok_or_else(|| eyre!("missing beneficiary customer (tag 59a)"))?,
+            tag_70: remittance_information,
+            tag_71a: details_of_charges
+                .ok_or_else(|| eyre!("missing details of charges (tag 71A)"))?,
+            tag_71f: senders_charges,
+            tag_71g: receivers_charges,
+            tag_72: sender_to_receiver_information,
+            tag_77b: regulatory_reporting,
+            unknown_tags,
         })
     }
+
+    pub fn into_owned(self) -> CustomerCreditTransfer<'static> {
+        CustomerCreditTransfer {
+            tag_20: self.tag_20.into_owned(),
+            tag_13c: self.tag_13c.into_iter().map(owned).collect(),
+            tag_23b: self.tag_23b,
+            tag_23e: self
+                .tag_23e
+                .into_iter()
+                .map(InstructionCode::into_owned)
+                .collect(),
+            tag_26t: self.tag_26t.map(owned),
+            tag_32a: self.tag_32a,
+            tag_33b: self.tag_33b,
+            tag_36: self.tag_36,
+            tag_50: self.tag_50.into_owned(),
+            tag_52: self.tag_52.map(Party::into_owned),
+            tag_53: self.tag_53.map(Party::into_owned),
+            tag_54: self.tag_54.map(Party::into_owned),
+            tag_56: self.tag_56.map(Party::into_owned),
+            tag_57: self.tag_57.map(Party::into_owned),
+            tag_59: self.tag_59.into_owned(),
+            tag_70: self.tag_70.map(RemittanceInformation::into_owned),
+            tag_71a: self.tag_71a,
+            tag_71f: self.tag_71f,
+            tag_71g: self.tag_71g,
+            tag_72: self.tag_72.map(SenderToReceiverInformation::into_owned),
+            tag_77b: self.tag_77b.map(RegulatoryReporting::into_owned),
+            unknown_tags: self
+                .unknown_tags
+                .into_iter()
+                .map(UnknownTag::into_owned)
+                .collect(),
+        }
+    }
+}
+
+// A tag that is not part of the format of its block, kept as it came in
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnknownTag<'a> {
+    pub tag: Cow<'a, str>,
+    pub value: Cow<'a, str>,
+}
+
+impl<'a> UnknownTag<'a> {
+    pub fn new(tag: &'a str, value: &'a str) -> Self {
+        Self {
+            tag: tag.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn into_owned(self) -> UnknownTag<'static> {
+        UnknownTag {
+            tag: owned(self.tag),
+            value: owned(self.value),
+        }
+    }
+}
+
+// A single field of block 4. The value runs from the tag until the start of the next
+// tag, so continuation lines (e.g. of :86:) are part of it
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Field<'a> {
+    pub tag: &'a str,
+    pub value: &'a str,
+    #[serde(borrow)]
+    pub lines: Vec<&'a str>,
+}
+
+impl<'a> Field<'a> {
+    pub fn new(tag: &'a str, data: &'a str) -> Self {
+        let mut lines = vec![];
+        let mut offset = 0;
+        let mut end = 0;
+
+        for line in data.split_inclusive('\n') {
+            let content = line.trim_end_matches(['\r', '\n']);
+
+            // trailing blank lines (e.g. before `-}`) are not part of the value
+            if !content.trim().is_empty() {
+                end = offset + content.len();
+            }
+
+            lines.push(content);
+            offset += line.len();
+        }
+
+        let value = &data[..end];
+        lines.truncate(value.lines().count());
+
+        Self { tag, value, lines }
+    }
+
+    // Splits the text block into its fields in a single pass over the lines. A tag starts
+    // a line, after any spaces or tabs, as `:`, two digits, an optional upper case letter
+    // and `:`. The value of a field runs up to the line of the next tag
+    pub fn tokenize(block_data: &'a str) -> Result<Vec<Self>> {
+        Self::tokenize_with(block_data, generic().as_ref())
+    }
+
+    // Splits the text block the way the bank of the profile writes it
+    pub fn tokenize_with(block_data: &'a str, profile: &BankProfile) -> Result<Vec<Self>> {
+        let mut fields = vec![];
+        // the tag being read and the start of its value
+        let mut field: Option<(&str, usize)> = None;
+        let mut line_start = 0;
+
+        loop {
+            if let Some((tag, value_start)) = tag_at(block_data, line_start, profile.indented_tags)
+            {
+                match field {
+                    Some((tag, start)) => {
+                        fields.push(Self::new(tag, &block_data[start..line_start]));
+                    }
+                    None if !block_data[..line_start].trim().is_empty() => {
+                        return Err(eyre!("unexpected data before the first tag in Text block"));
+                    }
+                    None => {}
+                }
+
+                field = Some((tag, value_start));
+            }
+
+            match block_data[line_start..].find('\n') {
+                Some(i) => line_start += i + 1,
+                None => break,
+            }
+        }
+
+        if let Some((tag, start)) = field {
+            fields.push(Self::new(tag, &block_data[start..]));
+        }
+
+        Ok(fields)
+    }
+}
+
+// The tag of a line and the start of its value, if the line starts with a tag
+fn tag_at(data: &str, line_start: usize, indented: bool) -> Option<(&str, usize)> {
+    let bytes = data.as_bytes();
+    let mut i = line_start;
+
+    while indented && matches!(bytes.get(i), Some(b' ' | b'\t')) {
+        i += 1;
+    }
+
+    if bytes.get(i) != Some(&b':') {
+        return None;
+    }
+
+    let tag_start = i + 1;
+    let mut tag_end = tag_start + 2;
+
+    if !bytes
+        .get(tag_start..tag_end)?
+        .iter()
+        .all(u8::is_ascii_digit)
+    {
+        return None;
+    }
+
+    if bytes.get(tag_end).is_some_and(u8::is_ascii_uppercase) {
+        tag_end += 1;
+    }
+
+    match bytes.get(tag_end) {
+        Some(b':') => Some((&data[tag_start..tag_end], tag_end + 1)),
+        _ => None,
+    }
 }
 
 // Block 5
 // Indicates special circumstances that relate to message handling or contains security information
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Trailer<'a> {
-    pub data: Option<&'a str>,
+    pub data: Option<Cow<'a, str>>,
 }
 
 impl<'a> Trailer<'a> {
@@ -319,16 +827,108 @@ impl<'a> Trailer<'a> {
         let mut data = None;
 
         if !block_data.is_empty() {
-            data = Some(block_data);
+            data = Some(Cow::Borrowed(block_data));
         }
 
         Self { data }
     }
+
+    pub fn into_owned(self) -> Trailer<'static> {
+        Trailer {
+            data: self.data.map(owned),
+        }
+    }
+}
+
+// Splits a FIN message into the id and the data of each of its blocks. A block starts at
+// `{`, a digit and `:` and runs up to the start of the next one
+pub fn split_blocks(message_data: &str) -> Result<Vec<(usize, &str)>> {
+    // files usually end with a line break after block 5
+    let message_data = message_data.trim_end();
+    let mut start =
+        block_start(message_data, 0).ok_or_else(|| eyre!("no blocks found in message"))?;
+    let mut blocks: Vec<(usize, &str)> = Vec::with_capacity(5);
+
+    loop {
+        let next = block_start(message_data, start + 1);
+        let end = next.unwrap_or(message_data.len());
+        // the id is read rather than counted, some banks leave out block 3 or block 5
+        let block_id = usize::from(message_data.as_bytes()[start + 1] - b'0');
+
+        if blocks.last().is_some_and(|(x, _)| *x >= block_id) {
+            return Err(eyre!("block {block_id} is out of order"));
+        }
+
+        let suffix = match block_id {
+            4 => Ok("-}"),
+            1 | 2 | 3 | 5 => Ok("}"),
+            _ => Err(eyre!("unexpected block_id `{block_id}`")),
+        }?;
+
+        let block_data = message_data[start + 3..end]
+            .strip_suffix(suffix)
+            .ok_or_else(|| eyre!("suffix '{suffix}' not found in block"))?;
+
+        blocks.push((block_id, block_data));
+
+        match next {
+            Some(next) => start = next,
+            None => break,
+        }
+    }
+
+    Ok(blocks)
+}
+
+// where the next block starts from `from` on
+fn block_start(message_data: &str, from: usize) -> Option<usize> {
+    let bytes = message_data.as_bytes();
+
+    message_data[from..]
+        .match_indices('{')
+        .map(|(i, _)| from + i)
+        .find(|i| {
+            bytes.get(i + 1).is_some_and(u8::is_ascii_digit) && bytes.get(i + 2) == Some(&b':')
+        })
+}
+
+// Splits a file of FIN messages into the messages, each starts with its basic header
+// block `{1:`. Anything before the first message is kept as a message of its own so
+// that it is reported as invalid rather than dropped
+pub fn split_messages(data: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = data.match_indices("{1:").map(|(i, _)| i).collect();
+
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, start)| &data[*start..starts.get(i + 1).copied().unwrap_or(data.len())])
+        .filter(|x| !x.trim().is_empty())
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::ParseOptions;
+
+    #[test]
+    fn test_split_messages() {
+        let first = include_str!("./test_messages/basic_test_1.txt");
+        let second = include_str!("./test_messages/basic_test_4.txt");
+        let data = format!("\n{first}\n{second}");
+
+        let messages = split_messages(&data);
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].trim(), first.trim());
+        assert_eq!(messages[1].trim(), second.trim());
+        assert_eq!(split_messages("garbage{1:F01"), ["garbage", "{1:F01"]);
+        assert!(split_messages(" \n").is_empty());
+    }
 
     #[test]
     #[should_panic(expected = "Application Id is either missing or the value 'T' is not valid")]
@@ -354,6 +954,224 @@ mod tests {
         Application::new("O537ASNBNL21XXXXN").unwrap();
     }
 
+    #[test]
+    fn test_block_text_transactions() -> Result<()> {
+        let text = Text::new(
+            ":20:3996-11-11111111
+:25:DABADKKK/111111-11111111
+:28C:00001/001
+:60F:C090924EUR54484,04
+:61:0909250925DR583,92NMSC1110030403010139//1234
+:86:11100304030101391234
+:61:0910010930DR62,60NCHGFees according//to advice
+:86:Fees according to advice
+:86:Charged on the first of the month
+:61:0909290929DR55,00NMSC0000000000000269//1234
+:62F:C090930EUR53126,94
+:64:C090930EUR53189,31
+:86:Statement closed",
+            &mut ParseContext::default(),
+        )?;
+
+        assert_eq!(text.transactions.len(), 3);
+        assert_eq!(
+            text.transactions[1].information(),
+            Some("Fees according to advice\nCharged on the first of the month".to_string())
+        );
+        assert_eq!(text.transactions[2].information(), None);
+        assert_eq!(
+            text.transactions[0].tag_86,
+            vec![InformationToAccountOwner::new("11100304030101391234")]
+        );
+        assert_eq!(text.transactions[1].tag_61.amount, 62.6);
+        assert_eq!(
+            text.transactions[1].tag_86,
+            vec![
+                InformationToAccountOwner::new("Fees according to advice"),
+                InformationToAccountOwner::new("Charged on the first of the month")
+            ]
+        );
+        assert!(text.transactions[2].tag_86.is_empty());
+        assert_eq!(
+            text.tag_86,
+            vec![InformationToAccountOwner::new("Statement closed")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_text_field_lines() -> Result<()> {
+        let fields = Field::tokenize(
+            "\r\n:61:0909250925DR583,92NMSC1110030403010139//1234\r\nSUPPLEMENTARY\r\n:86:11100304030101391234\r\nBeneficiary name\r\n:62F:C090930EUR53126,94\r\n",
+        )?;
+
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[0].tag, "61");
+        assert_eq!(
+            fields[0].lines,
+            vec![
+                "0909250925DR583,92NMSC1110030403010139//1234",
+                "SUPPLEMENTARY"
+            ]
+        );
+        assert_eq!(fields[1].tag, "86");
+        assert_eq!(fields[1].value, "11100304030101391234\r\nBeneficiary name");
+        assert_eq!(
+            fields[1].lines,
+            vec!["11100304030101391234", "Beneficiary name"]
+        );
+        assert_eq!(fields[2].tag, "62F");
+        assert_eq!(fields[2].value, "C090930EUR53126,94");
+        assert_eq!(fields[2].lines, vec!["C090930EUR53126,94"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_text_tokenize_tag_lookalikes() -> Result<()> {
+        // only a line that starts with a complete tag starts a field
+        let fields = Field::tokenize(
+            "  \t:20:REF\n:86:see :25: below\n:8:short\n:86X no colon\r\n\t:28C:1/1",
+        )?;
+
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[0].tag, "20");
+        assert_eq!(fields[1].value, "see :25: below\n:8:short\n:86X no colon");
+        assert_eq!(fields[2].tag, "28C");
+        assert_eq!(fields[2].value, "1/1");
+
+        assert!(Field::tokenize("data\n:20:REF").is_err());
+        assert!(Field::tokenize("")?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_text_tokenize_without_indented_tags() -> Result<()> {
+        let profile = BankProfile {
+            indented_tags: false,
+            ..BankProfile::default()
+        };
+        let fields = Field::tokenize_with(":20:REF\n:86:Invoice\n  :28C:1/1", &profile)?;
+
+        // the indented line carries on the :86:
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[1].value, "Invoice\n  :28C:1/1");
+
+        assert!(Field::tokenize_with(" :20:REF\n:25:NL20INGB0001234567", &profile).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_blocks() -> Result<()> {
+        let blocks = split_blocks("{1:F01}{2:O940}{3:{108:REF}}{4:\r\n:20:X\r\n-}\r\n")?;
+
+        assert_eq!(
+            blocks,
+            [
+                (1, "F01"),
+                (2, "O940"),
+                (3, "{108:REF}"),
+                (4, "\r\n:20:X\r\n")
+            ]
+        );
+        assert_eq!(
+            split_blocks("{1:F01}{4:\n-}{2:O940}")
+                .unwrap_err()
+                .to_string(),
+            "block 2 is out of order"
+        );
+        assert!(split_blocks("no blocks").is_err());
+        assert!(split_blocks("{1:F01}{4:\n:20:X\n}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_text_multi_line_information() -> Result<()> {
+        let text = Text::new(
+            ":20:3996-11-11111111
+:25:DABADKKK/111111-11111111
+:28C:00001/001
+:60F:C090924EUR54484,04
+:61:0909300930DR390,40NMSC0000000000754003//1234
+:86:00000000007540031234
+Beneficiary name
+Beneficiary name
+:62F:C090930EUR53126,94
+",
+            &mut ParseContext::default(),
+        )?;
+
+        let information = &text.transactions[0].tag_86[0];
+
+        assert_eq!(
+            information.information_to_account_owner,
+            "00000000007540031234\nBeneficiary name\nBeneficiary name"
+        );
+        assert_eq!(
+            information.lines,
+            vec![
+                "00000000007540031234",
+                "Beneficiary name",
+                "Beneficiary name"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_text_funds_code() -> Result<()> {
+        let text = Text::new(
+            ":20:3996-11-11111111
+:25:DABADKKK/111111-11111111
+:28C:00001/001
+:60F:C090924USD54484,04
+:61:0909250925DD583,92NMSC1110030403010139//1234
+:62F:C090930USD53900,12",
+            &mut ParseContext::default(),
+        )?;
+
+        assert_eq!(text.transactions[0].tag_61.funds_code, Some('D'));
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "funds code 'D' does not match the statement currency 'EUR'")]
+    fn test_block_text_funds_code_currency_mismatch() {
+        Text::new(
+            ":20:3996-11-11111111
+:25:DABADKKK/111111-11111111
+:28C:00001/001
+:60F:C090924EUR54484,04
+:61:0909250925DD583,92NMSC1110030403010139//1234
+:62F:C090930EUR53900,12",
+            &mut ParseContext::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_block_text_funds_code_currency_mismatch_lenient() -> Result<()> {
+        let mut context = ParseContext::with_options(ParseOptions {
+            strict: false,
+            ..ParseOptions::default()
+        });
+        let text = Text::new(
+            ":20:3996-11-11111111
+:25:DABADKKK/111111-11111111
+:28C:00001/001
+:60F:C090924EUR54484,04
+:61:0909250925DD583,92NMSC1110030403010139//1234
+:62F:C090930EUR53900,12",
+            &mut context,
+        )?;
+
+        assert_eq!(text.transactions[0].tag_61.funds_code, Some('D'));
+        assert_eq!(
+            context.warnings[0].message,
+            "funds code 'D' does not match the statement currency 'EUR'"
+        );
+        Ok(())
+    }
+
     #[test]
     #[should_panic(expected = "unexpected block key `69M` in Basic block")]
     fn test_block_text_wrong_tag() {
@@ -367,6 +1185,7 @@ mod tests {
                        :86:Fees according to advice
                        :62M:C090930EUR53126,94
                        :64:C090930EUR53189,31",
+            &mut ParseContext::default(),
         )
         .unwrap();
     }
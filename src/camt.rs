@@ -0,0 +1,1023 @@
+use crate::mt940::MT940;
+use crate::statement::{Entry, EntryStatus, Statement, StatementBalance, StatementSource};
+use crate::utils::{
+    is_iban, Balance, BalanceType, CreditDebit, TransactionType, TransactionTypeMarker,
+};
+use crate::xml::{
+    amount, amount_from_iso, currency_from_code, date_from_iso, date_time_from_iso, element, end,
+    format_date_time, start,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use eyre::{eyre, Result};
+use quick_xml::events::{BytesDecl, BytesStart, Event};
+use quick_xml::{Reader, Writer};
+use std::io::Cursor;
+
+// https://www.iso20022.org/iso-20022-message-definitions?search=camt.053
+// https://www.swift.com/standards/iso-20022/iso-20022-financial-institutions-focus-payments-instructions
+
+pub const CAMT_053_NAMESPACE: &str = "urn:iso:std:iso:20022:tech:xsd:camt.053.001.08";
+
+// Converts an MT940 into a camt.053.001.08 `BkToCstmrStmt` document.
+// The group header needs a creation time which MT940 does not carry, so it is passed in
+pub fn camt053_from_mt940(mt940: &MT940, creation_date_time: NaiveDateTime) -> Result<String> {
+    camt053_from_statement(&Statement::from(mt940), creation_date_time)
+}
+
+pub fn camt053_from_statement(
+    statement: &Statement,
+    creation_date_time: NaiveDateTime,
+) -> Result<String> {
+    let currency = statement.currency.code();
+    let reference = statement.reference.as_str();
+
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer.write_event(Event::Start(
+        BytesStart::new("Document").with_attributes([("xmlns", CAMT_053_NAMESPACE)]),
+    ))?;
+    start(&mut writer, "BkToCstmrStmt")?;
+
+    start(&mut writer, "GrpHdr")?;
+    element(&mut writer, "MsgId", reference)?;
+    element(
+        &mut writer,
+        "CreDtTm",
+        &format_date_time(creation_date_time),
+    )?;
+    end(&mut writer, "GrpHdr")?;
+
+    start(&mut writer, "Stmt")?;
+    element(&mut writer, "Id", reference)?;
+
+    if let Some(sequence_number) = statement.sequence_number {
+        let last_page = !matches!(
+            &statement.closing_balance,
+            Some(x) if x.balance_type == BalanceType::Intermediary
+        );
+
+        start(&mut writer, "StmtPgntn")?;
+        element(&mut writer, "PgNb", &sequence_number.to_string())?;
+        element(&mut writer, "LastPgInd", &last_page.to_string())?;
+        end(&mut writer, "StmtPgntn")?;
+    }
+
+    if let Some(statement_number) = statement.statement_number {
+        element(&mut writer, "ElctrncSeqNb", &statement_number.to_string())?;
+    }
+
+    start(&mut writer, "Acct")?;
+    start(&mut writer, "Id")?;
+    let account = statement.account.as_str();
+    if is_iban(account) {
+        element(&mut writer, "IBAN", account)?;
+    } else {
+        // an :25: is up to 35 characters, the identification of another scheme 34
+        if account.chars().count() > 34 {
+            return Err(eyre!(
+                "account '{account}' is longer than the 34 characters of a camt.053 account"
+            ));
+        }
+
+        start(&mut writer, "Othr")?;
+        element(&mut writer, "Id", account)?;
+        end(&mut writer, "Othr")?;
+    }
+    end(&mut writer, "Id")?;
+    element(&mut writer, "Ccy", currency)?;
+    end(&mut writer, "Acct")?;
+
+    if let Some(opening_balance) = &statement.opening_balance {
+        let code = match opening_balance.balance_type {
+            BalanceType::Final => "OPBD",
+            BalanceType::Intermediary => "ITBD",
+        };
+        balance(&mut writer, code, &opening_balance.balance_data)?;
+    }
+
+    if let Some(closing_balance) = &statement.closing_balance {
+        let code = match closing_balance.balance_type {
+            BalanceType::Final => "CLBD",
+            BalanceType::Intermediary => "ITBD",
+        };
+        balance(&mut writer, code, &closing_balance.balance_data)?;
+    }
+
+    if let Some(closing_available_balance) = &statement.closing_available_balance {
+        balance(&mut writer, "CLAV", closing_available_balance)?;
+    }
+
+    for forward_available_balance in &statement.forward_available_balances {
+        balance(&mut writer, "FWAV", forward_available_balance)?;
+    }
+
+    for statement_entry in &statement.entries {
+        entry(&mut writer, currency, statement_entry)?;
+    }
+
+    if let Some(information) = &statement.information {
+        element(&mut writer, "AddtlStmtInf", information)?;
+    }
+
+    end(&mut writer, "Stmt")?;
+    end(&mut writer, "BkToCstmrStmt")?;
+    end(&mut writer, "Document")?;
+
+    Ok(String::from_utf8(writer.into_inner().into_inner())?)
+}
+
+fn balance(writer: &mut Writer<Cursor<Vec<u8>>>, code: &str, balance: &Balance) -> Result<()> {
+    start(writer, "Bal")?;
+    start(writer, "Tp")?;
+    start(writer, "CdOrPrtry")?;
+    element(writer, "Cd", code)?;
+    end(writer, "CdOrPrtry")?;
+    end(writer, "Tp")?;
+    amount(writer, "Amt", balance.currency.code(), balance.amount)?;
+    element(
+        writer,
+        "CdtDbtInd",
+        credit_debit_indicator(&balance.credit_or_debit),
+    )?;
+    date(writer, "Dt", balance.date)?;
+    end(writer, "Bal")?;
+
+    Ok(())
+}
+
+fn entry(writer: &mut Writer<Cursor<Vec<u8>>>, currency: &str, entry: &Entry) -> Result<()> {
+    start(writer, "Ntry")?;
+    amount(writer, "Amt", currency, entry.amount)?;
+    element(
+        writer,
+        "CdtDbtInd",
+        credit_debit_indicator(&entry.debit_or_credit),
+    )?;
+
+    if matches!(
+        entry.debit_or_credit,
+        CreditDebit::CreditReversal | CreditDebit::DebitReversal
+    ) {
+        element(writer, "RvslInd", "true")?;
+    }
+
+    start(writer, "Sts")?;
+    element(writer, "Cd", &entry.status.value())?;
+    end(writer, "Sts")?;
+    date(writer, "BookgDt", entry.entry_date)?;
+    date(writer, "ValDt", entry.value_date)?;
+
+    if let Some(reference) = &entry.account_servicing_institution_reference {
+        element(writer, "AcctSvcrRef", reference)?;
+    }
+
+    start(writer, "BkTxCd")?;
+    if let Some([domain, family, sub_family]) = entry
+        .bank_transaction_code
+        .as_ref()
+        .and_then(|x| <[&str; 3]>::try_from(x.split('/').collect::<Vec<_>>()).ok())
+    {
+        start(writer, "Domn")?;
+        element(writer, "Cd", domain)?;
+        start(writer, "Fmly")?;
+        element(writer, "Cd", family)?;
+        element(writer, "SubFmlyCd", sub_family)?;
+        end(writer, "Fmly")?;
+        end(writer, "Domn")?;
+    }
+    start(writer, "Prtry")?;
+    element(writer, "Cd", &entry.transaction_type_code())?;
+    element(writer, "Issr", "SWIFT")?;
+    end(writer, "Prtry")?;
+    end(writer, "BkTxCd")?;
+
+    start(writer, "NtryDtls")?;
+    start(writer, "TxDtls")?;
+    start(writer, "Refs")?;
+    element(writer, "EndToEndId", &entry.account_owner_reference)?;
+    end(writer, "Refs")?;
+    if let Some(supplementary_details) = &entry.supplementary_details {
+        element(writer, "AddtlTxInf", supplementary_details)?;
+    }
+    end(writer, "TxDtls")?;
+    end(writer, "NtryDtls")?;
+
+    if let Some(information) = &entry.information {
+        element(writer, "AddtlNtryInf", information)?;
+    }
+
+    end(writer, "Ntry")?;
+
+    Ok(())
+}
+
+fn date(writer: &mut Writer<Cursor<Vec<u8>>>, name: &str, date: NaiveDate) -> Result<()> {
+    start(writer, name)?;
+    element(writer, "Dt", &date.format("%Y-%m-%d").to_string())?;
+    end(writer, name)
+}
+
+fn credit_debit_indicator(credit_or_debit: &CreditDebit) -> &'static str {
+    // a reversal of a credit is booked as a debit and vice versa
+    match credit_or_debit {
+        CreditDebit::Credit | CreditDebit::DebitReversal => "CRDT",
+        CreditDebit::Debit | CreditDebit::CreditReversal => "DBIT",
+    }
+}
+
+// Reads every `Stmt` of a camt.053 `BkToCstmrStmt` or every `Rpt` of a camt.052
+// `BkToCstmrAcctRpt` document. Only elements that exist in all versions are read,
+// so the version (and any namespace prefix) of the document does not matter
+pub fn statements_from_camt(xml: &str) -> Result<Vec<Statement>> {
+    let mut reader = Reader::from_str(xml);
+
+    let mut path: Vec<String> = vec![];
+    let mut text = String::new();
+    let mut leaf = false;
+    let mut amount_currency = None;
+
+    let mut source = None;
+    let mut creation_date_time = None;
+    let mut statements = vec![];
+    let mut statement: Option<CamtStatement> = None;
+    let mut balance: Option<CamtBalance> = None;
+    let mut entry: Option<CamtEntry> = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => {
+                let name = String::from_utf8(e.local_name().as_ref().to_vec())?;
+
+                match (path.last().map(String::as_str), name.as_str()) {
+                    (_, "BkToCstmrStmt") => source = Some(StatementSource::Camt053),
+                    (_, "BkToCstmrAcctRpt") => source = Some(StatementSource::Camt052),
+                    (Some("BkToCstmrStmt"), "Stmt") | (Some("BkToCstmrAcctRpt"), "Rpt") => {
+                        if statement.is_some() {
+                            return Err(eyre!("{name} is nested in another statement"));
+                        }
+                        statement = Some(CamtStatement::default());
+                    }
+                    (_, "Bal" | "Ntry") if balance.is_some() || entry.is_some() => {
+                        return Err(eyre!("{name} is nested in a balance or an entry"));
+                    }
+                    (_, "Bal") if statement.is_some() => balance = Some(CamtBalance::default()),
+                    (_, "Ntry") if statement.is_some() => entry = Some(CamtEntry::default()),
+                    (_, "Amt") => {
+                        amount_currency = e
+                            .try_get_attribute("Ccy")?
+                            .map(|x| x.unescape_value().map(|x| x.to_string()))
+                            .transpose()?;
+                    }
+                    _ => {}
+                }
+
+                path.push(name);
+                text.clear();
+                leaf = true;
+            }
+            Event::Text(e) => text.push_str(&e.unescape()?),
+            Event::CData(e) => text.push_str(std::str::from_utf8(&e.into_inner())?),
+            Event::End(_) => {
+                let value = std::mem::take(&mut text);
+                let parent = path.iter().rev().nth(1).map(String::as_str);
+
+                match (parent, path.last().map(String::as_str)) {
+                    (_, Some("Ntry")) if entry.is_some() => {
+                        if let (Some(finished), Some(current)) = (entry.take(), &mut statement) {
+                            current.entries.push(finished.build()?);
+                        }
+                    }
+                    (_, Some("Bal")) if balance.is_some() => {
+                        if let (Some(finished), Some(current)) = (balance.take(), &mut statement) {
+                            current.balances.push(finished.build()?);
+                        }
+                    }
+                    (Some("BkToCstmrStmt"), Some("Stmt"))
+                    | (Some("BkToCstmrAcctRpt"), Some("Rpt")) => {
+                        let (Some(finished), Some(source)) = (statement.take(), source) else {
+                            return Err(eyre!("statement is closed without being opened"));
+                        };
+                        statements.push(finished.build(source, creation_date_time)?);
+                    }
+                    // only the text of an element without children is a value
+                    _ if !leaf => {}
+                    _ => {
+                        if let (Some(current), Some(relative)) =
+                            (&mut entry, relative_path(&path, "Ntry"))
+                        {
+                            current.read(&relative, value)?;
+                        } else if let (Some(current), Some(relative)) =
+                            (&mut balance, relative_path(&path, "Bal"))
+                        {
+                            current.read(&relative, value, &amount_currency)?;
+                        } else if let (Some(current), Some(relative)) = (
+                            &mut statement,
+                            relative_path(&path, "Stmt").or_else(|| relative_path(&path, "Rpt")),
+                        ) {
+                            current.read(&relative, value)?;
+                        } else if relative_path(&path, "GrpHdr").as_deref() == Some("CreDtTm") {
+                            creation_date_time = Some(date_time_from_iso(&value)?);
+                        }
+                    }
+                }
+
+                path.pop();
+                leaf = false;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if source.is_none() {
+        return Err(eyre!(
+            "document is neither a camt.053 statement nor a camt.052 report"
+        ));
+    }
+
+    Ok(statements)
+}
+
+// the path of the current element below the innermost `parent` e.g. `BookgDt/Dt` in `Ntry`
+fn relative_path(path: &[String], parent: &str) -> Option<String> {
+    let index = path.iter().rposition(|x| x == parent)?;
+
+    Some(path[index + 1..].join("/"))
+}
+
+#[derive(Default)]
+struct CamtStatement {
+    reference: Option<String>,
+    account: Option<String>,
+    currency: Option<String>,
+    statement_number: Option<u32>,
+    sequence_number: Option<u32>,
+    creation_date_time: Option<NaiveDateTime>,
+    balances: Vec<(String, Balance)>,
+    entries: Vec<Entry>,
+    information: Vec<String>,
+}
+
+impl CamtStatement {
+    fn read(&mut self, path: &str, value: String) -> Result<()> {
+        match path {
+            "Id" => self.reference = Some(value),
+            "CreDtTm" => self.creation_date_time = Some(date_time_from_iso(&value)?),
+            "StmtPgntn/PgNb" | "RptPgntn/PgNb" => {
+                self.sequence_number = Some(number_from_iso(&value)?);
+            }
+            "ElctrncSeqNb" => self.statement_number = Some(number_from_iso(&value)?),
+            "Acct/Id/IBAN" | "Acct/Id/Othr/Id" => self.account = Some(value),
+            "Acct/Ccy" => self.currency = Some(value),
+            "AddtlStmtInf" | "AddtlRptInf" => self.information.push(value),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn build(
+        self,
+        source: StatementSource,
+        creation_date_time: Option<NaiveDateTime>,
+    ) -> Result<Statement> {
+        let reference = self
+            .reference
+            .ok_or_else(|| eyre!("statement is missing the identification"))?;
+        let account = self
+            .account
+            .ok_or_else(|| eyre!("statement '{reference}' is missing the account"))?;
+
+        // the account currency is optional, the balances are always in the account currency
+        let currency = match self.currency {
+            Some(currency) => currency_from_code(&currency)?,
+            None => self
+                .balances
+                .first()
+                .map(|(_, balance)| balance.currency)
+                .ok_or_else(|| eyre!("statement '{reference}' is missing the currency"))?,
+        };
+
+        let mut opening_balance = None;
+        let mut closing_balance = None;
+        let mut closing_available_balance = None;
+        let mut forward_available_balances = vec![];
+
+        for (code, balance_data) in self.balances {
+            match code.as_str() {
+                "OPBD" | "PRCD" => {
+                    opening_balance = Some(StatementBalance {
+                        balance_type: BalanceType::Final,
+                        balance_data,
+                    });
+                }
+                "CLBD" => {
+                    closing_balance = Some(StatementBalance {
+                        balance_type: BalanceType::Final,
+                        balance_data,
+                    });
+                }
+                // an interim booked balance opens the page when it comes first and closes it otherwise
+                "ITBD" if opening_balance.is_none() => {
+                    opening_balance = Some(StatementBalance {
+                        balance_type: BalanceType::Intermediary,
+                        balance_data,
+                    });
+                }
+                "ITBD" => {
+                    closing_balance = Some(StatementBalance {
+                        balance_type: BalanceType::Intermediary,
+                        balance_data,
+                    });
+                }
+                "CLAV" | "ITAV" => closing_available_balance = Some(balance_data),
+                "FWAV" => forward_available_balances.push(balance_data),
+                _ => {}
+            }
+        }
+
+        Ok(Statement {
+            source,
+            reference,
+            account,
+            currency,
+            statement_number: self.statement_number,
+            sequence_number: self.sequence_number,
+            creation_date_time: self.creation_date_time.or(creation_date_time),
+            opening_balance,
+            closing_balance,
+            closing_available_balance,
+            forward_available_balances,
+            entries: self.entries,
+            information: if self.information.is_empty() {
+                None
+            } else {
+                Some(self.information.join("\n"))
+            },
+        })
+    }
+}
+
+#[derive(Default)]
+struct CamtBalance {
+    code: Option<String>,
+    amount: Option<f64>,
+    currency: Option<String>,
+    credit_debit: Option<String>,
+    date: Option<NaiveDate>,
+}
+
+impl CamtBalance {
+    fn read(&mut self, path: &str, value: String, currency: &Option<String>) -> Result<()> {
+        match path {
+            "Tp/CdOrPrtry/Cd" => self.code = Some(value),
+            "Amt" => {
+                self.amount = Some(amount_from_iso(&value)?);
+                self.currency = currency.clone();
+            }
+            "CdtDbtInd" => self.credit_debit = Some(value),
+            "Dt/Dt" | "Dt/DtTm" => self.date = Some(date_from_iso(&value)?),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn build(self) -> Result<(String, Balance)> {
+        let code = self
+            .code
+            .ok_or_else(|| eyre!("balance is missing the type code"))?;
+
+        let balance = Balance {
+            credit_or_debit: credit_debit_from_indicator(self.credit_debit.as_deref(), false)?,
+            date: self
+                .date
+                .ok_or_else(|| eyre!("balance '{code}' is missing the date"))?,
+            currency: currency_from_code(self.currency.as_deref().unwrap_or_default())?,
+            amount: self
+                .amount
+                .ok_or_else(|| eyre!("balance '{code}' is missing the amount"))?,
+        };
+
+        Ok((code, balance))
+    }
+}
+
+#[derive(Default)]
+struct CamtEntry {
+    amount: Option<f64>,
+    credit_debit: Option<String>,
+    reversal: bool,
+    status: Option<String>,
+    booking_date: Option<NaiveDate>,
+    value_date: Option<NaiveDate>,
+    account_servicer_reference: Option<String>,
+    domain: Vec<String>,
+    proprietary_code: Option<String>,
+    proprietary_issuer: Option<String>,
+    end_to_end_id: Option<String>,
+    additional_transaction_information: Option<String>,
+    additional_entry_information: Option<String>,
+}
+
+impl CamtEntry {
+    fn read(&mut self, path: &str, value: String) -> Result<()> {
+        match path {
+            "Amt" => self.amount = Some(amount_from_iso(&value)?),
+            "CdtDbtInd" => self.credit_debit = Some(value),
+            "RvslInd" => self.reversal = value == "true" || value == "1",
+            // a code of its own up to version 2, a choice of code or proprietary since
+            "Sts" | "Sts/Cd" => self.status = Some(value),
+            "BookgDt/Dt" | "BookgDt/DtTm" => self.booking_date = Some(date_from_iso(&value)?),
+            "ValDt/Dt" | "ValDt/DtTm" => self.value_date = Some(date_from_iso(&value)?),
+            "AcctSvcrRef" => self.account_servicer_reference = Some(value),
+            "BkTxCd/Domn/Cd" | "BkTxCd/Domn/Fmly/Cd" | "BkTxCd/Domn/Fmly/SubFmlyCd" => {
+                self.domain.push(value);
+            }
+            "BkTxCd/Prtry/Cd" => self.proprietary_code = Some(value),
+            "BkTxCd/Prtry/Issr" => self.proprietary_issuer = Some(value),
+            // batch bookings carry a `TxDtls` per transaction, only the first one is kept
+            "NtryDtls/TxDtls/Refs/EndToEndId" => {
+                self.end_to_end_id.get_or_insert(value);
+            }
+            "NtryDtls/TxDtls/AddtlTxInf" => {
+                self.additional_transaction_information.get_or_insert(value);
+            }
+            "AddtlNtryInf" => self.additional_entry_information = Some(value),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn build(self) -> Result<Entry> {
+        let amount = self
+            .amount
+            .ok_or_else(|| eyre!("entry is missing the amount"))?;
+
+        let value_date = self.value_date.or(self.booking_date);
+        let entry_date = self.booking_date.or(self.value_date);
+        let (value_date, entry_date) = value_date.zip(entry_date).ok_or_else(|| {
+            eyre!("entry of {amount} is missing both the booking and the value date")
+        })?;
+
+        // the SWIFT transaction type is only known when it was given as a proprietary code,
+        // as we do ourselves when writing camt.053, anything else is miscellaneous
+        let swift_code = match (&self.proprietary_code, self.proprietary_issuer.as_deref()) {
+            (Some(code), Some("SWIFT")) => swift_transaction_type(code),
+            _ => None,
+        };
+
+        let bank_transaction_code = if self.domain.is_empty() {
+            self.proprietary_code.filter(|_| swift_code.is_none())
+        } else {
+            Some(self.domain.join("/"))
+        };
+
+        let (transaction_type_marker, transaction_type, identification_code) = swift_code
+            .unwrap_or((
+                TransactionTypeMarker::NonSwiftTransfer,
+                Some(TransactionType::MSC),
+                "MSC".to_string(),
+            ));
+
+        Ok(Entry {
+            status: EntryStatus::try_from(self.status.as_deref().unwrap_or("BOOK"))?,
+            value_date,
+            entry_date,
+            debit_or_credit: credit_debit_from_indicator(
+                self.credit_debit.as_deref(),
+                self.reversal,
+            )?,
+            funds_code: None,
+            amount,
+            transaction_type_marker,
+            transaction_type,
+            identification_code,
+            bank_transaction_code,
+            account_owner_reference: self.end_to_end_id.unwrap_or_else(|| "NONREF".to_string()),
+            account_servicing_institution_reference: self.account_servicer_reference,
+            supplementary_details: self.additional_transaction_information,
+            information: self.additional_entry_information,
+        })
+    }
+}
+
+// a SWIFT transaction type such as `NMSC` or `S103`
+fn swift_transaction_type(
+    code: &str,
+) -> Option<(TransactionTypeMarker, Option<TransactionType>, String)> {
+    let transaction_type_marker = TransactionTypeMarker::try_from(code.get(..1)?).ok()?;
+    let identification_code = code.get(1..).filter(|x| x.len() == 3)?;
+
+    let transaction_type = if transaction_type_marker == TransactionTypeMarker::SwiftTransfer {
+        None
+    } else {
+        Some(TransactionType::try_from(identification_code).ok()?)
+    };
+
+    Some((
+        transaction_type_marker,
+        transaction_type,
+        identification_code.to_string(),
+    ))
+}
+
+fn credit_debit_from_indicator(indicator: Option<&str>, reversal: bool) -> Result<CreditDebit> {
+    match (indicator, reversal) {
+        (Some("CRDT"), false) => Ok(CreditDebit::Credit),
+        (Some("DBIT"), false) => Ok(CreditDebit::Debit),
+        (Some("CRDT"), true) => Ok(CreditDebit::DebitReversal),
+        (Some("DBIT"), true) => Ok(CreditDebit::CreditReversal),
+        (indicator, _) => Err(eyre!(
+            "Credit Debit Indicator is either missing or the value '{}' is not valid",
+            indicator.unwrap_or_default()
+        )),
+    }
+}
+
+fn number_from_iso(number: &str) -> Result<u32> {
+    number
+        .parse::<u32>()
+        .map_err(|_| eyre!("Invalid number provided: '{number}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iso_currency::Currency;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    // xmllint of libxml2 validates the documents, without it they are not checked
+    // against the schema
+    fn xmllint_installed() -> bool {
+        Command::new("xmllint")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok()
+    }
+
+    // validates the document against the vendored camt.053.001.08 schema, or the one
+    // `CAMT053_XSD` points to, e.g. the full schema from iso20022.org
+    fn validate(xml: &str) -> Result<()> {
+        if !xmllint_installed() {
+            eprintln!("xmllint is not installed, the schema validation is skipped");
+            return Ok(());
+        }
+
+        let schema = std::env::var("CAMT053_XSD").unwrap_or_else(|_| {
+            concat!(env!("CARGO_MANIFEST_DIR"), "/schemas/camt.053.001.08.xsd").to_string()
+        });
+        let mut xmllint = Command::new("xmllint")
+            .args(["--noout", "--schema", &schema, "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| eyre!("xmllint is needed to validate against the schema: {e}"))?;
+
+        xmllint
+            .stdin
+            .take()
+            .ok_or_else(|| eyre!("xmllint has no stdin"))?
+            .write_all(xml.as_bytes())?;
+
+        let output = xmllint.wait_with_output()?;
+        if !output.status.success() {
+            return Err(eyre!("{}", String::from_utf8_lossy(&output.stderr)));
+        }
+        Ok(())
+    }
+
+    fn creation_date_time() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2009, 10, 1)
+            .unwrap()
+            .and_hms_opt(8, 30, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_camt053_from_mt940() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let xml = camt053_from_mt940(&mt940, creation_date_time())?;
+
+        validate(&xml)?;
+        assert!(xml.contains(CAMT_053_NAMESPACE));
+        assert!(xml.contains("<MsgId>3996-11-11111111</MsgId>"));
+        assert!(xml.contains("<CreDtTm>2009-10-01T08:30:00</CreDtTm>"));
+        assert!(xml.contains("<Id>DABADKKK/111111-11111111</Id>"));
+        assert!(!xml.contains("<IBAN>"));
+        assert!(xml.contains("<Cd>OPBD</Cd>"));
+        assert!(xml.contains("<Cd>CLBD</Cd>"));
+        assert!(xml.contains("<Cd>CLAV</Cd>"));
+        assert!(xml.contains("<Amt Ccy=\"EUR\">54484.04</Amt>"));
+        assert_eq!(xml.matches("<Ntry>").count(), 6);
+        assert!(xml.contains("<Cd>NMSC</Cd>"));
+        assert!(xml.contains("<AcctSvcrRef>to advice</AcctSvcrRef>"));
+        assert!(xml.contains("<EndToEndId>Fees according</EndToEndId>"));
+        assert!(xml.contains(
+            "<AddtlNtryInf>00000000007540031234\nBeneficiary name\nBeneficiary name</AddtlNtryInf>"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_camt053_schema_rejects_invalid() -> Result<()> {
+        if !xmllint_installed() {
+            return Ok(());
+        }
+
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let xml = camt053_from_mt940(&mt940, creation_date_time())?;
+
+        let unknown = xml
+            .replacen("<Sts>", "<Stz>", 1)
+            .replacen("</Sts>", "</Stz>", 1);
+        // the value date before the booking date
+        let out_of_order = xml
+            .replacen("BookgDt", "Dt0", 2)
+            .replacen("ValDt", "BookgDt", 2)
+            .replacen("Dt0", "ValDt", 2);
+        let too_long = xml.replacen("<MsgId>", &format!("<MsgId>{}", "X".repeat(35)), 1);
+
+        for invalid in [unknown, out_of_order, too_long] {
+            assert!(validate(&invalid).is_err());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_camt053_balances() -> Result<()> {
+        let mt940 = MT940::new(
+            "{1:F01ASNBNL21XXXX0000000000}{2:O940ASNBNL21XXXXN}{3:}{4:
+:20:0000000000
+:25:NL81ASNB9999999999
+:28C:3/2
+:60M:C200103EUR379,29
+:61:2001030103RC12,50NTRFNONREF
+:62M:D200103EUR12,50
+:64:C200103EUR379,29
+:65:C200104EUR379,29
+:65:C200105EUR379,29
+:86:Intermediate statement
+-}{5:}",
+        )?;
+        let xml = camt053_from_mt940(&mt940, creation_date_time())?;
+
+        validate(&xml)?;
+        assert!(xml.contains("<IBAN>NL81ASNB9999999999</IBAN>"));
+        assert!(xml.contains("<PgNb>2</PgNb>"));
+        assert!(xml.contains("<LastPgInd>false</LastPgInd>"));
+        assert!(xml.contains("<ElctrncSeqNb>3</ElctrncSeqNb>"));
+        assert_eq!(xml.matches("<Cd>ITBD</Cd>").count(), 2);
+        assert_eq!(xml.matches("<Cd>FWAV</Cd>").count(), 2);
+        assert!(xml.contains("<CdtDbtInd>DBIT</CdtDbtInd>"));
+        assert!(xml.contains("<RvslInd>true</RvslInd>"));
+        assert!(xml.contains("<AddtlStmtInf>Intermediate statement</AddtlStmtInf>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_camt053_escapes_text() -> Result<()> {
+        let mt940 = MT940::new(
+            "{1:F01ASNBNL21XXXX0000000000}{2:O940ASNBNL21XXXXN}{3:}{4:
+:20:0000000000
+:25:NL81ASNB9999999999
+:28C:3/1
+:60F:C200103EUR379,29
+:61:2001030103D12,50NTRFNONREF
+:86:Smith & Sons <Ltd>
+:62F:C200103EUR366,79
+-}{5:}",
+        )?;
+        let xml = camt053_from_mt940(&mt940, creation_date_time())?;
+
+        validate(&xml)?;
+        assert!(xml.contains("<AddtlNtryInf>Smith &amp; Sons &lt;Ltd&gt;</AddtlNtryInf>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_camt053_account_length() -> Result<()> {
+        let mut statement = Statement::from(&MT940::new(include_str!(
+            "./test_messages/basic_test_4.txt"
+        ))?);
+
+        // an :25: of 34 characters fits, one of 35 does not
+        statement.account = "X".repeat(34);
+        validate(&camt053_from_statement(&statement, creation_date_time())?)?;
+
+        statement.account = "X".repeat(35);
+        let error = camt053_from_statement(&statement, creation_date_time()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "account '{}' is longer than the 34 characters of a camt.053 account",
+                statement.account
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_camt053_round_trip() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let xml = camt053_from_mt940(&mt940, creation_date_time())?;
+
+        let statements = statements_from_camt(&xml)?;
+
+        let mut expected = Statement::from(&mt940);
+        expected.source = StatementSource::Camt053;
+        expected.creation_date_time = Some(creation_date_time());
+        // camt.053 has no funds code
+        for entry in &mut expected.entries {
+            entry.funds_code = None;
+        }
+
+        assert_eq!(statements, vec![expected]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_camt053_domain_code() -> Result<()> {
+        let mut statement = Statement::from(&MT940::new(include_str!(
+            "./test_messages/basic_test_4.txt"
+        ))?);
+        statement.entries[0].bank_transaction_code = Some("PMNT/RCDT/ESCT".to_string());
+
+        let xml = camt053_from_statement(&statement, creation_date_time())?;
+
+        validate(&xml)?;
+        assert_eq!(xml.matches("<Domn>").count(), 1);
+        assert!(xml.contains("<SubFmlyCd>ESCT</SubFmlyCd>"));
+        assert_eq!(
+            statements_from_camt(&xml)?[0].entries[0].bank_transaction_code,
+            Some("PMNT/RCDT/ESCT".to_string())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_camt052_report() -> Result<()> {
+        let statements = statements_from_camt(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<ns:Document xmlns:ns="urn:iso:std:iso:20022:tech:xsd:camt.052.001.08">
+  <ns:BkToCstmrAcctRpt>
+    <ns:GrpHdr>
+      <ns:MsgId>REPORT-1</ns:MsgId>
+      <ns:CreDtTm>2020-01-03T12:00:00+01:00</ns:CreDtTm>
+    </ns:GrpHdr>
+    <ns:Rpt>
+      <ns:Id>REPORT-1-1</ns:Id>
+      <ns:RptPgntn>
+        <ns:PgNb>2</ns:PgNb>
+        <ns:LastPgInd>false</ns:LastPgInd>
+      </ns:RptPgntn>
+      <ns:CreDtTm>2020-01-03T12:30:00.000+01:00</ns:CreDtTm>
+      <ns:Acct>
+        <ns:Id>
+          <ns:IBAN>NL81ASNB9999999999</ns:IBAN>
+        </ns:Id>
+      </ns:Acct>
+      <ns:Bal>
+        <ns:Tp><ns:CdOrPrtry><ns:Cd>ITBD</ns:Cd></ns:CdOrPrtry></ns:Tp>
+        <ns:Amt Ccy="EUR">379.29</ns:Amt>
+        <ns:CdtDbtInd>CRDT</ns:CdtDbtInd>
+        <ns:Dt><ns:DtTm>2020-01-03T08:00:00</ns:DtTm></ns:Dt>
+      </ns:Bal>
+      <ns:Bal>
+        <ns:Tp><ns:CdOrPrtry><ns:Cd>ITAV</ns:Cd></ns:CdOrPrtry></ns:Tp>
+        <ns:Amt Ccy="EUR">366.79</ns:Amt>
+        <ns:CdtDbtInd>CRDT</ns:CdtDbtInd>
+        <ns:Dt><ns:Dt>2020-01-03</ns:Dt></ns:Dt>
+      </ns:Bal>
+      <ns:Ntry>
+        <ns:Amt Ccy="EUR">12.5</ns:Amt>
+        <ns:CdtDbtInd>CRDT</ns:CdtDbtInd>
+        <ns:RvslInd>true</ns:RvslInd>
+        <ns:Sts><ns:Cd>PDNG</ns:Cd></ns:Sts>
+        <ns:BookgDt><ns:DtTm>2020-01-03T10:15:00</ns:DtTm></ns:BookgDt>
+        <ns:BkTxCd>
+          <ns:Domn>
+            <ns:Cd>PMNT</ns:Cd>
+            <ns:Fmly><ns:Cd>RCDT</ns:Cd><ns:SubFmlyCd>ESCT</ns:SubFmlyCd></ns:Fmly>
+          </ns:Domn>
+        </ns:BkTxCd>
+        <ns:NtryDtls>
+          <ns:TxDtls>
+            <ns:Refs><ns:EndToEndId>E2E-1</ns:EndToEndId></ns:Refs>
+          </ns:TxDtls>
+          <ns:TxDtls>
+            <ns:Refs><ns:EndToEndId>E2E-2</ns:EndToEndId></ns:Refs>
+          </ns:TxDtls>
+        </ns:NtryDtls>
+        <ns:AddtlNtryInf><![CDATA[Smith & Sons]]></ns:AddtlNtryInf>
+      </ns:Ntry>
+      <ns:AddtlRptInf>Intraday</ns:AddtlRptInf>
+    </ns:Rpt>
+  </ns:BkToCstmrAcctRpt>
+</ns:Document>"#,
+        )?;
+
+        assert_eq!(statements.len(), 1);
+        let statement = &statements[0];
+
+        assert_eq!(statement.source, StatementSource::Camt052);
+        assert_eq!(statement.reference, "REPORT-1-1");
+        assert_eq!(statement.account, "NL81ASNB9999999999");
+        assert_eq!(statement.currency, Currency::EUR);
+        assert_eq!(statement.statement_number, None);
+        assert_eq!(statement.sequence_number, Some(2));
+        assert_eq!(
+            statement.creation_date_time,
+            Some(
+                NaiveDate::from_ymd_opt(2020, 1, 3)
+                    .unwrap()
+                    .and_hms_opt(12, 30, 0)
+                    .unwrap()
+            )
+        );
+
+        let opening_balance = statement.opening_balance.as_ref().unwrap();
+        assert_eq!(opening_balance.balance_type, BalanceType::Intermediary);
+        assert_eq!(
+            opening_balance.balance_data.date,
+            NaiveDate::from_ymd_opt(2020, 1, 3).unwrap()
+        );
+        assert_eq!(statement.closing_balance, None);
+        assert_eq!(
+            statement
+                .closing_available_balance
+                .as_ref()
+                .map(|x| x.amount),
+            Some(366.79)
+        );
+
+        let entry = &statement.entries[0];
+        assert_eq!(entry.status, EntryStatus::Pending);
+        assert_eq!(entry.debit_or_credit, CreditDebit::DebitReversal);
+        assert_eq!(entry.amount, 12.5);
+        assert_eq!(
+            entry.entry_date,
+            NaiveDate::from_ymd_opt(2020, 1, 3).unwrap()
+        );
+        assert_eq!(
+            entry.value_date,
+            NaiveDate::from_ymd_opt(2020, 1, 3).unwrap()
+        );
+        assert_eq!(entry.transaction_type, Some(TransactionType::MSC));
+        assert_eq!(
+            entry.bank_transaction_code.as_deref(),
+            Some("PMNT/RCDT/ESCT")
+        );
+        assert_eq!(entry.account_owner_reference, "E2E-1");
+        assert_eq!(entry.information.as_deref(), Some("Smith & Sons"));
+        assert_eq!(statement.information.as_deref(), Some("Intraday"));
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "Stmt is nested in another statement")]
+    fn test_camt_nested_statement() {
+        statements_from_camt(
+            "<Document><BkToCstmrStmt><Stmt><BkToCstmrStmt><Stmt></Stmt></BkToCstmrStmt>\
+             </Stmt></BkToCstmrStmt></Document>",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Ntry is nested in a balance or an entry")]
+    fn test_camt_nested_entry() {
+        statements_from_camt(
+            "<Document><BkToCstmrStmt><Stmt><Ntry><Ntry></Ntry></Ntry></Stmt></BkToCstmrStmt>\
+             </Document>",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_camt_stray_closing_tag() {
+        for xml in [
+            "<Document><BkToCstmrStmt></Stmt></BkToCstmrStmt></Document>",
+            "<Document><BkToCstmrStmt></BkToCstmrStmt></Stmt></Document>",
+            "<Document></Document></Stmt>",
+        ] {
+            let error = statements_from_camt(xml).unwrap_err().to_string();
+            assert!(error.ends_with("found </Stmt>"), "{error}");
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "document is neither a camt.053 statement nor a camt.052 report")]
+    fn test_camt_unknown_document() {
+        statements_from_camt(
+            r#"<Document xmlns="urn:iso:std:iso:20022:tech:xsd:pacs.008.001.08"><FIToFICstmrCdtTrf/></Document>"#,
+        )
+        .unwrap();
+    }
+}
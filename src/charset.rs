@@ -0,0 +1,223 @@
+use crate::block::{split_blocks, Field};
+use crate::spec::FormatSpec;
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+
+// https://www2.swift.com/knowledgecentre/publications/us9m_20230720/?topic=con_31519.htm
+
+// The character sets of FIN. Line breaks are written as CrLf and allowed in all of
+// them, they separate the lines of a field rather than being part of its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Charset {
+    // a-z A-Z 0-9 / - ? : ( ) . , ' + space
+    X,
+    // upper case EDIFACT level A: A-Z 0-9 . , - ( ) / = ' + : ? ! " % & * < > ; space
+    Y,
+    // X and Y together with = ! " % & * < > ; { @ # _
+    Z,
+}
+
+impl TryFrom<&str> for Charset {
+    type Error = eyre::Error;
+
+    fn try_from(input: &str) -> Result<Self> {
+        match input {
+            "x" => Ok(Self::X),
+            "y" => Ok(Self::Y),
+            "z" => Ok(Self::Z),
+            _ => Err(eyre!(
+                "Charset is either missing or the value '{input}' is not valid"
+            )),
+        }
+    }
+}
+
+impl Charset {
+    pub fn value(&self) -> String {
+        match self {
+            Self::X => "x".to_string(),
+            Self::Y => "y".to_string(),
+            Self::Z => "z".to_string(),
+        }
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        if c == '\r' || c == '\n' {
+            return true;
+        }
+
+        match self {
+            Self::X => c.is_ascii_alphanumeric() || "/-?:().,'+ ".contains(c),
+            Self::Y => {
+                c.is_ascii_uppercase() || c.is_ascii_digit() || ".,-()/='+:?!\"%&*<>; ".contains(c)
+            }
+            Self::Z => c.is_ascii_alphanumeric() || "/-?:().,'+ =!\"%&*<>;{@#_".contains(c),
+        }
+    }
+}
+
+// The character set of the format of a tag, tags without a known format are held to X
+pub fn charset_for_tag(tag: &str) -> Charset {
+    FormatSpec::for_tag(tag).map_or(Charset::X, |spec| spec.charset())
+}
+
+// Checks the value of a tag against the character set of its format
+pub fn validate_field(tag: &str, value: &str) -> Result<()> {
+    let charset = charset_for_tag(tag);
+
+    match value
+        .chars()
+        .enumerate()
+        .find(|(_, c)| !charset.contains(*c))
+    {
+        Some((position, c)) => Err(eyre!(
+            "tag {tag} contains '{c}' at position {} which is not in the SWIFT {} character set",
+            position + 1,
+            charset.value().to_uppercase()
+        )),
+        None => Ok(()),
+    }
+}
+
+// Checks every tag of the text block of a FIN message, the first offending tag is
+// the error
+pub fn validate_message(message_data: &str) -> Result<()> {
+    let text = split_blocks(message_data)?
+        .into_iter()
+        .find(|(block_id, _)| *block_id == 4)
+        .ok_or_else(|| eyre!("missing text block (block 4)"))?;
+
+    for field in Field::tokenize(text.1)? {
+        validate_field(field.tag, field.value)?;
+    }
+
+    Ok(())
+}
+
+// Rewrites text into the X character set so it can be written to any field. Umlauts
+// and ß are spelled out as in German, other Latin letters lose their accents, `&`
+// becomes `+` and anything else that has no sensible replacement becomes `.`
+pub fn transliterate(text: &str) -> String {
+    let mut transliterated = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if Charset::X.contains(c) {
+            transliterated.push(c);
+            continue;
+        }
+
+        let replacement = match c {
+            'ä' | 'æ' => "ae",
+            'ö' | 'ø' | 'œ' => "oe",
+            'ü' => "ue",
+            'Ä' | 'Æ' => "Ae",
+            'Ö' | 'Ø' | 'Œ' => "Oe",
+            'Ü' => "Ue",
+            'ß' => "ss",
+            'à' | 'á' | 'â' | 'ã' | 'å' | 'ą' => "a",
+            'À' | 'Á' | 'Â' | 'Ã' | 'Å' | 'Ą' => "A",
+            'ç' | 'ć' | 'č' => "c",
+            'Ç' | 'Ć' | 'Č' => "C",
+            'è' | 'é' | 'ê' | 'ë' | 'ę' | 'ě' => "e",
+            'È' | 'É' | 'Ê' | 'Ë' | 'Ę' | 'Ě' => "E",
+            'ì' | 'í' | 'î' | 'ï' => "i",
+            'Ì' | 'Í' | 'Î' | 'Ï' => "I",
+            'ł' => "l",
+            'Ł' => "L",
+            'ñ' | 'ń' | 'ň' => "n",
+            'Ñ' | 'Ń' | 'Ň' => "N",
+            'ò' | 'ó' | 'ô' | 'õ' => "o",
+            'Ò' | 'Ó' | 'Ô' | 'Õ' => "O",
+            'ř' => "r",
+            'Ř' => "R",
+            'ś' | 'š' => "s",
+            'Ś' | 'Š' => "S",
+            'ù' | 'ú' | 'û' | 'ů' => "u",
+            'Ù' | 'Ú' | 'Û' | 'Ů' => "U",
+            'ý' | 'ÿ' => "y",
+            'Ý' | 'Ÿ' => "Y",
+            'ź' | 'ż' | 'ž' => "z",
+            'Ź' | 'Ż' | 'Ž' => "Z",
+            '&' => "+",
+            '@' => "(at)",
+            '_' | '–' | '—' => "-",
+            '"' | '‘' | '’' | '`' | '´' => "'",
+            ';' => ",",
+            '[' | '{' | '<' => "(",
+            ']' | '}' | '>' => ")",
+            '\t' => " ",
+            _ => ".",
+        };
+
+        transliterated.push_str(replacement);
+    }
+
+    transliterated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_charset_for_tag() {
+        assert_eq!(charset_for_tag("86"), Charset::X);
+        assert_eq!(charset_for_tag("32A"), Charset::X);
+        assert_eq!(charset_for_tag("77T"), Charset::Z);
+        assert_eq!(charset_for_tag("99"), Charset::X);
+    }
+
+    #[test]
+    fn test_charset_contains() {
+        assert!("abcXYZ019/-?:().,'+ \r\n"
+            .chars()
+            .all(|c| Charset::X.contains(c)));
+        assert!(!"&ü@_=;!".chars().any(|c| Charset::X.contains(c)));
+
+        assert!(Charset::Y.contains('&'));
+        assert!(!Charset::Y.contains('a'));
+
+        assert!("@#_{".chars().all(|c| Charset::Z.contains(c)));
+        assert!(!Charset::Z.contains('ü'));
+    }
+
+    #[test]
+    fn test_validate_field() -> eyre::Result<()> {
+        validate_field("86", "Interest\r\nFor your inform. IBAN no.: FI11")?;
+        // ü is not in the Z character set either
+        assert!(validate_field("77T", "/NAME/Müller & Co").is_err());
+
+        let error = validate_field("59", "/DE89370400440532013000\nMüller & Söhne").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "tag 59 contains 'ü' at position 26 which is not in the SWIFT X character set"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_message() -> eyre::Result<()> {
+        validate_message(include_str!("./test_messages/basic_test_4.txt"))?;
+        validate_message(include_str!("./test_messages/mt103_test_1.txt"))?;
+
+        let message =
+            include_str!("./test_messages/mt103_test_1.txt").replacen(":70:", ":70:R&D ", 1);
+        assert!(validate_message(&message)
+            .unwrap_err()
+            .to_string()
+            .starts_with("tag 70 contains '&'"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_transliterate() -> eyre::Result<()> {
+        assert_eq!(transliterate("Müller & Söhne"), "Mueller + Soehne");
+        assert_eq!(transliterate("Straße 5\r\nZürich"), "Strasse 5\r\nZuerich");
+        assert_eq!(transliterate("Łódź, São Paulo"), "Lodz, Sao Paulo");
+        assert_eq!(transliterate("info@bank.dk"), "info(at)bank.dk");
+        assert_eq!(transliterate("¤"), ".");
+
+        validate_field("59", &transliterate("Crème brûlée «Maison»; 100%"))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,580 @@
+use crate::csv_export::{csv_header, csv_rows, CsvOptions};
+use crate::json::JsonStatement;
+use crate::mt940::MT940;
+use crate::profile::{find, profiles, BankProfile};
+use crate::reader::MessageReader;
+use crate::statement::Statement;
+use crate::utils::ParseOptions;
+use clap::{Parser, ValueEnum};
+use eyre::{eyre, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+// The command line of the binary. Clap takes the help texts from the doc comments,
+// which is why these are `///`
+
+/// Parses and converts SWIFT MT940 bank statements
+#[derive(Debug, Parser)]
+#[command(
+    name = "fin-swift-rs",
+    version,
+    after_help = "Exit codes:\n  \
+        0  every message is valid\n  \
+        1  at least one message is invalid\n  \
+        2  the command line is wrong\n  \
+        3  an input could not be read or a glob matched nothing\n  \
+        4  the output could not be written"
+)]
+pub struct Cli {
+    /// Files, directories or glob patterns to read, `-` or none reads stdin. A file can hold several messages
+    pub inputs: Vec<String>,
+
+    /// Format of the output
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Json)]
+    pub format: OutputFormat,
+
+    /// Only check that every message parses, errors go to stderr and nothing to stdout
+    #[arg(long)]
+    pub validate: bool,
+
+    /// Accept messages that bend the format, what was worked around is printed to stderr
+    #[arg(long)]
+    pub lenient: bool,
+
+    /// Bank profile to parse with, e.g. `ING` or `Deutsche Bank`. By default it is detected from the BIC of the sender
+    #[arg(long, value_parser = bank_profile)]
+    pub profile: Option<BankProfile>,
+}
+
+fn bank_profile(name: &str) -> std::result::Result<BankProfile, String> {
+    find(name).map(|x| (*x).clone()).ok_or_else(|| {
+        let profiles = profiles();
+        let names: Vec<&str> = profiles.iter().map(|x| x.name.as_str()).collect();
+        format!(
+            "unknown bank profile, known profiles are {}",
+            names.join(", ")
+        )
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// An array of statements in the versioned JSON representation
+    Json,
+    /// One statement per line in the versioned JSON representation
+    Ndjson,
+    /// One row per statement line
+    Csv,
+    /// The valid messages as they came in, with the CRLF line breaks of FIN
+    Fin,
+}
+
+// The classes of errors, a run exits with the code of the worst one it ran into.
+// Usage errors are reported by clap, which exits with 2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorClass {
+    InvalidMessage,
+    Input,
+    Output,
+}
+
+impl ErrorClass {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Self::InvalidMessage => 1,
+            Self::Input => 3,
+            Self::Output => 4,
+        }
+    }
+}
+
+pub fn main() -> std::process::ExitCode {
+    let cli = Cli::parse();
+
+    let code = run(
+        &cli,
+        &mut std::io::stdin().lock(),
+        &mut std::io::stdout().lock(),
+        &mut std::io::stderr().lock(),
+    );
+
+    std::process::ExitCode::from(code)
+}
+
+// Runs the command line against the given streams and returns the exit code. Messages
+// are read and written one at a time, so memory does not grow with the size of the input
+pub fn run(cli: &Cli, stdin: &mut dyn Read, stdout: &mut dyn Write, stderr: &mut dyn Write) -> u8 {
+    let mut worst: Option<ErrorClass> = None;
+    let mut report = |class: ErrorClass, message: String, stderr: &mut dyn Write| {
+        // there is nowhere left to report a failing stderr to
+        let _ = writeln!(stderr, "{message}");
+        worst = worst.max(Some(class));
+    };
+
+    let mut output = Output::new(cli.format, stdout);
+    let mut valid = 0;
+    let mut invalid = 0;
+
+    for input in inputs(cli) {
+        let (source, reader): (String, Box<dyn BufRead + '_>) = match input {
+            Ok(Input::Stdin) => ("<stdin>".to_string(), Box::new(BufReader::new(&mut *stdin))),
+            Ok(Input::File(path)) => match File::open(&path) {
+                Ok(file) => (path.display().to_string(), Box::new(BufReader::new(file))),
+                Err(e) => {
+                    report(
+                        ErrorClass::Input,
+                        format!("{}: {e}", path.display()),
+                        stderr,
+                    );
+                    continue;
+                }
+            },
+            Err(e) => {
+                report(ErrorClass::Input, format!("{e}"), stderr);
+                continue;
+            }
+        };
+
+        for message in MessageReader::new(reader) {
+            let message = match message {
+                Ok(x) => x,
+                // the input failed, what was read of it has been handled
+                Err(e) if e.downcast_ref::<std::io::Error>().is_some() => {
+                    report(ErrorClass::Input, format!("{source}: {e}"), stderr);
+                    break;
+                }
+                Err(e) => {
+                    invalid += 1;
+                    report(ErrorClass::InvalidMessage, format!("{source}: {e}"), stderr);
+                    continue;
+                }
+            };
+
+            let options = ParseOptions {
+                strict: !cli.lenient,
+                profile: cli.profile.clone(),
+                ..ParseOptions::default()
+            };
+            let number = message.index + 1;
+
+            let mt940 = match message.mt940_with_options(options) {
+                Ok(x) => x,
+                Err(e) => {
+                    invalid += 1;
+                    report(
+                        ErrorClass::InvalidMessage,
+                        format!("{source}: message {number}: {e}"),
+                        stderr,
+                    );
+                    continue;
+                }
+            };
+
+            for warning in &mt940.warnings {
+                let _ = writeln!(
+                    stderr,
+                    "{source}: message {number}: warning: {}",
+                    warning.message
+                );
+            }
+
+            valid += 1;
+
+            if cli.validate {
+                continue;
+            }
+
+            if let Err(e) = output.write(&message.data, &mt940) {
+                report(
+                    ErrorClass::Output,
+                    format!("failed to write the output: {e}"),
+                    stderr,
+                );
+                return ErrorClass::Output.exit_code();
+            }
+        }
+    }
+
+    if cli.validate {
+        let _ = writeln!(stderr, "{valid} valid, {invalid} invalid");
+    } else if let Err(e) = output.finish() {
+        report(
+            ErrorClass::Output,
+            format!("failed to write the output: {e}"),
+            stderr,
+        );
+    }
+
+    worst.map_or(0, |x| x.exit_code())
+}
+
+// Writes the statements as they are parsed. JSON is one array and CSV has one header,
+// so these are opened by the first statement and closed by `finish`
+struct Output<'a> {
+    format: OutputFormat,
+    stdout: &'a mut dyn Write,
+    written: usize,
+}
+
+impl<'a> Output<'a> {
+    fn new(format: OutputFormat, stdout: &'a mut dyn Write) -> Self {
+        Self {
+            format,
+            stdout,
+            written: 0,
+        }
+    }
+
+    fn write(&mut self, message: &str, mt940: &MT940) -> Result<()> {
+        match self.format {
+            OutputFormat::Json => {
+                let statement = serde_json::to_string_pretty(&JsonStatement::from(mt940))?;
+                let separator = if self.written == 0 { "[" } else { "," };
+
+                // the statement is indented as an element of the array, line breaks
+                // within strings are escaped so every line is part of the layout
+                writeln!(self.stdout, "{separator}")?;
+                for (i, line) in statement.lines().enumerate() {
+                    if i > 0 {
+                        writeln!(self.stdout)?;
+                    }
+                    write!(self.stdout, "  {line}")?;
+                }
+            }
+            OutputFormat::Ndjson => {
+                let statement = serde_json::to_string(&JsonStatement::from(mt940))?;
+                writeln!(self.stdout, "{statement}")?;
+            }
+            OutputFormat::Csv => {
+                let options = CsvOptions::default();
+                if self.written == 0 {
+                    write!(self.stdout, "{}", csv_header(&options)?)?;
+                }
+                write!(
+                    self.stdout,
+                    "{}",
+                    csv_rows(&Statement::from(mt940), &options)?
+                )?;
+            }
+            // the reader has already written the line breaks as CRLF
+            OutputFormat::Fin => write!(self.stdout, "{}\r\n", message.trim())?,
+        }
+
+        self.written += 1;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        match (self.format, self.written) {
+            (OutputFormat::Json, 0) => writeln!(self.stdout, "[]")?,
+            (OutputFormat::Json, _) => writeln!(self.stdout, "\n]")?,
+            (OutputFormat::Csv, 0) => {
+                write!(self.stdout, "{}", csv_header(&CsvOptions::default())?)?
+            }
+            _ => {}
+        }
+
+        self.stdout.flush()?;
+        Ok(())
+    }
+}
+
+enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+// Every input in the order given. Globs are expanded in path order and one that matches
+// nothing is an error, as a misspelled file name would be. The files of a directory and
+// of the directories in it are read in path order too
+fn inputs(cli: &Cli) -> Vec<Result<Input>> {
+    if cli.inputs.is_empty() {
+        return vec![Ok(Input::Stdin)];
+    }
+
+    let mut inputs = vec![];
+
+    for input in &cli.inputs {
+        if input == "-" {
+            inputs.push(Ok(Input::Stdin));
+            continue;
+        }
+
+        let is_glob = input.contains(['*', '?', '[']);
+        if !is_glob || Path::new(input).exists() {
+            push_path(PathBuf::from(input), &mut inputs);
+            continue;
+        }
+
+        let paths = match glob::glob(input) {
+            Ok(paths) => paths,
+            Err(e) => {
+                inputs.push(Err(eyre!("{input}: {e}")));
+                continue;
+            }
+        };
+
+        let mut matched = false;
+        for path in paths {
+            matched = true;
+            match path {
+                Ok(path) => push_path(path, &mut inputs),
+                Err(e) => inputs.push(Err(eyre!("{e}"))),
+            }
+        }
+
+        if !matched {
+            inputs.push(Err(eyre!("{input}: no files match the pattern")));
+        }
+    }
+
+    inputs
+}
+
+fn push_path(path: PathBuf, inputs: &mut Vec<Result<Input>>) {
+    if !path.is_dir() {
+        inputs.push(Ok(Input::File(path)));
+        return;
+    }
+
+    let entries = match std::fs::read_dir(&path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            inputs.push(Err(eyre!("{}: {e}", path.display())));
+            return;
+        }
+    };
+
+    let mut paths = vec![];
+    for entry in entries {
+        match entry {
+            Ok(entry) => paths.push(entry.path()),
+            Err(e) => inputs.push(Err(eyre!("{}: {e}", path.display()))),
+        }
+    }
+    paths.sort();
+
+    for path in paths {
+        push_path(path, inputs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_with(args: &[&str], stdin: &str) -> (u8, String, String) {
+        let cli = Cli::try_parse_from([&["fin-swift-rs"], args].concat()).unwrap();
+        let mut stdout = vec![];
+        let mut stderr = vec![];
+
+        let code = run(&cli, &mut stdin.as_bytes(), &mut stdout, &mut stderr);
+
+        (
+            code,
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_cli_json_from_glob() -> Result<()> {
+        let (code, stdout, stderr) = run_with(&["src/test_messages/basic_test_*.txt"], "");
+        let statements: Vec<JsonStatement> = serde_json::from_str(&stdout)?;
+
+        assert_eq!(code, 0, "{stderr}");
+        assert_eq!(statements.len(), 4);
+        assert_eq!(statements[3].account, "DABADKKK/111111-11111111");
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_json_is_one_array() -> Result<()> {
+        let (_, stdout, _) = run_with(&["src/test_messages/basic_test_*.txt"], "");
+        let statements: Vec<JsonStatement> = serde_json::from_str(&stdout)?;
+
+        assert_eq!(stdout, serde_json::to_string_pretty(&statements)? + "\n");
+
+        let (code, stdout, _) = run_with(&[], "");
+        assert_eq!(code, 0);
+        assert_eq!(stdout, "[]\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_directory() -> Result<()> {
+        let directory = std::env::temp_dir().join(format!("fin-swift-rs-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("2009"))?;
+        std::fs::write(
+            directory.join("2009/b.fin"),
+            include_str!("./test_messages/basic_test_4.txt"),
+        )?;
+        std::fs::write(
+            directory.join("a.fin"),
+            include_str!("./test_messages/basic_test_1.txt"),
+        )?;
+
+        let (code, stdout, stderr) = run_with(&["-f", "ndjson", &directory.to_string_lossy()], "");
+        std::fs::remove_dir_all(&directory)?;
+
+        let references: Vec<String> = stdout
+            .lines()
+            .map(|x| Ok(serde_json::from_str::<JsonStatement>(x)?.reference))
+            .collect::<Result<_>>()?;
+
+        assert_eq!(code, 0, "{stderr}");
+        // `2009` sorts before `a.fin`
+        assert_eq!(references, ["3996-11-11111111", "0000000000"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_ndjson_from_stdin() -> Result<()> {
+        let message = include_str!("./test_messages/basic_test_4.txt");
+        let (code, stdout, _) = run_with(&["--format", "ndjson"], &format!("{message}\n{message}"));
+
+        assert_eq!(code, 0);
+        assert_eq!(stdout.lines().count(), 2);
+        for line in stdout.lines() {
+            assert_eq!(
+                serde_json::from_str::<JsonStatement>(line)?.reference,
+                "3996-11-11111111"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_csv_and_fin() {
+        let (code, stdout, _) = run_with(&["-f", "csv", "src/test_messages/basic_test_4.txt"], "");
+        assert_eq!(code, 0);
+        assert!(stdout.starts_with("account,statement_number,"));
+        assert_eq!(
+            csv::Reader::from_reader(stdout.as_bytes())
+                .records()
+                .count(),
+            6
+        );
+
+        let (code, stdout, _) = run_with(
+            &["-f", "fin", "-"],
+            include_str!("./test_messages/basic_test_4.txt"),
+        );
+        assert_eq!(code, 0);
+        assert!(stdout.starts_with("{1:F01ASNBNL21XXXX0000000000}"));
+        assert!(stdout.contains(":20:3996-11-11111111\r\n:25:"));
+        assert!(stdout.ends_with("{5:{CHK:C77F8E009597}}\r\n"));
+    }
+
+    #[test]
+    fn test_cli_validate() {
+        let valid = include_str!("./test_messages/basic_test_4.txt");
+        let invalid = include_str!("./test_messages/mt103_test_1.txt");
+        let (code, stdout, stderr) = run_with(&["--validate"], &format!("{valid}{invalid}"));
+
+        assert_eq!(code, 1);
+        assert!(stdout.is_empty());
+        assert!(
+            stderr.contains("<stdin>: message 2: expected an MT940 but the message type is 103")
+        );
+        assert!(stderr.ends_with("1 valid, 1 invalid\n"));
+    }
+
+    #[test]
+    fn test_cli_validate_malformed_headers() {
+        let message = include_str!("./test_messages/basic_test_1.txt");
+        let cases = [
+            ("{1:F01ASNBNL21XXXX0000000000}", "{1:F01}", "Basic Header"),
+            ("{2:O940ASNBNL21XXXXN}", "{2:O9}", "Application Header"),
+            ("{3:}", "{3:{106:20}}", "Message Input Reference"),
+            ("{3:}", "{3:{423:2001}}", "Swift date time"),
+        ];
+
+        for (header, malformed, error) in cases {
+            let (code, stdout, stderr) =
+                run_with(&["--validate"], &message.replace(header, malformed));
+
+            assert_eq!(code, 1, "{stderr}");
+            assert!(stdout.is_empty());
+            assert!(stderr.contains(error), "{stderr}");
+            assert!(stderr.ends_with("0 valid, 1 invalid\n"));
+        }
+
+        // a batch goes on after a malformed message
+        let (code, _, stderr) = run_with(&["--validate"], &format!("{{1:F01}}\n{message}"));
+        assert_eq!(code, 1);
+        assert!(stderr.ends_with("1 valid, 1 invalid\n"));
+    }
+
+    #[test]
+    fn test_cli_lenient() {
+        let message =
+            include_str!("./test_messages/basic_test_1.txt").replace("{3:}", "{3:{999:X}}");
+
+        let (code, _, stderr) = run_with(&["--validate"], &message);
+        assert_eq!(code, 1);
+        assert!(stderr.contains("unexpected tag `999` in User block"));
+
+        let (code, _, stderr) = run_with(&["--validate", "--lenient"], &message);
+        assert_eq!(code, 0);
+        assert_eq!(
+            stderr,
+            "<stdin>: message 1: warning: unexpected tag `999` in User block\n1 valid, 0 invalid\n"
+        );
+    }
+
+    #[test]
+    fn test_cli_profile() {
+        // block 5 can only be left out for a bank whose profile allows it
+        let message = include_str!("./test_messages/basic_test_1.txt").replace("{5:}", "");
+
+        let (code, _, stderr) = run_with(&["--validate"], &message);
+        assert_eq!(code, 1);
+        assert!(stderr.contains("block 5 not found"));
+
+        let (code, _, _) = run_with(&["--validate", "--profile", "deutsche bank"], &message);
+        assert_eq!(code, 0);
+
+        let error =
+            Cli::try_parse_from(["fin-swift-rs", "--profile", "Bank of Nowhere"]).unwrap_err();
+        assert_eq!(error.exit_code(), 2);
+        assert!(error
+            .to_string()
+            .contains("known profiles are ING, ABN AMRO"));
+    }
+
+    #[test]
+    fn test_cli_unreadable_input() {
+        let (code, stdout, stderr) = run_with(
+            &[
+                "src/test_messages/missing.txt",
+                "src/test_messages/basic_test_4.txt",
+            ],
+            "",
+        );
+
+        assert_eq!(code, 3);
+        assert!(stderr.starts_with("src/test_messages/missing.txt: "));
+        assert_eq!(
+            serde_json::from_str::<Vec<JsonStatement>>(&stdout)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let (code, _, stderr) = run_with(&["src/test_messages/*.fin"], "");
+        assert_eq!(code, 3);
+        assert_eq!(
+            stderr,
+            "src/test_messages/*.fin: no files match the pattern\n"
+        );
+    }
+
+    #[test]
+    fn test_cli_usage_error() {
+        let error = Cli::try_parse_from(["fin-swift-rs", "--format", "xml"]).unwrap_err();
+
+        assert_eq!(error.exit_code(), 2);
+    }
+}
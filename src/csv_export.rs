@@ -0,0 +1,225 @@
+use crate::mt940::MT940;
+use crate::statement::{Entry, Statement};
+use eyre::{eyre, Result};
+use serde::Serialize;
+
+// A column of the CSV export. The header names are part of the format that users
+// build spreadsheets on, so existing names must never change, only new ones be added
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CsvColumn {
+    Account,
+    StatementNumber,
+    ValueDate,
+    EntryDate,
+    Amount,
+    Currency,
+    TransactionType,
+    AccountOwnerReference,
+    AccountServicingInstitutionReference,
+    SupplementaryDetails,
+    Information,
+}
+
+impl CsvColumn {
+    pub const ALL: [Self; 11] = [
+        Self::Account,
+        Self::StatementNumber,
+        Self::ValueDate,
+        Self::EntryDate,
+        Self::Amount,
+        Self::Currency,
+        Self::TransactionType,
+        Self::AccountOwnerReference,
+        Self::AccountServicingInstitutionReference,
+        Self::SupplementaryDetails,
+        Self::Information,
+    ];
+
+    pub fn header(&self) -> &'static str {
+        match self {
+            Self::Account => "account",
+            Self::StatementNumber => "statement_number",
+            Self::ValueDate => "value_date",
+            Self::EntryDate => "entry_date",
+            Self::Amount => "amount",
+            Self::Currency => "currency",
+            Self::TransactionType => "transaction_type",
+            Self::AccountOwnerReference => "account_owner_reference",
+            Self::AccountServicingInstitutionReference => "account_servicing_institution_reference",
+            Self::SupplementaryDetails => "supplementary_details",
+            Self::Information => "information",
+        }
+    }
+
+    fn value(&self, statement: &Statement, entry: &Entry) -> String {
+        match self {
+            Self::Account => statement.account.clone(),
+            Self::StatementNumber => statement
+                .statement_number
+                .map(|x| x.to_string())
+                .unwrap_or_default(),
+            Self::ValueDate => entry.value_date.format("%Y-%m-%d").to_string(),
+            Self::EntryDate => entry.entry_date.format("%Y-%m-%d").to_string(),
+            Self::Amount => format!(
+                "{:.*}",
+                usize::from(statement.currency.exponent().unwrap_or(2)),
+                entry.signed_amount()
+            ),
+            Self::Currency => statement.currency.code().to_string(),
+            Self::TransactionType => entry.transaction_type_code(),
+            Self::AccountOwnerReference => entry.account_owner_reference.clone(),
+            Self::AccountServicingInstitutionReference => entry
+                .account_servicing_institution_reference
+                .clone()
+                .unwrap_or_default(),
+            Self::SupplementaryDetails => entry.supplementary_details.clone().unwrap_or_default(),
+            Self::Information => entry.information.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<&str> for CsvColumn {
+    type Error = eyre::Error;
+
+    fn try_from(input: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|x| x.header() == input)
+            .ok_or_else(|| {
+                eyre!("CSV column is either missing or the value '{input}' is not valid")
+            })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    pub columns: Vec<CsvColumn>,
+    pub delimiter: u8,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            columns: CsvColumn::ALL.to_vec(),
+            delimiter: b',',
+        }
+    }
+}
+
+// One row per statement line with the header as the first row
+pub fn csv_from_mt940(mt940: &MT940, options: &CsvOptions) -> Result<String> {
+    csv_from_statements(&[Statement::from(mt940)], options)
+}
+
+pub fn csv_from_statements(statements: &[Statement], options: &CsvOptions) -> Result<String> {
+    let mut csv = csv_header(options)?;
+
+    for statement in statements {
+        csv.push_str(&csv_rows(statement, options)?);
+    }
+
+    Ok(csv)
+}
+
+// The header row on its own, to write a long run of statements one at a time with `csv_rows`
+pub fn csv_header(options: &CsvOptions) -> Result<String> {
+    write_records(options, |writer| {
+        Ok(writer.write_record(options.columns.iter().map(CsvColumn::header))?)
+    })
+}
+
+// The rows of a statement without the header
+pub fn csv_rows(statement: &Statement, options: &CsvOptions) -> Result<String> {
+    write_records(options, |writer| {
+        for entry in &statement.entries {
+            writer.write_record(options.columns.iter().map(|x| x.value(statement, entry)))?;
+        }
+        Ok(())
+    })
+}
+
+fn write_records(
+    options: &CsvOptions,
+    write: impl FnOnce(&mut csv::Writer<Vec<u8>>) -> Result<()>,
+) -> Result<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(options.delimiter)
+        .from_writer(vec![]);
+
+    write(&mut writer)?;
+
+    let data = writer.into_inner().map_err(|e| eyre!("{e}"))?;
+
+    Ok(String::from_utf8(data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_header_is_stable() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let csv = csv_from_mt940(&mt940, &CsvOptions::default())?;
+
+        assert_eq!(
+            csv.lines().next(),
+            Some(
+                "account,statement_number,value_date,entry_date,amount,currency,transaction_type,\
+                 account_owner_reference,account_servicing_institution_reference,\
+                 supplementary_details,information"
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_from_mt940() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let csv = csv_from_mt940(&mt940, &CsvOptions::default())?;
+
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(records.len(), mt940.text.transactions.len());
+        assert_eq!(&records[0][0], "DABADKKK/111111-11111111");
+        assert_eq!(&records[0][1], "1");
+        assert_eq!(&records[0][2], "2009-10-01");
+        assert_eq!(&records[0][3], "2009-09-30");
+        assert_eq!(&records[0][4], "0.23");
+        assert_eq!(&records[0][5], "EUR");
+        assert_eq!(&records[0][6], "FINT");
+        assert_eq!(&records[0][7], "Interest");
+        assert!(records[0][10].starts_with("For your inform. IBAN no.: FI1111111111111111\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_columns_and_delimiter() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let options = CsvOptions {
+            columns: vec![
+                CsvColumn::try_from("value_date")?,
+                CsvColumn::Amount,
+                CsvColumn::AccountServicingInstitutionReference,
+            ],
+            delimiter: b';',
+        };
+        let csv = csv_from_mt940(&mt940, &options)?;
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "value_date;amount;account_servicing_institution_reference"
+        );
+        assert_eq!(lines[1], "2009-10-01;0.23;");
+        assert_eq!(lines[2], "2009-09-25;-583.92;1234");
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "CSV column is either missing or the value 'balance' is not valid")]
+    fn test_csv_unknown_column() {
+        CsvColumn::try_from("balance").unwrap();
+    }
+}
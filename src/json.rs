@@ -0,0 +1,344 @@
+use crate::mt940::MT940;
+use crate::statement::{Entry, EntryStatus, Statement, StatementBalance, StatementSource};
+use crate::utils::{Balance, BalanceType, CreditDebit};
+use chrono::{NaiveDate, NaiveDateTime};
+use eyre::Result;
+use iso_currency::Currency;
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+// The published JSON representation of a statement. Unlike the serde output of the
+// parsed message it is a contract: field names are camelCase, enums are documented
+// strings and amounts are decimal strings so no precision is lost in a float.
+//
+// Adding an optional field keeps the version, renaming, removing or changing the meaning
+// of a field needs a new version with its own schema file in `schemas/`. The doc comments
+// of these types end up as the descriptions of the JSON Schema, so they are `///`
+
+pub const JSON_SCHEMA_V1: &str = include_str!("../schemas/statement-v1.schema.json");
+
+/// Version of the representation, a document is only valid against the schema of its version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum JsonVersion {
+    #[serde(rename = "1")]
+    V1,
+}
+
+/// A bank statement (MT940, camt.053), intraday report (camt.052) or BAI2 account report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+#[schemars(title = "Statement, version 1")]
+pub struct JsonStatement {
+    pub version: JsonVersion,
+    pub source: JsonSource,
+    /// BIC of the sender of the message (MT940 block 1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender: Option<String>,
+    /// BIC the message was addressed to (MT940 block 2)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receiver: Option<String>,
+    /// Reference of the statement given by the sender (MT940 :20:)
+    pub reference: String,
+    /// Account the statement is for, an IBAN or the account as given by the bank (MT940 :25:)
+    pub account: String,
+    /// ISO 4217 currency code of the account
+    #[schemars(regex(pattern = r"^[A-Z]{3}$"))]
+    pub currency: String,
+    /// Statement number (MT940 :28C:)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statement_number: Option<u32>,
+    /// Page of the statement, starting at 1 (MT940 :28C:)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence_number: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creation_date_time: Option<NaiveDateTime>,
+    /// Booked balance before the first entry (MT940 :60F: or :60M:)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opening_balance: Option<JsonBalance>,
+    /// Booked balance after the last entry (MT940 :62F: or :62M:)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closing_balance: Option<JsonBalance>,
+    /// Funds available at the end of the statement (MT940 :64:)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closing_available_balance: Option<JsonBalance>,
+    /// Funds available on future dates (MT940 :65:)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forward_available_balances: Vec<JsonBalance>,
+    #[serde(default)]
+    pub entries: Vec<JsonEntry>,
+    /// Information about the statement as a whole, one line per line of the source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub information: Option<String>,
+}
+
+/// Format the statement was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum JsonSource {
+    #[serde(rename = "MT940")]
+    Mt940,
+    #[serde(rename = "camt.053")]
+    Camt053,
+    #[serde(rename = "camt.052")]
+    Camt052,
+    #[serde(rename = "BAI2")]
+    Bai2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct JsonBalance {
+    /// Whether the balance ends the statement (`final`) or only a page of it (`intermediate`),
+    /// available balances have no type
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance_type: Option<JsonBalanceType>,
+    pub date: NaiveDate,
+    /// Signed decimal amount in the currency of the statement, negative for a debit balance
+    #[schemars(regex(pattern = r"^-?[0-9]+(\.[0-9]+)?$"))]
+    pub amount: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum JsonBalanceType {
+    Final,
+    Intermediate,
+}
+
+/// A single booking (MT940 :61: with its :86:)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct JsonEntry {
+    pub status: JsonEntryStatus,
+    pub value_date: NaiveDate,
+    pub entry_date: NaiveDate,
+    pub credit_debit: JsonCreditDebit,
+    /// Signed decimal amount in the currency of the statement, negative when the entry
+    /// lowers the balance i.e. a debit or the reversal of a credit
+    #[schemars(regex(pattern = r"^-?[0-9]+(\.[0-9]+)?$"))]
+    pub amount: String,
+    /// Third character of the statement currency code when the bank gives it (MT940 :61:)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub funds_code: Option<String>,
+    /// SWIFT transaction type, the marker `S`, `N` or `F` and a code e.g. `NMSC` or `S103`
+    #[schemars(regex(pattern = r"^[SNF][A-Z0-9]{3}$"))]
+    pub transaction_type: String,
+    /// ISO 20022 bank transaction code as domain, family and sub family e.g. `PMNT/RCDT/ESCT`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bank_transaction_code: Option<String>,
+    /// Reference for the account owner, `NONREF` when there is none
+    pub account_owner_reference: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account_servicing_institution_reference: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supplementary_details: Option<String>,
+    /// Information to the account owner, one line per line of the source
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub information: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum JsonEntryStatus {
+    Booked,
+    Pending,
+    Information,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum JsonCreditDebit {
+    Credit,
+    Debit,
+    ReversalOfCredit,
+    ReversalOfDebit,
+}
+
+impl From<&MT940<'_>> for JsonStatement {
+    fn from(mt940: &MT940) -> Self {
+        Self {
+            sender: Some(mt940.basic.source_address.bic()),
+            receiver: Some(mt940.application.destination_address.bic()),
+            ..Self::from(&Statement::from(mt940))
+        }
+    }
+}
+
+impl From<&Statement> for JsonStatement {
+    fn from(statement: &Statement) -> Self {
+        let currency = statement.currency;
+
+        Self {
+            version: JsonVersion::V1,
+            source: match statement.source {
+                StatementSource::Mt940 => JsonSource::Mt940,
+                StatementSource::Camt053 => JsonSource::Camt053,
+                StatementSource::Camt052 => JsonSource::Camt052,
+                StatementSource::Bai2 => JsonSource::Bai2,
+            },
+            sender: None,
+            receiver: None,
+            reference: statement.reference.clone(),
+            account: statement.account.clone(),
+            currency: currency.code().to_string(),
+            statement_number: statement.statement_number,
+            sequence_number: statement.sequence_number,
+            creation_date_time: statement.creation_date_time,
+            opening_balance: statement
+                .opening_balance
+                .as_ref()
+                .map(|x| statement_balance(currency, x)),
+            closing_balance: statement
+                .closing_balance
+                .as_ref()
+                .map(|x| statement_balance(currency, x)),
+            closing_available_balance: statement
+                .closing_available_balance
+                .as_ref()
+                .map(|x| balance(currency, x, None)),
+            forward_available_balances: statement
+                .forward_available_balances
+                .iter()
+                .map(|x| balance(currency, x, None))
+                .collect(),
+            entries: statement
+                .entries
+                .iter()
+                .map(|x| entry(currency, x))
+                .collect(),
+            information: statement.information.clone(),
+        }
+    }
+}
+
+// The JSON Schema of the current version as generated from the types, it has to match
+// the published `schemas/statement-v1.schema.json`
+pub fn json_schema() -> Result<String> {
+    Ok(serde_json::to_string_pretty(&schema_for!(JsonStatement))? + "\n")
+}
+
+fn statement_balance(currency: Currency, balance_data: &StatementBalance) -> JsonBalance {
+    let balance_type = match balance_data.balance_type {
+        BalanceType::Final => JsonBalanceType::Final,
+        BalanceType::Intermediary => JsonBalanceType::Intermediate,
+    };
+
+    balance(currency, &balance_data.balance_data, Some(balance_type))
+}
+
+fn balance(
+    currency: Currency,
+    balance: &Balance,
+    balance_type: Option<JsonBalanceType>,
+) -> JsonBalance {
+    JsonBalance {
+        balance_type,
+        date: balance.date,
+        amount: decimal(currency, balance.signed_amount()),
+    }
+}
+
+fn entry(currency: Currency, entry: &Entry) -> JsonEntry {
+    JsonEntry {
+        status: match entry.status {
+            EntryStatus::Booked => JsonEntryStatus::Booked,
+            EntryStatus::Pending => JsonEntryStatus::Pending,
+            EntryStatus::Information => JsonEntryStatus::Information,
+        },
+        value_date: entry.value_date,
+        entry_date: entry.entry_date,
+        credit_debit: match entry.debit_or_credit {
+            CreditDebit::Credit => JsonCreditDebit::Credit,
+            CreditDebit::Debit => JsonCreditDebit::Debit,
+            CreditDebit::CreditReversal => JsonCreditDebit::ReversalOfCredit,
+            CreditDebit::DebitReversal => JsonCreditDebit::ReversalOfDebit,
+        },
+        amount: decimal(currency, entry.signed_amount()),
+        funds_code: entry.funds_code.map(String::from),
+        transaction_type: entry.transaction_type_code(),
+        bank_transaction_code: entry.bank_transaction_code.clone(),
+        account_owner_reference: entry.account_owner_reference.clone(),
+        account_servicing_institution_reference: entry
+            .account_servicing_institution_reference
+            .clone(),
+        supplementary_details: entry.supplementary_details.clone(),
+        information: entry.information.clone(),
+    }
+}
+
+fn decimal(currency: Currency, amount: f64) -> String {
+    format!(
+        "{:.*}",
+        usize::from(currency.exponent().unwrap_or(2)),
+        amount
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_schema_is_published() -> Result<()> {
+        assert_eq!(
+            json_schema()?,
+            JSON_SCHEMA_V1,
+            "schemas/statement-v1.schema.json is out of date with the types"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_from_mt940() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let value = serde_json::to_value(JsonStatement::from(&mt940))?;
+
+        assert_eq!(value["version"], "1");
+        assert_eq!(value["source"], "MT940");
+        assert_eq!(value["sender"], "ASNBNL21");
+        assert_eq!(value["account"], "DABADKKK/111111-11111111");
+        assert_eq!(value["statementNumber"], 1);
+        assert_eq!(
+            value["openingBalance"],
+            json!({"balanceType": "final", "date": "2009-09-24", "amount": "54484.04"})
+        );
+        assert_eq!(
+            value["closingAvailableBalance"],
+            json!({"date": "2009-09-30", "amount": "53189.31"})
+        );
+        assert_eq!(value.get("creationDateTime"), None);
+
+        let entry = &value["entries"][0];
+        assert_eq!(entry["status"], "booked");
+        assert_eq!(entry["valueDate"], "2009-10-01");
+        assert_eq!(entry["creditDebit"], "credit");
+        assert_eq!(entry["amount"], "0.23");
+        assert_eq!(entry["fundsCode"], "R");
+        assert_eq!(entry["transactionType"], "FINT");
+        assert_eq!(entry["accountOwnerReference"], "Interest");
+
+        let entry = &value["entries"][1];
+        assert_eq!(entry["creditDebit"], "debit");
+        assert_eq!(entry["amount"], "-583.92");
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_round_trip() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let statement = JsonStatement::from(&mt940);
+        let json = serde_json::to_string(&statement)?;
+
+        assert_eq!(serde_json::from_str::<JsonStatement>(&json)?, statement);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "unknown variant `2`, expected `1`")]
+    fn test_json_unknown_version() {
+        let json = r#"{"version": "2", "source": "MT940", "reference": "REF",
+            "account": "NL81ASNB9999999999", "currency": "EUR"}"#;
+
+        serde_json::from_str::<JsonStatement>(json).unwrap();
+    }
+}
@@ -0,0 +1,61 @@
+// #![warn(
+//     // clippy::all,
+//     // clippy::restriction,
+//     // clippy::pedantic,
+//     // clippy::nursery,
+//     // clippy::cargo
+// )] // generated 739 warnings - Lord help me
+
+// The public API. The message types and their blocks and tags, the shared statement
+// model and the conversions live in their own modules, the types most callers need
+// are re-exported here. `utils` and `xml` are helpers of the parsers and stay private,
+// their types that show up in the messages are re-exported below
+
+#[cfg(feature = "async")]
+pub mod async_reader;
+pub mod bai2;
+pub mod block;
+pub mod camt;
+pub mod charset;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod csv_export;
+pub mod json;
+pub mod mt103;
+pub mod mt940;
+pub mod ofx;
+pub mod pacs;
+pub mod profile;
+pub mod qif;
+pub mod reader;
+pub mod rules;
+pub mod spec;
+pub mod statement;
+pub mod tag;
+mod utils;
+mod xml;
+
+// Every parser returns an eyre report, its message says what was missing or invalid
+pub use eyre::{Report as Error, Result};
+
+#[cfg(feature = "async")]
+pub use async_reader::MessageStream;
+pub use bai2::statements_from_bai2;
+pub use block::split_messages;
+pub use camt::statements_from_camt;
+pub use charset::{transliterate, validate_message, Charset};
+pub use mt103::MT103;
+pub use mt940::MT940;
+pub use pacs::mt103_from_pacs008;
+pub use profile::{BankProfile, InformationFormat, TransactionDetails};
+pub use reader::{Message, MessageReader};
+pub use rules::Violation;
+pub use statement::Statement;
+
+pub use utils::{
+    AddressInformation, ApplicationId, Balance, BalanceType, BankOperationCode,
+    BusinessIdentifierCode, CreditDebit, CurrencyAmount, DatePolicy, DetailsOfCharges,
+    InvalidDates, LogicalTerminalAddress, MessageInputReference, ParseContext, ParseOptions,
+    SanctionScreenType, ServiceId, SwiftType, TransactionType, TransactionTypeMarker,
+    ValidationFlag, Warning, IO,
+};
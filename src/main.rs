@@ -1,32 +1,7 @@
-// #![warn(
-//     // clippy::all,
-//     // clippy::restriction,
-//     // clippy::pedantic,
-//     // clippy::nursery,
-//     // clippy::cargo
-// )] // generated 739 warnings - Lord help me
+// The command line tool, everything it does is in the `cli` module of the library
 
-use crate::mt940::MT940;
-use eyre::Result;
-use std::time::Instant;
+use std::process::ExitCode;
 
-mod block;
-mod mt940;
-mod tag;
-mod utils;
-
-fn main() -> Result<()> {
-    let content = include_str!("./test_messages/basic_test_4.txt");
-
-    let start = Instant::now();
-    let swift = MT940::new(content)?;
-    let duration = start.elapsed();
-
-    let serialized = serde_json::to_string_pretty(&swift)?;
-    println!("{serialized}");
-
-    //eprintln!("swift = {:#?}", swift.user);
-    //eprintln!("swift = {:#?}", swift);
-    eprintln!("Time elapsed: {duration:?}");
-    Ok(())
+fn main() -> ExitCode {
+    fin_swift_rs::cli::main()
 }
@@ -0,0 +1,160 @@
+use crate::block::{split_blocks, Application, Basic, CustomerCreditTransfer, Trailer, User};
+use crate::utils::{DatePolicy, ParseContext, ParseOptions, SwiftType, Warning};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+
+// https://www2.swift.com/knowledgecentre/publications/us1m_20220722/?topic=mt103-format-spec.htm
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct MT103<'a> {
+    pub basic: Basic<'a>,
+    pub application: Application<'a>,
+    pub user: User<'a>,
+    pub text: CustomerCreditTransfer<'a>,
+    pub trailer: Trailer<'a>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+impl<'a> MT103<'a> {
+    pub fn new(message_data: &'a str) -> Result<Self> {
+        Self::with_date_policy(message_data, DatePolicy::default())
+    }
+
+    pub fn with_date_policy(message_data: &'a str, date_policy: DatePolicy) -> Result<Self> {
+        Self::with_options(
+            message_data,
+            ParseOptions {
+                date_policy,
+                ..ParseOptions::default()
+            },
+        )
+    }
+
+    // In lenient mode (`strict: false`) the message is parsed as far as it can be, the
+    // problems that were worked around are in `warnings`
+    pub fn with_options(message_data: &'a str, options: ParseOptions) -> Result<Self> {
+        let mut context = ParseContext::with_options(options);
+
+        let mut block_1 = None;
+        let mut block_2 = None;
+        let mut block_3 = None;
+        let mut block_4 = None;
+        let mut block_5 = None;
+
+        for (block_id, block_data) in split_blocks(message_data)? {
+            match block_id {
+                1 => {
+                    block_1 = Some(Basic::new(block_data)?);
+                }
+                2 => {
+                    let application = Application::new(block_data)?;
+
+                    if application.message_type != SwiftType::Mt103 {
+                        return Err(eyre!(
+                            "expected an MT103 but the message type is {}",
+                            application.message_type.value()
+                        ));
+                    }
+
+                    block_2 = Some(application);
+                }
+                3 => {
+                    block_3 = Some(User::new(block_data, &mut context)?);
+                }
+                4 => {
+                    block_4 = Some(CustomerCreditTransfer::new(block_data, &mut context)?);
+                }
+                5 => {
+                    // TODO: if it is zero here then lets not even create an empty struct?
+                    block_5 = Some(Trailer::new(block_data));
+                }
+                _ => {
+                    unreachable!();
+                }
+            }
+        }
+
+        let block_1 = block_1.ok_or_else(|| eyre!("block 1 not found"))?;
+        let block_2 = block_2.ok_or_else(|| eyre!("block 2 not found"))?;
+        let block_3 = block_3.ok_or_else(|| eyre!("block 3 not found"))?;
+        let block_4 = block_4.ok_or_else(|| eyre!("block 4 not found"))?;
+        let block_5 = block_5.ok_or_else(|| eyre!("block 5 not found"))?;
+
+        Ok(Self {
+            basic: block_1,
+            application: block_2,
+            user: block_3,
+            text: block_4,
+            trailer: block_5,
+            warnings: context.warnings,
+        })
+    }
+
+    // an owned copy that can outlive the message data e.g. to send it to another thread
+    pub fn into_owned(self) -> MT103<'static> {
+        MT103 {
+            basic: self.basic.into_owned(),
+            application: self.application.into_owned(),
+            user: self.user.into_owned(),
+            text: self.text.into_owned(),
+            trailer: self.trailer.into_owned(),
+            warnings: self.warnings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{BankOperationCode, DetailsOfCharges};
+    use chrono::NaiveDate;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_message() -> Result<()> {
+        let mt103 = MT103::new(include_str!("./test_messages/mt103_test_1.txt"))?;
+
+        assert_eq!(mt103.application.message_type, SwiftType::Mt103);
+        assert_eq!(
+            mt103.user.tag_121,
+            Some(Uuid::parse_str("eb6305c9-1f7f-49de-aed0-16487c27b42d")?)
+        );
+        assert_eq!(
+            mt103.text.tag_20.transaction_reference_number,
+            "REF20200103001"
+        );
+        assert_eq!(mt103.text.tag_23b, BankOperationCode::CRED);
+        assert_eq!(
+            mt103.text.tag_32a.value_date,
+            NaiveDate::from_ymd_opt(2020, 1, 3).unwrap()
+        );
+        assert_eq!(mt103.text.tag_32a.amount, 1000.5);
+        assert_eq!(mt103.text.tag_50.name.as_deref(), Some("JOHN SMITH"));
+        assert_eq!(
+            mt103.text.tag_57.as_ref().and_then(|x| x.bic.as_deref()),
+            Some("DEUTDEFFXXX")
+        );
+        assert_eq!(
+            mt103.text.tag_59.party_identifier.as_deref(),
+            Some("DE89370400440532013000")
+        );
+        assert_eq!(mt103.text.tag_71a, DetailsOfCharges::SHA);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "expected an MT103 but the message type is 940")]
+    fn test_message_wrong_type() {
+        MT103::new(include_str!("./test_messages/basic_test_1.txt")).unwrap();
+    }
+
+    #[test]
+    fn test_message_json_round_trip() -> Result<()> {
+        let mt103 = MT103::new(include_str!("./test_messages/mt103_test_1.txt"))?;
+        let json = serde_json::to_string(&mt103)?;
+
+        assert_eq!(serde_json::from_str::<MT103>(&json)?, mt103);
+        Ok(())
+    }
+}
@@ -1,71 +1,82 @@
-use crate::block::{Application, Basic, Text, Trailer, User};
+use crate::block::{split_blocks, Application, Basic, Text, Trailer, User};
+use crate::profile::{detect, generic};
+use crate::rules::{mt940_violations, Violation};
+use crate::utils::{DatePolicy, ParseContext, ParseOptions, SwiftType, Warning};
 use eyre::{eyre, Result};
-use regex::Regex;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 // https://www.paiementor.com/swift-mt950-statement-message-detailed-analysis/
 
-#[derive(Debug, Serialize)]
+// The text fields borrow from the message they were parsed from, `into_owned` detaches
+// them. Deserializing always produces owned text so `MT940<'static>` is `DeserializeOwned`
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct MT940<'a> {
     pub basic: Basic<'a>,
     pub application: Application<'a>,
     pub user: User<'a>,
     pub text: Text<'a>,
     pub trailer: Trailer<'a>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
 }
 
 impl<'a> MT940<'a> {
     pub fn new(message_data: &'a str) -> Result<Self> {
+        Self::with_date_policy(message_data, DatePolicy::default())
+    }
+
+    pub fn with_date_policy(message_data: &'a str, date_policy: DatePolicy) -> Result<Self> {
+        Self::with_options(
+            message_data,
+            ParseOptions {
+                date_policy,
+                ..ParseOptions::default()
+            },
+        )
+    }
+
+    // In lenient mode (`strict: false`) the message is parsed as far as it can be, the
+    // problems that were worked around are in `warnings`
+    pub fn with_options(message_data: &'a str, options: ParseOptions) -> Result<Self> {
+        // an explicitly chosen profile is used whatever the BIC
+        let detect_profile = options.profile.is_none();
+        let mut context = ParseContext::with_options(options);
+
         let mut block_1 = None;
         let mut block_2 = None;
         let mut block_3 = None;
         let mut block_4 = None;
         let mut block_5 = None;
 
-        let block_regex = Regex::new(r"(?m)(\{\d:)")?;
-        let block_start: Vec<usize> = block_regex
-            .captures_iter(message_data)
-            .map(|x| return x.get(0).expect("").start())
-            .collect();
-
-        let mut block_end: Vec<usize> = block_start
-            .iter()
-            .map(|&x| if x == 0 { x } else { x - 1 })
-            .collect();
-
-        block_end.remove(0);
-        block_end.push(message_data.len() - 1);
-
-        let block_segments = block_start.iter().zip(block_end.iter());
-
-        for (i, (start, end)) in block_segments.enumerate() {
-            let block_id = 1_usize + i;
-
-            let prefix = format!("{{{block_id}:");
-            let suffix = match block_id {
-                4 => Ok("-}"),
-                1 | 2 | 3 | 5 => Ok("}"),
-                _ => Err(eyre!("unexpected block_id `{block_id}`")),
-            }?;
-
-            let block_data = message_data[*start..=*end]
-                .strip_prefix(&prefix)
-                .ok_or_else(|| eyre!("prefix '{prefix}' not found in block"))?
-                .strip_suffix(suffix)
-                .ok_or_else(|| eyre!("suffix '{suffix}' not found in block"))?;
-
+        for (block_id, block_data) in split_blocks(message_data)? {
             match block_id {
                 1 => {
-                    block_1 = Some(Basic::new(block_data)?);
+                    let basic = Basic::new(block_data)?;
+
+                    if detect_profile {
+                        context.profile =
+                            detect(&basic.source_address.bic()).unwrap_or_else(generic);
+                    }
+
+                    block_1 = Some(basic);
                 }
                 2 => {
-                    block_2 = Some(Application::new(block_data)?);
+                    let application = Application::new(block_data)?;
+
+                    if application.message_type != SwiftType::Mt940 {
+                        return Err(eyre!(
+                            "expected an MT940 but the message type is {}",
+                            application.message_type.value()
+                        ));
+                    }
+
+                    block_2 = Some(application);
                 }
                 3 => {
-                    block_3 = Some(User::new(block_data)?);
+                    block_3 = Some(User::new(block_data, &mut context)?);
                 }
                 4 => {
-                    block_4 = Some(Text::new(block_data)?);
+                    block_4 = Some(Text::new(block_data, &mut context)?);
                 }
                 5 => {
                     // TODO: if it is zero here then lets not even create an empty struct?
@@ -79,9 +90,18 @@ impl<'a> MT940<'a> {
 
         let block_1 = block_1.ok_or_else(|| eyre!("block 1 not found"))?;
         let block_2 = block_2.ok_or_else(|| eyre!("block 2 not found"))?;
-        let block_3 = block_3.ok_or_else(|| eyre!("block 3 not found"))?;
+        let optional_blocks = context.profile.optional_blocks;
+        let block_3 = match block_3 {
+            Some(block_3) => block_3,
+            None if optional_blocks => User::default(),
+            None => return Err(eyre!("block 3 not found")),
+        };
         let block_4 = block_4.ok_or_else(|| eyre!("block 4 not found"))?;
-        let block_5 = block_5.ok_or_else(|| eyre!("block 5 not found"))?;
+        let block_5 = match block_5 {
+            Some(block_5) => block_5,
+            None if optional_blocks => Trailer::default(),
+            None => return Err(eyre!("block 5 not found")),
+        };
 
         Ok(Self {
             basic: block_1,
@@ -89,13 +109,34 @@ impl<'a> MT940<'a> {
             user: block_3,
             text: block_4,
             trailer: block_5,
+            warnings: context.warnings,
         })
     }
+
+    // the MT940 rules the message does not keep to, see `rules::MT940_RULES`
+    pub fn violations(&self) -> Vec<Violation> {
+        mt940_violations(&self.text)
+    }
+
+    // an owned copy that can outlive the message data e.g. to send it to another thread
+    pub fn into_owned(self) -> MT940<'static> {
+        MT940 {
+            basic: self.basic.into_owned(),
+            application: self.application.into_owned(),
+            user: self.user.into_owned(),
+            text: self.text.into_owned(),
+            trailer: self.trailer.into_owned(),
+            warnings: self.warnings,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::profile::BankProfile;
+    use crate::utils::InvalidDates;
+    use chrono::NaiveDate;
 
     #[test]
     #[should_panic(expected = "unexpected block_id `6`")]
@@ -111,4 +152,226 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn test_message_date_policy() -> Result<()> {
+        let message = "{1:F01ASNBNL21XXXX0000000000}{2:O940ASNBNL21XXXXN}{3:}{4:
+:20:0000000000
+:25:NL81ASNB9999999999
+:28C:3/1
+:60F:C970230EUR379,29
+:62F:C970303EUR379,29
+-}{5:}";
+
+        let mt940 = MT940::with_date_policy(
+            message,
+            DatePolicy {
+                invalid_dates: InvalidDates::Warn,
+                ..DatePolicy::default()
+            },
+        )?;
+
+        assert_eq!(
+            mt940.text.tag_60.balance_data.date,
+            NaiveDate::from_ymd_opt(1997, 2, 28).unwrap()
+        );
+        assert_eq!(
+            mt940.text.tag_62.balance_data.date,
+            NaiveDate::from_ymd_opt(1997, 3, 3).unwrap()
+        );
+        assert_eq!(mt940.warnings.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid swift date provided: '970230'")]
+    fn test_message_invalid_date() {
+        MT940::new(
+            "{1:F01ASNBNL21XXXX0000000000}{2:O940ASNBNL21XXXXN}{3:}{4:
+:20:0000000000
+:25:NL81ASNB9999999999
+:28C:3/1
+:60F:C970230EUR379,29
+:62F:C970303EUR379,29
+-}{5:}",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_message_lenient() -> Result<()> {
+        let message = "{1:F01ASNBNL21XXXX0000000000}{2:O940ASNBNL21XXXXN}{3:{999:X}}{4:
+:20:0000000000
+:25:NL81ASNB9999999999
+:28C:3/1
+:60F:c200103eur379,29
+:61:2001030103d12,50ntrfNONREF
+:86:1
+
+2
+3
+4
+5
+6
+7
+:99:unknown
+:62F:C200103EUR366,79
+-}{5:}";
+
+        assert_eq!(
+            MT940::new(message).unwrap_err().to_string(),
+            "unexpected tag `999` in User block"
+        );
+
+        let mt940 = MT940::with_options(
+            message,
+            ParseOptions {
+                strict: false,
+                ..ParseOptions::default()
+            },
+        )?;
+        let text = &mt940.text;
+
+        assert_eq!(mt940.user.unknown_tags[0].tag, "999");
+        assert_eq!(text.unknown_tags[0].tag, "99");
+        assert_eq!(text.unknown_tags[0].value, "unknown");
+        assert_eq!(text.tag_60.balance_data.amount, 379.29);
+        assert_eq!(text.transactions[0].tag_61.identification_code, "TRF");
+        assert_eq!(
+            text.transactions[0].tag_86[0].information_to_account_owner,
+            "1\n2\n3\n4\n5\n6\n7"
+        );
+        assert_eq!(
+            mt940
+                .warnings
+                .iter()
+                .map(|x| x.message.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "unexpected tag `999` in User block",
+                "Balance 'c200103eur379,29' has lower case codes which were read as upper case",
+                "statement line '2001030103d12,50ntrfNONREF' has lower case codes which were read as upper case",
+                "tag 86 '1\n\n2\n3\n4\n5\n6\n7' does not match the format '6*65x'",
+                "unexpected block key `99` in Basic block",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_json_round_trip() -> Result<()> {
+        let messages = [
+            include_str!("./test_messages/basic_test_1.txt"),
+            include_str!("./test_messages/basic_test_2.txt"),
+            include_str!("./test_messages/basic_test_3.txt"),
+            include_str!("./test_messages/basic_test_4.txt"),
+        ];
+
+        for message in messages {
+            let mt940 = MT940::new(message)?;
+            let json = serde_json::to_string(&mt940)?;
+
+            assert_eq!(serde_json::from_str::<MT940>(&json)?, mt940);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_into_owned() -> Result<()> {
+        let message = include_str!("./test_messages/basic_test_4.txt").to_string();
+        let expected = MT940::new(&message)?.into_owned();
+        let mt940 = MT940::new(&message)?.into_owned();
+        drop(message);
+
+        let handle = std::thread::spawn(move || mt940);
+        let mt940 = handle.join().map_err(|_| eyre!("thread panicked"))?;
+
+        assert_eq!(mt940, expected);
+        assert_eq!(
+            mt940.text.tag_25.account_identification,
+            "DABADKKK/111111-11111111"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_json_owned() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let json = serde_json::to_vec(&mt940)?;
+
+        // text with escapes and a reader that cannot lend its data
+        let from_reader: MT940<'static> = serde_json::from_reader(json.as_slice())?;
+        assert_eq!(from_reader, mt940);
+
+        let mut escaped = mt940.into_owned();
+        escaped.text.tag_20.transaction_reference_number = "REF\\\"1".into();
+        let json = serde_json::to_string(&escaped)?;
+        assert_eq!(serde_json::from_str::<MT940>(&json)?, escaped);
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_ing_profile() -> Result<()> {
+        let message = "{1:F01INGBNL2AXXXX0000000000}{2:O940INGBNL2AXXXXN}{3:}{4:
+:20:0000000000
+:25:NL20INGB0001234567
+:28C:1/1
+:60F:C200103EUR100,00
+:61:2001030103CR50,00NTRFNONREF
+:86:/TRTP/SEPA OVERBOEKING/IBAN/NL44RABO0123456789/BIC/RABONL2U
+/NAME/J. Jansen/REMI/USTD//Factuur 1
+:62F:C200103EUR150,00
+-}{5:}";
+
+        let mt940 = MT940::new(message)?;
+        let transaction = &mt940.text.transactions[0];
+
+        assert!(mt940.violations().is_empty());
+
+        let details = transaction.details.as_ref().unwrap();
+        assert_eq!(details.counterparty_name.as_deref(), Some("J. Jansen"));
+        assert_eq!(details.remittance_information.as_deref(), Some("Factuur 1"));
+
+        // without the profile the :86: is free text
+        let mt940 = MT940::with_options(
+            message,
+            ParseOptions {
+                profile: Some(BankProfile::default()),
+                ..ParseOptions::default()
+            },
+        )?;
+
+        assert_eq!(mt940.text.transactions[0].details, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_gvc_profile() -> Result<()> {
+        let message = "{1:F01DEUTDEFFXXXX0000000000}{2:O940DEUTDEFFXXXXN}{4:
+:20:0000000000
+:25:50070010/0123456789
+:28C:1/1
+:60F:C200103EUR100,00
+:61:2001030103D25,00NTRFNONREF
+:86:166?00SEPA-UEBERWEISUNG?20EREF+INV-4711?21SVWZ+Rechnung 4711
+?32Max Mustermann
+:62F:C200103EUR75,00
+-}";
+
+        let mt940 = MT940::new(message)?;
+        let details = mt940.text.transactions[0].details.as_ref().unwrap();
+
+        assert_eq!(mt940.user, User::default());
+        assert_eq!(mt940.trailer.data, None);
+        assert_eq!(details.transaction_code.as_deref(), Some("166"));
+        assert_eq!(details.end_to_end_reference.as_deref(), Some("INV-4711"));
+        assert_eq!(details.counterparty_name.as_deref(), Some("Max Mustermann"));
+
+        let message = message.replace("DEUTDEFF", "ASNBNL21");
+        assert_eq!(
+            MT940::new(&message).unwrap_err().to_string(),
+            "block 3 not found"
+        );
+        Ok(())
+    }
 }
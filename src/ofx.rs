@@ -0,0 +1,390 @@
+use crate::mt940::MT940;
+use crate::statement::{Entry, Statement};
+use crate::utils::{is_iban, Balance, TransactionType, TransactionTypeMarker};
+use crate::xml::{element, end, start};
+use chrono::{NaiveDate, NaiveDateTime};
+use eyre::Result;
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::Writer;
+use std::collections::HashMap;
+use std::io::Cursor;
+
+// Converts an MT940 into an OFX 2.2 bank statement response (`STMTRS`).
+// The signon response needs a server time which MT940 does not carry, so it is passed in
+pub fn ofx_from_mt940(mt940: &MT940, creation_date_time: NaiveDateTime) -> Result<String> {
+    ofx_from_statement(&Statement::from(mt940), creation_date_time)
+}
+
+pub fn ofx_from_statement(
+    statement: &Statement,
+    creation_date_time: NaiveDateTime,
+) -> Result<String> {
+    let currency = statement.currency;
+    let (bank_id, account_id) = bank_and_account(&statement.account)?;
+
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+
+    writer.write_event(Event::Decl(BytesDecl::new(
+        "1.0",
+        Some("UTF-8"),
+        Some("no"),
+    )))?;
+    writer.write_event(Event::PI(BytesText::from_escaped(
+        "OFX OFXHEADER=\"200\" VERSION=\"220\" SECURITY=\"NONE\" OLDFILEUID=\"NONE\" NEWFILEUID=\"NONE\"",
+    )))?;
+    start(&mut writer, "OFX")?;
+
+    start(&mut writer, "SIGNONMSGSRSV1")?;
+    start(&mut writer, "SONRS")?;
+    status(&mut writer)?;
+    element(
+        &mut writer,
+        "DTSERVER",
+        &creation_date_time.format("%Y%m%d%H%M%S").to_string(),
+    )?;
+    element(&mut writer, "LANGUAGE", "ENG")?;
+    end(&mut writer, "SONRS")?;
+    end(&mut writer, "SIGNONMSGSRSV1")?;
+
+    start(&mut writer, "BANKMSGSRSV1")?;
+    start(&mut writer, "STMTTRNRS")?;
+    element(&mut writer, "TRNUID", &statement.reference)?;
+    status(&mut writer)?;
+
+    start(&mut writer, "STMTRS")?;
+    element(&mut writer, "CURDEF", currency.code())?;
+
+    start(&mut writer, "BANKACCTFROM")?;
+    element(&mut writer, "BANKID", &bank_id)?;
+    element(&mut writer, "ACCTID", &account_id)?;
+    element(&mut writer, "ACCTTYPE", "CHECKING")?;
+    end(&mut writer, "BANKACCTFROM")?;
+
+    let entry_dates = || statement.entries.iter().map(|x| x.entry_date);
+    let start_date = statement
+        .opening_balance
+        .as_ref()
+        .map(|x| x.balance_data.date)
+        .or_else(|| entry_dates().min());
+    let end_date = statement
+        .closing_balance
+        .as_ref()
+        .map(|x| x.balance_data.date)
+        .or_else(|| entry_dates().max());
+
+    start(&mut writer, "BANKTRANLIST")?;
+    if let (Some(start_date), Some(end_date)) = (start_date, end_date) {
+        element(&mut writer, "DTSTART", &date(start_date))?;
+        element(&mut writer, "DTEND", &date(end_date))?;
+    }
+
+    for (entry, fitid) in statement.entries.iter().zip(fitids(statement)) {
+        start(&mut writer, "STMTTRN")?;
+        element(&mut writer, "TRNTYPE", transaction_type(entry))?;
+        element(&mut writer, "DTPOSTED", &date(entry.entry_date))?;
+        element(&mut writer, "DTUSER", &date(entry.value_date))?;
+        element(
+            &mut writer,
+            "TRNAMT",
+            &amount(statement, entry.signed_amount()),
+        )?;
+        element(&mut writer, "FITID", &fitid)?;
+        if entry.account_owner_reference != "NONREF" {
+            element(
+                &mut writer,
+                "REFNUM",
+                &truncate(&entry.account_owner_reference, 32),
+            )?;
+        }
+        if let Some(supplementary_details) = &entry.supplementary_details {
+            element(&mut writer, "NAME", &truncate(supplementary_details, 32))?;
+        }
+        if let Some(information) = &entry.information {
+            element(&mut writer, "MEMO", &truncate(&one_line(information), 255))?;
+        }
+        end(&mut writer, "STMTTRN")?;
+    }
+    end(&mut writer, "BANKTRANLIST")?;
+
+    // OFX requires a ledger balance, without a :62: the entries are all there is
+    if let Some(closing_balance) = &statement.closing_balance {
+        balance(
+            &mut writer,
+            "LEDGERBAL",
+            statement,
+            &closing_balance.balance_data,
+        )?;
+    }
+
+    if let Some(available_balance) = &statement.closing_available_balance {
+        balance(&mut writer, "AVAILBAL", statement, available_balance)?;
+    }
+
+    end(&mut writer, "STMTRS")?;
+    end(&mut writer, "STMTTRNRS")?;
+    end(&mut writer, "BANKMSGSRSV1")?;
+    end(&mut writer, "OFX")?;
+
+    Ok(String::from_utf8(writer.into_inner().into_inner())?)
+}
+
+// The OFX TRNTYPE for a statement line. Codes without an OFX equivalent fall back
+// to CREDIT or DEBIT by the sign of the amount
+pub fn transaction_type(entry: &Entry) -> &'static str {
+    if entry.transaction_type_marker == TransactionTypeMarker::SwiftTransfer {
+        return "XFER";
+    }
+
+    match entry.transaction_type {
+        Some(TransactionType::CHG) => "SRVCHG",
+        Some(TransactionType::COM) => "FEE",
+        Some(TransactionType::INT) => "INT",
+        Some(TransactionType::DIV) => "DIV",
+        Some(TransactionType::CHK) => "CHECK",
+        Some(TransactionType::CAS) => "CASH",
+        Some(TransactionType::DDT) => "DIRECTDEBIT",
+        Some(TransactionType::STO) => "REPEATPMT",
+        Some(TransactionType::TRF) => "XFER",
+        _ if entry.signed_amount() < 0.0 => "DEBIT",
+        _ => "CREDIT",
+    }
+}
+
+// FITIDs must not change when the same statement is exported again, otherwise
+// accounting packages import its lines twice. Each is a hash of the line and the
+// number of identical lines before it in the statement
+pub fn fitids(statement: &Statement) -> Vec<String> {
+    let mut seen: HashMap<u64, u32> = HashMap::new();
+
+    statement
+        .entries
+        .iter()
+        .map(|entry| {
+            let key = [
+                statement.account.as_str(),
+                statement.currency.code(),
+                &date(entry.value_date),
+                &date(entry.entry_date),
+                &amount(statement, entry.signed_amount()),
+                &entry.transaction_type_code(),
+                &entry.account_owner_reference,
+                entry
+                    .account_servicing_institution_reference
+                    .as_deref()
+                    .unwrap_or_default(),
+                entry.supplementary_details.as_deref().unwrap_or_default(),
+                entry.information.as_deref().unwrap_or_default(),
+            ]
+            .join("\u{1f}");
+
+            let hash = fnv1a(key.as_bytes());
+            let occurrence = seen.entry(hash).or_default();
+            *occurrence += 1;
+
+            format!("{hash:016X}{occurrence}")
+        })
+        .collect()
+}
+
+// 64 bit FNV-1a, the std hashers are not guaranteed to be stable between releases
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Where the bank code is in the IBAN of a country, from the IBAN registry. In Italy and
+// San Marino a check character comes before it
+const IBAN_BANK_CODES: &[(&str, usize, usize)] = &[
+    ("AT", 4, 9),
+    ("BE", 4, 7),
+    ("BG", 4, 8),
+    ("CH", 4, 9),
+    ("CY", 4, 7),
+    ("CZ", 4, 8),
+    ("DE", 4, 12),
+    ("DK", 4, 8),
+    ("EE", 4, 6),
+    ("ES", 4, 8),
+    ("FI", 4, 7),
+    ("FR", 4, 9),
+    ("GB", 4, 8),
+    ("GR", 4, 7),
+    ("HR", 4, 11),
+    ("HU", 4, 7),
+    ("IE", 4, 8),
+    ("IT", 5, 10),
+    ("LI", 4, 9),
+    ("LT", 4, 9),
+    ("LU", 4, 7),
+    ("LV", 4, 8),
+    ("MT", 4, 8),
+    ("NL", 4, 8),
+    ("NO", 4, 8),
+    ("PT", 4, 8),
+    ("RO", 4, 8),
+    ("SE", 4, 7),
+    ("SI", 4, 9),
+    ("SK", 4, 8),
+    ("SM", 5, 10),
+];
+
+// The bank part of the account: the BIC in front of a `/` as in `DABADKKK/111111-11111111`,
+// or the bank code of an IBAN of a country in `IBAN_BANK_CODES`. OFX needs a BANKID, so
+// anything else falls back to `0`
+fn bank_and_account(account: &str) -> Result<(String, String)> {
+    if let Some((bank, account)) = account.split_once('/') {
+        if !bank.is_empty() && bank.len() <= 9 {
+            return Ok((bank.to_string(), account.to_string()));
+        }
+    }
+
+    let bank_code = IBAN_BANK_CODES
+        .iter()
+        .find(|(country, _, _)| is_iban(account) && account.starts_with(country))
+        .and_then(|(_, start, end)| account.get(*start..*end));
+
+    match bank_code {
+        Some(bank_code) => Ok((bank_code.to_string(), account.to_string())),
+        None => Ok(("0".to_string(), account.to_string())),
+    }
+}
+
+fn balance(
+    writer: &mut Writer<Cursor<Vec<u8>>>,
+    name: &str,
+    statement: &Statement,
+    balance: &Balance,
+) -> Result<()> {
+    start(writer, name)?;
+    element(
+        writer,
+        "BALAMT",
+        &amount(statement, balance.signed_amount()),
+    )?;
+    element(writer, "DTASOF", &date(balance.date))?;
+    end(writer, name)
+}
+
+fn status(writer: &mut Writer<Cursor<Vec<u8>>>) -> Result<()> {
+    start(writer, "STATUS")?;
+    element(writer, "CODE", "0")?;
+    element(writer, "SEVERITY", "INFO")?;
+    end(writer, "STATUS")
+}
+
+fn amount(statement: &Statement, amount: f64) -> String {
+    format!(
+        "{:.*}",
+        usize::from(statement.currency.exponent().unwrap_or(2)),
+        amount
+    )
+}
+
+fn date(date: NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+fn one_line(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn truncate(text: &str, length: usize) -> String {
+    text.chars().take(length).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xml::leaf_values;
+
+    fn creation_date_time() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2009, 10, 1)
+            .and_then(|x| x.and_hms_opt(8, 0, 0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_ofx_from_mt940() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let ofx = ofx_from_mt940(&mt940, creation_date_time())?;
+        let values = leaf_values(&ofx)?;
+        let value = |path: &str| {
+            values
+                .get(&format!("BANKMSGSRSV1/STMTTRNRS/STMTRS/{path}"))
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        assert!(ofx.contains("<?OFX OFXHEADER=\"200\" VERSION=\"220\""));
+        assert_eq!(value("CURDEF"), ["EUR"]);
+        assert_eq!(value("BANKACCTFROM/BANKID"), ["DABADKKK"]);
+        assert_eq!(value("BANKACCTFROM/ACCTID"), ["111111-11111111"]);
+        assert_eq!(value("BANKTRANLIST/DTSTART"), ["20090924"]);
+        assert_eq!(value("BANKTRANLIST/DTEND"), ["20090930"]);
+        assert_eq!(value("LEDGERBAL/BALAMT"), ["53126.94"]);
+        assert_eq!(value("LEDGERBAL/DTASOF"), ["20090930"]);
+        assert_eq!(value("AVAILBAL/BALAMT"), ["53189.31"]);
+        assert_eq!(
+            value("BANKTRANLIST/STMTTRN/TRNTYPE"),
+            ["INT", "DEBIT", "DEBIT", "DEBIT", "SRVCHG", "DEBIT"]
+        );
+        assert_eq!(
+            value("BANKTRANLIST/STMTTRN/TRNAMT"),
+            ["0.23", "-583.92", "-390.40", "-265.41", "-62.60", "-55.00"]
+        );
+        assert_eq!(value("BANKTRANLIST/STMTTRN/REFNUM").len(), 6);
+        assert_eq!(value("BANKTRANLIST/STMTTRN/NAME"), ["NONREFHOLMENSKANAL"]);
+        assert_eq!(
+            value("BANKTRANLIST/STMTTRN/MEMO")[0],
+            "For your inform. IBAN no.: FI1111111111111111 DABADKKK 111111-11111111 \
+             DANSKE BANK                        HOLMENS KANAL 2-12"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ofx_fitids_are_deterministic() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let mut statement = Statement::from(&mt940);
+
+        let first = fitids(&statement);
+        assert_eq!(first, fitids(&Statement::from(&mt940)));
+        assert!(first.iter().all(|x| x.len() == 17));
+
+        // an identical line in the same statement still gets its own FITID
+        let duplicate = Entry::from(&mt940.text.transactions[1]);
+        statement.entries.push(duplicate);
+        let second = fitids(&statement);
+
+        assert_eq!(second[..first.len()], first[..]);
+        assert_eq!(second[6][..16], second[1][..16]);
+        assert_ne!(second[6], second[1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_ofx_bank_and_account() -> Result<()> {
+        assert_eq!(
+            bank_and_account("NL81ASNB9999999999")?,
+            ("ASNB".to_string(), "NL81ASNB9999999999".to_string())
+        );
+        // the Bankleitzahl of a German IBAN is eight digits
+        assert_eq!(
+            bank_and_account("DE89370400440532013000")?,
+            ("37040044".to_string(), "DE89370400440532013000".to_string())
+        );
+        assert_eq!(bank_and_account("IT60X0542811101000000123456")?.0, "05428");
+        // a country without a known bank code
+        assert_eq!(bank_and_account("QA58DOHB00001234567890ABCDEFG")?.0, "0");
+        assert_eq!(
+            bank_and_account("1234567890")?,
+            ("0".to_string(), "1234567890".to_string())
+        );
+        Ok(())
+    }
+}
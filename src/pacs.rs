@@ -0,0 +1,952 @@
+use crate::mt103::MT103;
+use crate::tag::Party;
+use crate::utils::{is_iban, CurrencyAmount, DetailsOfCharges};
+use crate::xml::{amount, date_from_iso, element, end, format_date_time, leaf_values, start};
+use chrono::NaiveDateTime;
+use eyre::{eyre, Result};
+use quick_xml::events::{BytesDecl, BytesStart, Event};
+use quick_xml::Writer;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use uuid::Uuid;
+
+// https://www.swift.com/standards/iso-20022/iso-20022-programme/cbpr
+// https://www2.swift.com/mystandards/#/mp/mx/_Rt4XcP0-EeyKr6HkNKQ8Kw/version/21
+
+pub const PACS_008_NAMESPACE: &str = "urn:iso:std:iso:20022:tech:xsd:pacs.008.001.08";
+
+// An element of a pacs.008 that did not fit into its MT103 field. Following the CBPR+
+// translation rules the field is cut and its last character replaced with `+`
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Truncation {
+    pub tag: String,
+    pub element: String,
+    pub value: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Mt103Translation {
+    pub message: String,
+    pub truncations: Vec<Truncation>,
+}
+
+// Translates an MT103 into a CBPR+ pacs.008.001.08 `FIToFICstmrCdtTrf`. CBPR+ requires
+// the UETR, so block 3 must carry tag 121. :13C:, :23E: and :26T: are not translated
+pub fn pacs008_from_mt103(mt103: &MT103, creation_date_time: NaiveDateTime) -> Result<String> {
+    let text = &mt103.text;
+    let uetr = mt103
+        .user
+        .tag_121
+        .ok_or_else(|| eyre!("MT103 is missing the UETR (block 3 tag 121) which CBPR+ requires"))?;
+    let sender = mt103.basic.source_address.bic();
+    let receiver = mt103.application.destination_address.bic();
+    let reference = text.tag_20.transaction_reference_number.as_ref();
+
+    let (end_to_end_id, remittance_information) = match &text.tag_70 {
+        Some(tag_70) => split_remittance_information(&tag_70.lines),
+        None => (None, String::new()),
+    };
+
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer.write_event(Event::Start(
+        BytesStart::new("Document").with_attributes([("xmlns", PACS_008_NAMESPACE)]),
+    ))?;
+    start(&mut writer, "FIToFICstmrCdtTrf")?;
+
+    start(&mut writer, "GrpHdr")?;
+    element(&mut writer, "MsgId", reference)?;
+    element(
+        &mut writer,
+        "CreDtTm",
+        &format_date_time(creation_date_time),
+    )?;
+    element(&mut writer, "NbOfTxs", "1")?;
+    start(&mut writer, "SttlmInf")?;
+    // correspondents in :53a: or :54a: mean the settlement is covered by a separate transfer
+    let settlement_method = if text.tag_53.is_some() || text.tag_54.is_some() {
+        "COVE"
+    } else {
+        "INDA"
+    };
+    element(&mut writer, "SttlmMtd", settlement_method)?;
+    if let Some(tag_53) = &text.tag_53 {
+        agent(&mut writer, "InstgRmbrsmntAgt", tag_53)?;
+    }
+    if let Some(tag_54) = &text.tag_54 {
+        agent(&mut writer, "InstdRmbrsmntAgt", tag_54)?;
+    }
+    end(&mut writer, "SttlmInf")?;
+    end(&mut writer, "GrpHdr")?;
+
+    start(&mut writer, "CdtTrfTxInf")?;
+
+    start(&mut writer, "PmtId")?;
+    element(&mut writer, "InstrId", reference)?;
+    element(
+        &mut writer,
+        "EndToEndId",
+        end_to_end_id.unwrap_or("NOTPROVIDED"),
+    )?;
+    element(&mut writer, "UETR", &uetr.to_string())?;
+    end(&mut writer, "PmtId")?;
+
+    amount(
+        &mut writer,
+        "IntrBkSttlmAmt",
+        text.tag_32a.currency.code(),
+        text.tag_32a.amount,
+    )?;
+    element(
+        &mut writer,
+        "IntrBkSttlmDt",
+        &text.tag_32a.value_date.format("%Y-%m-%d").to_string(),
+    )?;
+
+    if let Some(tag_33b) = &text.tag_33b {
+        amount(
+            &mut writer,
+            "InstdAmt",
+            tag_33b.currency.code(),
+            tag_33b.amount,
+        )?;
+    }
+
+    if let Some(tag_36) = text.tag_36 {
+        element(&mut writer, "XchgRate", &tag_36.to_string())?;
+    }
+
+    let charge_bearer = match text.tag_71a {
+        DetailsOfCharges::BEN => "CRED",
+        DetailsOfCharges::OUR => "DEBT",
+        DetailsOfCharges::SHA => "SHAR",
+    };
+    element(&mut writer, "ChrgBr", charge_bearer)?;
+
+    // the sender's charges are taken by the sender, the receiver's charges by the receiver
+    for tag_71f in &text.tag_71f {
+        charges(&mut writer, tag_71f, &sender)?;
+    }
+    if let Some(tag_71g) = &text.tag_71g {
+        charges(&mut writer, tag_71g, &receiver)?;
+    }
+
+    agent_bic(&mut writer, "InstgAgt", &sender)?;
+    agent_bic(&mut writer, "InstdAgt", &receiver)?;
+
+    if let Some(tag_56) = &text.tag_56 {
+        agent(&mut writer, "IntrmyAgt1", tag_56)?;
+        agent_account(&mut writer, "IntrmyAgt1Acct", tag_56)?;
+    }
+
+    party(&mut writer, "Dbtr", &text.tag_50)?;
+    if let Some(account_identifier) = account_identifier(&text.tag_50) {
+        account(&mut writer, "DbtrAcct", account_identifier)?;
+    }
+
+    // CBPR+ always names the debtor and creditor agents, these default to sender and receiver
+    match &text.tag_52 {
+        Some(tag_52) => {
+            agent(&mut writer, "DbtrAgt", tag_52)?;
+            agent_account(&mut writer, "DbtrAgtAcct", tag_52)?;
+        }
+        None => agent_bic(&mut writer, "DbtrAgt", &sender)?,
+    }
+
+    match &text.tag_57 {
+        Some(tag_57) => {
+            agent(&mut writer, "CdtrAgt", tag_57)?;
+            agent_account(&mut writer, "CdtrAgtAcct", tag_57)?;
+        }
+        None => agent_bic(&mut writer, "CdtrAgt", &receiver)?,
+    }
+
+    party(&mut writer, "Cdtr", &text.tag_59)?;
+    if let Some(account_identifier) = account_identifier(&text.tag_59) {
+        account(&mut writer, "CdtrAcct", account_identifier)?;
+    }
+
+    let instructions = text
+        .tag_72
+        .as_ref()
+        .map(|x| split_instructions(&x.lines))
+        .unwrap_or_default();
+
+    for instruction in &instructions {
+        if let Some(information) = instruction.strip_prefix("/ACC/") {
+            start(&mut writer, "InstrForCdtrAgt")?;
+            element(&mut writer, "InstrInf", information)?;
+            end(&mut writer, "InstrForCdtrAgt")?;
+        }
+    }
+
+    for instruction in &instructions {
+        if !instruction.starts_with("/ACC/") {
+            let information = instruction.strip_prefix("/REC/").unwrap_or(instruction);
+
+            start(&mut writer, "InstrForNxtAgt")?;
+            element(&mut writer, "InstrInf", information)?;
+            end(&mut writer, "InstrForNxtAgt")?;
+        }
+    }
+
+    if let Some(tag_77b) = &text.tag_77b {
+        start(&mut writer, "RgltryRptg")?;
+        start(&mut writer, "Dtls")?;
+        for line in &tag_77b.lines {
+            element(&mut writer, "Inf", line)?;
+        }
+        end(&mut writer, "Dtls")?;
+        end(&mut writer, "RgltryRptg")?;
+    }
+
+    if !remittance_information.is_empty() {
+        start(&mut writer, "RmtInf")?;
+        element(&mut writer, "Ustrd", &remittance_information)?;
+        end(&mut writer, "RmtInf")?;
+    }
+
+    end(&mut writer, "CdtTrfTxInf")?;
+    end(&mut writer, "FIToFICstmrCdtTrf")?;
+    end(&mut writer, "Document")?;
+
+    Ok(String::from_utf8(writer.into_inner().into_inner())?)
+}
+
+fn charges(
+    writer: &mut Writer<Cursor<Vec<u8>>>,
+    charges: &CurrencyAmount,
+    bic: &str,
+) -> Result<()> {
+    start(writer, "ChrgsInf")?;
+    amount(writer, "Amt", charges.currency.code(), charges.amount)?;
+    agent_bic(writer, "Agt", bic)?;
+    end(writer, "ChrgsInf")
+}
+
+fn agent_bic(writer: &mut Writer<Cursor<Vec<u8>>>, name: &str, bic: &str) -> Result<()> {
+    start(writer, name)?;
+    start(writer, "FinInstnId")?;
+    element(writer, "BICFI", bic)?;
+    end(writer, "FinInstnId")?;
+    end(writer, name)
+}
+
+fn agent(writer: &mut Writer<Cursor<Vec<u8>>>, name: &str, party: &Party) -> Result<()> {
+    if let Some(bic) = &party.bic {
+        return agent_bic(writer, name, bic);
+    }
+
+    start(writer, name)?;
+    start(writer, "FinInstnId")?;
+    if let Some(party_name) = &party.name {
+        element(writer, "Nm", party_name)?;
+    }
+    postal_address(writer, &party.address)?;
+    end(writer, "FinInstnId")?;
+    end(writer, name)
+}
+
+fn agent_account(writer: &mut Writer<Cursor<Vec<u8>>>, name: &str, party: &Party) -> Result<()> {
+    match account_identifier(party) {
+        Some(account_identifier) => account(writer, name, account_identifier),
+        None => Ok(()),
+    }
+}
+
+fn party(writer: &mut Writer<Cursor<Vec<u8>>>, name: &str, party: &Party) -> Result<()> {
+    start(writer, name)?;
+    if let Some(party_name) = &party.name {
+        element(writer, "Nm", party_name)?;
+    }
+    postal_address(writer, &party.address)?;
+    if let Some(bic) = &party.bic {
+        start(writer, "Id")?;
+        start(writer, "OrgId")?;
+        element(writer, "AnyBIC", bic)?;
+        end(writer, "OrgId")?;
+        end(writer, "Id")?;
+    }
+    end(writer, name)
+}
+
+fn postal_address(writer: &mut Writer<Cursor<Vec<u8>>>, address: &[Cow<str>]) -> Result<()> {
+    if address.is_empty() {
+        return Ok(());
+    }
+
+    start(writer, "PstlAdr")?;
+    for line in address {
+        element(writer, "AdrLine", line)?;
+    }
+    end(writer, "PstlAdr")
+}
+
+fn account(writer: &mut Writer<Cursor<Vec<u8>>>, name: &str, identifier: &str) -> Result<()> {
+    start(writer, name)?;
+    start(writer, "Id")?;
+    if is_iban(identifier) {
+        element(writer, "IBAN", identifier)?;
+    } else {
+        start(writer, "Othr")?;
+        element(writer, "Id", identifier)?;
+        end(writer, "Othr")?;
+    }
+    end(writer, "Id")?;
+    end(writer, name)
+}
+
+// the account of a party without the `C/` or `D/` mark of option A and D, the identifier
+// of option F is only an account when it is not a code such as `CUST/DE/...`
+fn account_identifier<'a>(party: &'a Party) -> Option<&'a str> {
+    let identifier = party.party_identifier.as_deref()?;
+
+    if party.option == Some('F') && identifier.contains('/') {
+        return None;
+    }
+
+    match identifier.as_bytes() {
+        [b'C' | b'D', b'/', ..] => Some(&identifier[2..]),
+        _ => Some(identifier),
+    }
+}
+
+// :70: can start with the end to end reference as `/ROC/`, the rest is the remittance
+// information which is wrapped over the lines of the field
+fn split_remittance_information<'a>(lines: &'a [Cow<str>]) -> (Option<&'a str>, String) {
+    match lines.split_first() {
+        Some((first, rest)) if first.starts_with("/ROC/") => (Some(&first[5..]), rest.concat()),
+        _ => (None, lines.concat()),
+    }
+}
+
+// each :72: instruction starts with a `/code/`, continuation lines start with `//`
+fn split_instructions(lines: &[Cow<str>]) -> Vec<String> {
+    let mut instructions: Vec<String> = vec![];
+
+    for line in lines {
+        match (line.strip_prefix("//"), instructions.last_mut()) {
+            (Some(continuation), Some(instruction)) => instruction.push_str(continuation),
+            _ => instructions.push(line.to_string()),
+        }
+    }
+
+    instructions
+}
+
+// Translates a CBPR+ pacs.008.001.08 `FIToFICstmrCdtTrf` with a single transaction into
+// an MT103 FIN message. Anything that does not fit is cut and returned as a truncation
+pub fn mt103_from_pacs008(xml: &str) -> Result<Mt103Translation> {
+    let values = leaf_values(xml)?;
+    let pacs008 = Pacs008 { values: &values };
+
+    if !values.keys().any(|x| x.starts_with("FIToFICstmrCdtTrf/")) {
+        return Err(eyre!("document is not a pacs.008 FIToFICstmrCdtTrf"));
+    }
+
+    if pacs008.all("CdtTrfTxInf/IntrBkSttlmAmt").len() > 1 {
+        return Err(eyre!(
+            "only a pacs.008 with a single transaction can be translated to MT103"
+        ));
+    }
+
+    let sender = pacs008.require("CdtTrfTxInf/InstgAgt/FinInstnId/BICFI")?;
+    let receiver = pacs008.require("CdtTrfTxInf/InstdAgt/FinInstnId/BICFI")?;
+    let uetr = Uuid::parse_str(pacs008.require("CdtTrfTxInf/PmtId/UETR")?)?;
+
+    let mut translation = Translation::default();
+
+    let reference = match pacs008.get("CdtTrfTxInf/PmtId/InstrId") {
+        Some(reference) => ("CdtTrfTxInf/PmtId/InstrId", reference),
+        None => ("GrpHdr/MsgId", pacs008.require("GrpHdr/MsgId")?),
+    };
+    let reference = translation.truncate("20", reference.0, reference.1, 16);
+    translation.field("20", &[reference]);
+    translation.field("23B", &["CRED".to_string()]);
+
+    let settlement_date = date_from_iso(pacs008.require("CdtTrfTxInf/IntrBkSttlmDt")?)?;
+    translation.field(
+        "32A",
+        &[format!(
+            "{}{}{}",
+            settlement_date.format("%y%m%d"),
+            pacs008.require("CdtTrfTxInf/IntrBkSttlmAmt@Ccy")?,
+            swift_amount(pacs008.require("CdtTrfTxInf/IntrBkSttlmAmt")?)
+        )],
+    );
+
+    if let Some(instructed_amount) = pacs008.get("CdtTrfTxInf/InstdAmt") {
+        translation.field(
+            "33B",
+            &[format!(
+                "{}{}",
+                pacs008.require("CdtTrfTxInf/InstdAmt@Ccy")?,
+                swift_amount(instructed_amount)
+            )],
+        );
+    }
+
+    if let Some(exchange_rate) = pacs008.get("CdtTrfTxInf/XchgRate") {
+        translation.field("36", &[swift_amount(exchange_rate)]);
+    }
+
+    translation.party("50", "CdtTrfTxInf/Dbtr", "CdtTrfTxInf/DbtrAcct", &pacs008);
+
+    // the debtor and creditor agents are only named when they are not the sender and receiver
+    if !pacs008.is_bic("CdtTrfTxInf/DbtrAgt", sender) {
+        translation.agent("52", "CdtTrfTxInf/DbtrAgt", &pacs008);
+    }
+    translation.agent("53", "GrpHdr/SttlmInf/InstgRmbrsmntAgt", &pacs008);
+    translation.agent("54", "GrpHdr/SttlmInf/InstdRmbrsmntAgt", &pacs008);
+    translation.agent("56", "CdtTrfTxInf/IntrmyAgt1", &pacs008);
+    if !pacs008.is_bic("CdtTrfTxInf/CdtrAgt", receiver) {
+        translation.agent("57", "CdtTrfTxInf/CdtrAgt", &pacs008);
+    }
+
+    translation.party("59", "CdtTrfTxInf/Cdtr", "CdtTrfTxInf/CdtrAcct", &pacs008);
+
+    let mut remittance_information = vec![];
+    match pacs008.get("CdtTrfTxInf/PmtId/EndToEndId") {
+        Some("NOTPROVIDED") | None => {}
+        Some(end_to_end_id) => {
+            let line = format!("/ROC/{end_to_end_id}");
+            remittance_information.push(translation.truncate(
+                "70",
+                "CdtTrfTxInf/PmtId/EndToEndId",
+                &line,
+                35,
+            ));
+        }
+    }
+    for unstructured in pacs008.all("CdtTrfTxInf/RmtInf/Ustrd") {
+        remittance_information.extend(wrap(unstructured, ""));
+    }
+    let remittance_information =
+        translation.limit_lines("70", "CdtTrfTxInf/RmtInf/Ustrd", remittance_information, 4);
+    translation.field("70", &remittance_information);
+
+    let details_of_charges = match pacs008.require("CdtTrfTxInf/ChrgBr")? {
+        "CRED" => "BEN",
+        "DEBT" => "OUR",
+        "SHAR" => "SHA",
+        charge_bearer => {
+            return Err(eyre!(
+                "Charge Bearer is either missing or the value '{charge_bearer}' is not valid"
+            ))
+        }
+    };
+    translation.field("71A", &[details_of_charges.to_string()]);
+
+    // every ChrgsInf has an amount and the agent that takes it, the values are paired up
+    // by their order so each of them has to be there for every charge
+    let charge_amounts = pacs008.all("CdtTrfTxInf/ChrgsInf/Amt");
+    let charge_currencies = pacs008.all("CdtTrfTxInf/ChrgsInf/Amt@Ccy");
+    let charge_agents = pacs008.all("CdtTrfTxInf/ChrgsInf/Agt/FinInstnId/BICFI");
+
+    if charge_currencies.len() != charge_amounts.len() {
+        return Err(eyre!(
+            "pacs.008 has {} charge amounts but {} of them have a currency",
+            charge_amounts.len(),
+            charge_currencies.len()
+        ));
+    }
+    if charge_agents.len() != charge_amounts.len() {
+        return Err(eyre!(
+            "pacs.008 has {} charge amounts but {} charge agents with a BIC",
+            charge_amounts.len(),
+            charge_agents.len()
+        ));
+    }
+
+    let charges = charge_amounts
+        .iter()
+        .zip(charge_currencies)
+        .zip(charge_agents);
+
+    for ((amount, currency), agent) in charges {
+        let tag = match same_bic(agent, receiver) {
+            true => "71G",
+            false => "71F",
+        };
+        translation.field(tag, &[format!("{currency}{}", swift_amount(amount))]);
+    }
+
+    let mut instructions = vec![];
+    for information in pacs008.all("CdtTrfTxInf/InstrForCdtrAgt/InstrInf") {
+        instructions.extend(wrap(&format!("/ACC/{information}"), "//"));
+    }
+    for information in pacs008.all("CdtTrfTxInf/InstrForNxtAgt/InstrInf") {
+        let instruction = if information.starts_with('/') {
+            information.to_string()
+        } else {
+            format!("/REC/{information}")
+        };
+        instructions.extend(wrap(&instruction, "//"));
+    }
+    let instructions = translation.limit_lines("72", "CdtTrfTxInf/InstrForNxtAgt", instructions, 6);
+    translation.field("72", &instructions);
+
+    let mut regulatory_reporting = vec![];
+    for information in pacs008.all("CdtTrfTxInf/RgltryRptg/Dtls/Inf") {
+        regulatory_reporting.extend(wrap(information, ""));
+    }
+    let regulatory_reporting = translation.limit_lines(
+        "77B",
+        "CdtTrfTxInf/RgltryRptg/Dtls/Inf",
+        regulatory_reporting,
+        3,
+    );
+    translation.field("77B", &regulatory_reporting);
+
+    let message = format!(
+        "{{1:F01{}0000000000}}{{2:I103{}N}}{{3:{{121:{uetr}}}}}{{4:\n{}\n-}}{{5:}}",
+        logical_terminal_address(sender)?,
+        logical_terminal_address(receiver)?,
+        translation.fields.join("\n")
+    );
+
+    Ok(Mt103Translation {
+        message,
+        truncations: translation.truncations,
+    })
+}
+
+// the leaf values of a pacs.008 below `FIToFICstmrCdtTrf`
+struct Pacs008<'a> {
+    values: &'a BTreeMap<String, Vec<String>>,
+}
+
+impl<'a> Pacs008<'a> {
+    fn all(&self, path: &str) -> &'a [String] {
+        self.values
+            .get(&format!("FIToFICstmrCdtTrf/{path}"))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    fn get(&self, path: &str) -> Option<&'a str> {
+        self.all(path).first().map(String::as_str)
+    }
+
+    fn require(&self, path: &str) -> Result<&'a str> {
+        self.get(path)
+            .ok_or_else(|| eyre!("pacs.008 is missing `{path}`"))
+    }
+
+    fn is_bic(&self, agent: &str, bic: &str) -> bool {
+        self.get(&format!("{agent}/FinInstnId/BICFI"))
+            .filter(|x| same_bic(x, bic))
+            .is_some()
+    }
+}
+
+#[derive(Default)]
+struct Translation {
+    fields: Vec<String>,
+    truncations: Vec<Truncation>,
+}
+
+impl Translation {
+    fn field(&mut self, tag: &str, lines: &[String]) {
+        if !lines.is_empty() {
+            self.fields.push(format!(":{tag}:{}", lines.join("\n")));
+        }
+    }
+
+    // cuts a value to `length` characters
+    fn truncate(&mut self, tag: &str, element: &str, value: &str, length: usize) -> String {
+        if value.chars().count() <= length {
+            return value.to_string();
+        }
+
+        self.truncations.push(Truncation {
+            tag: tag.to_string(),
+            element: element.to_string(),
+            value: value.to_string(),
+        });
+
+        value.chars().take(length - 1).chain(['+']).collect()
+    }
+
+    // keeps the first `max_lines` lines of a field
+    fn limit_lines(
+        &mut self,
+        tag: &str,
+        element: &str,
+        mut lines: Vec<String>,
+        max_lines: usize,
+    ) -> Vec<String> {
+        if lines.len() <= max_lines {
+            return lines;
+        }
+
+        self.truncations.push(Truncation {
+            tag: tag.to_string(),
+            element: element.to_string(),
+            value: lines.join("\n"),
+        });
+
+        lines.truncate(max_lines);
+        if let Some(last) = lines.last_mut() {
+            if last.chars().count() >= 35 {
+                last.pop();
+            }
+            last.push('+');
+        }
+
+        lines
+    }
+
+    // a customer as option A when it is identified by a BIC and by name and address otherwise
+    fn party(&mut self, tag: &str, path: &str, account_path: &str, pacs008: &Pacs008) {
+        let mut lines = vec![];
+
+        let account = pacs008
+            .get(&format!("{account_path}/Id/IBAN"))
+            .or_else(|| pacs008.get(&format!("{account_path}/Id/Othr/Id")));
+        if let Some(account) = account {
+            let element = format!("{account_path}/Id");
+            lines.push(format!("/{}", self.truncate(tag, &element, account, 34)));
+        }
+
+        if let Some(bic) = pacs008.get(&format!("{path}/Id/OrgId/AnyBIC")) {
+            lines.push(bic.to_string());
+            self.field(&format!("{tag}A"), &lines);
+            return;
+        }
+
+        lines.extend(self.name_and_address(tag, path, pacs008));
+
+        // the beneficiary customer has no letter for its name and address option
+        let option = if tag == "59" { "" } else { "K" };
+        self.field(&format!("{tag}{option}"), &lines);
+    }
+
+    // an institution as option A when it is identified by a BIC and as option D otherwise
+    fn agent(&mut self, tag: &str, path: &str, pacs008: &Pacs008) {
+        let mut lines = vec![];
+
+        let account = pacs008
+            .get(&format!("{path}Acct/Id/IBAN"))
+            .or_else(|| pacs008.get(&format!("{path}Acct/Id/Othr/Id")));
+        if let Some(account) = account {
+            let element = format!("{path}Acct/Id");
+            lines.push(format!("/{}", self.truncate(tag, &element, account, 34)));
+        }
+
+        if let Some(bic) = pacs008.get(&format!("{path}/FinInstnId/BICFI")) {
+            lines.push(bic.to_string());
+            self.field(&format!("{tag}A"), &lines);
+        } else if pacs008.get(&format!("{path}/FinInstnId/Nm")).is_some() {
+            lines.extend(self.name_and_address(tag, &format!("{path}/FinInstnId"), pacs008));
+            self.field(&format!("{tag}D"), &lines);
+        }
+    }
+
+    // the name and the address lines, at most 4 lines of 35 characters
+    fn name_and_address(&mut self, tag: &str, path: &str, pacs008: &Pacs008) -> Vec<String> {
+        let mut lines = vec![];
+
+        if let Some(name) = pacs008.get(&format!("{path}/Nm")) {
+            lines.push(self.truncate(tag, &format!("{path}/Nm"), name, 35));
+        }
+
+        let address_lines = pacs008.all(&format!("{path}/PstlAdr/AdrLine"));
+        let address: Vec<String> = if address_lines.is_empty() {
+            // a structured address is written as street, town and country
+            let part = |name: &str| pacs008.get(&format!("{path}/PstlAdr/{name}"));
+            [
+                [part("StrtNm"), part("BldgNb")],
+                [part("PstCd"), part("TwnNm")],
+                [part("Ctry"), None],
+            ]
+            .iter()
+            .map(|x| x.iter().flatten().copied().collect::<Vec<_>>().join(" "))
+            .filter(|x| !x.is_empty())
+            .collect()
+        } else {
+            address_lines.to_vec()
+        };
+
+        let element = format!("{path}/PstlAdr");
+        for line in address {
+            lines.push(self.truncate(tag, &element, &line, 35));
+        }
+
+        self.limit_lines(tag, &element, lines, 4)
+    }
+}
+
+// splits text into lines of 35 characters, lines after the first start with `continuation`
+fn wrap(text: &str, continuation: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut rest: Vec<char> = text.chars().collect();
+    let mut prefix = "";
+
+    while !rest.is_empty() {
+        let width = (35 - prefix.len()).min(rest.len());
+        lines.push(format!(
+            "{prefix}{}",
+            rest.drain(..width).collect::<String>()
+        ));
+        prefix = continuation;
+    }
+
+    lines
+}
+
+// an ISO 20022 decimal such as `1000.5` as a SWIFT amount `1000,5`
+fn swift_amount(amount: &str) -> String {
+    if amount.contains('.') {
+        amount.replace('.', ",")
+    } else {
+        format!("{amount},")
+    }
+}
+
+// a BIC without a branch code is the same as the one of its primary office `XXX`
+fn same_bic(left: &str, right: &str) -> bool {
+    let normalize = |bic: &str| match bic.len() {
+        8 => format!("{bic}XXX"),
+        _ => bic.to_string(),
+    };
+
+    normalize(left) == normalize(right)
+}
+
+// the BIC is checked to be ASCII first, slicing a multi-byte character would panic
+fn logical_terminal_address(bic: &str) -> Result<String> {
+    if !bic.bytes().all(|x| x.is_ascii_alphanumeric()) {
+        return Err(eyre!("BIC '{bic}' is not valid"));
+    }
+
+    match bic.len() {
+        8 => Ok(format!("{bic}XXXX")),
+        11 => Ok(format!("{}X{}", &bic[..8], &bic[8..])),
+        _ => Err(eyre!("BIC '{bic}' is not valid")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn creation_date_time() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2020, 1, 3)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_pacs008_from_mt103() -> Result<()> {
+        let mt103 = MT103::new(include_str!("./test_messages/mt103_test_1.txt"))?;
+        let xml = pacs008_from_mt103(&mt103, creation_date_time())?;
+
+        assert!(xml.contains(PACS_008_NAMESPACE));
+        assert!(xml.contains("<UETR>eb6305c9-1f7f-49de-aed0-16487c27b42d</UETR>"));
+        assert!(xml.contains("<InstrId>REF20200103001</InstrId>"));
+        assert!(xml.contains("<EndToEndId>E2E-20200103-1</EndToEndId>"));
+        assert!(xml.contains("<IntrBkSttlmAmt Ccy=\"EUR\">1000.5</IntrBkSttlmAmt>"));
+        assert!(xml.contains("<IntrBkSttlmDt>2020-01-03</IntrBkSttlmDt>"));
+        assert!(xml.contains("<SttlmMtd>INDA</SttlmMtd>"));
+        assert!(xml.contains("<ChrgBr>SHAR</ChrgBr>"));
+        assert!(xml.contains("<IBAN>GB29NWBK60161331926819</IBAN>"));
+        assert!(xml.contains("<AdrLine>1 HIGH STREET</AdrLine>"));
+        assert!(xml.contains("<BICFI>DEUTDEFFXXX</BICFI>"));
+        assert!(xml.contains("<InstrInf>PAY BY RETURN</InstrInf>"));
+        assert!(xml.contains("<Ustrd>INVOICE 2020-001</Ustrd>"));
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "MT103 is missing the UETR (block 3 tag 121) which CBPR+ requires")]
+    fn test_pacs008_from_mt103_without_uetr() {
+        let message = include_str!("./test_messages/mt103_test_1.txt")
+            .replace("{3:{121:eb6305c9-1f7f-49de-aed0-16487c27b42d}}", "{3:}");
+        let mt103 = MT103::new(&message).unwrap();
+
+        pacs008_from_mt103(&mt103, creation_date_time()).unwrap();
+    }
+
+    #[test]
+    fn test_mt103_round_trip() -> Result<()> {
+        let original = MT103::new(include_str!("./test_messages/mt103_test_1.txt"))?;
+        let xml = pacs008_from_mt103(&original, creation_date_time())?;
+
+        let translation = mt103_from_pacs008(&xml)?;
+        let mt103 = MT103::new(&translation.message)?;
+
+        assert!(translation.truncations.is_empty());
+        assert_eq!(mt103.user.tag_121, original.user.tag_121);
+        assert_eq!(mt103.basic.source_address.bic(), "BARCGB22");
+        assert_eq!(mt103.application.destination_address.bic(), "DEUTDEFF");
+        assert_eq!(mt103.text.tag_20, original.text.tag_20);
+        assert_eq!(mt103.text.tag_32a, original.text.tag_32a);
+        assert_eq!(mt103.text.tag_33b, original.text.tag_33b);
+        assert_eq!(mt103.text.tag_50, original.text.tag_50);
+        // the ordering and account with institutions are the sender and receiver
+        assert_eq!(mt103.text.tag_52, None);
+        assert_eq!(mt103.text.tag_57, None);
+        assert_eq!(mt103.text.tag_59, original.text.tag_59);
+        assert_eq!(mt103.text.tag_70, original.text.tag_70);
+        assert_eq!(mt103.text.tag_71a, original.text.tag_71a);
+        assert_eq!(mt103.text.tag_72, original.text.tag_72);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mt103_from_pacs008_truncation() -> Result<()> {
+        let translation = mt103_from_pacs008(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:pacs.008.001.08">
+  <FIToFICstmrCdtTrf>
+    <GrpHdr>
+      <MsgId>MSG-1</MsgId>
+      <CreDtTm>2020-01-03T09:00:00</CreDtTm>
+      <NbOfTxs>1</NbOfTxs>
+      <SttlmInf><SttlmMtd>INDA</SttlmMtd></SttlmInf>
+    </GrpHdr>
+    <CdtTrfTxInf>
+      <PmtId>
+        <InstrId>INSTRUCTION-ID-LONGER-THAN-16</InstrId>
+        <EndToEndId>NOTPROVIDED</EndToEndId>
+        <UETR>eb6305c9-1f7f-49de-aed0-16487c27b42d</UETR>
+      </PmtId>
+      <IntrBkSttlmAmt Ccy="EUR">250</IntrBkSttlmAmt>
+      <IntrBkSttlmDt>2020-01-03</IntrBkSttlmDt>
+      <ChrgBr>DEBT</ChrgBr>
+      <ChrgsInf>
+        <Amt Ccy="EUR">5</Amt>
+        <Agt><FinInstnId><BICFI>DEUTDEFFXXX</BICFI></FinInstnId></Agt>
+      </ChrgsInf>
+      <InstgAgt><FinInstnId><BICFI>BARCGB22</BICFI></FinInstnId></InstgAgt>
+      <InstdAgt><FinInstnId><BICFI>DEUTDEFF</BICFI></FinInstnId></InstdAgt>
+      <Dbtr>
+        <Nm>A VERY LONG COMPANY NAME THAT DOES NOT FIT INTO AN MT LINE</Nm>
+        <PstlAdr>
+          <StrtNm>HIGH STREET</StrtNm>
+          <BldgNb>1</BldgNb>
+          <PstCd>EC1A 1BB</PstCd>
+          <TwnNm>LONDON</TwnNm>
+          <Ctry>GB</Ctry>
+        </PstlAdr>
+      </Dbtr>
+      <DbtrAcct><Id><Othr><Id>12345678</Id></Othr></Id></DbtrAcct>
+      <DbtrAgt><FinInstnId><BICFI>BARCGB22XXX</BICFI></FinInstnId></DbtrAgt>
+      <CdtrAgt><FinInstnId><BICFI>COBADEFF</BICFI></FinInstnId></CdtrAgt>
+      <Cdtr>
+        <Id><OrgId><AnyBIC>MUSTDEFF</AnyBIC></OrgId></Id>
+      </Cdtr>
+      <CdtrAcct><Id><IBAN>DE89370400440532013000</IBAN></Id></CdtrAcct>
+      <RmtInf>
+        <Ustrd>INVOICES 2020-001 2020-002 2020-003 2020-004 2020-005 2020-006 2020-007 2020-008 2020-009 2020-010 2020-011 2020-012 2020-013 2020-014 2020-015</Ustrd>
+      </RmtInf>
+    </CdtTrfTxInf>
+  </FIToFICstmrCdtTrf>
+</Document>"#,
+        )?;
+
+        let mt103 = MT103::new(&translation.message)?;
+
+        assert_eq!(
+            mt103.text.tag_20.transaction_reference_number,
+            "INSTRUCTION-ID-+"
+        );
+        assert_eq!(
+            mt103.text.tag_50.name.as_deref(),
+            Some("A VERY LONG COMPANY NAME THAT DOES+")
+        );
+        assert_eq!(
+            mt103.text.tag_50.address,
+            vec!["HIGH STREET 1", "EC1A 1BB LONDON", "GB"]
+        );
+        assert_eq!(
+            mt103.text.tag_50.party_identifier.as_deref(),
+            Some("12345678")
+        );
+        assert_eq!(mt103.text.tag_52, None);
+        assert_eq!(
+            mt103.text.tag_57.as_ref().and_then(|x| x.bic.as_deref()),
+            Some("COBADEFF")
+        );
+        assert_eq!(mt103.text.tag_59.bic.as_deref(), Some("MUSTDEFF"));
+        assert_eq!(mt103.text.tag_71a, DetailsOfCharges::OUR);
+        assert_eq!(mt103.text.tag_71g.as_ref().map(|x| x.amount), Some(5.0));
+
+        let tag_70 = mt103.text.tag_70.as_ref().unwrap();
+        assert_eq!(tag_70.lines.len(), 4);
+        assert!(tag_70.lines[3].ends_with('+'));
+
+        let truncated: Vec<(&str, &str)> = translation
+            .truncations
+            .iter()
+            .map(|x| (x.tag.as_str(), x.element.as_str()))
+            .collect();
+        assert_eq!(
+            truncated,
+            vec![
+                ("20", "CdtTrfTxInf/PmtId/InstrId"),
+                ("50", "CdtTrfTxInf/Dbtr/Nm"),
+                ("70", "CdtTrfTxInf/RmtInf/Ustrd"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_logical_terminal_address() -> Result<()> {
+        assert_eq!(logical_terminal_address("ASNBNL21")?, "ASNBNL21XXXX");
+        assert_eq!(logical_terminal_address("DEUTDEFF500")?, "DEUTDEFFX500");
+
+        for bic in ["ASNBNL2", "ÄSNBNL2", "ASNBNL2ÄXX", "ASNB NL21"] {
+            assert_eq!(
+                logical_terminal_address(bic).unwrap_err().to_string(),
+                format!("BIC '{bic}' is not valid")
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_mt103_from_pacs008_charges() -> Result<()> {
+        let message = include_str!("./test_messages/mt103_test_1.txt")
+            .replace(":71A:SHA", ":71A:SHA\n:71F:EUR2,50\n:71F:EUR1,");
+        let original = MT103::new(&message)?;
+        let xml = pacs008_from_mt103(&original, creation_date_time())?;
+
+        let translation = mt103_from_pacs008(&xml)?;
+        let mt103 = MT103::new(&translation.message)?;
+
+        assert_eq!(mt103.text.tag_71f, original.text.tag_71f);
+        assert_eq!(mt103.text.tag_71g, None);
+
+        // a charge whose agent has no BIC cannot be told to be the sender's or the receiver's
+        let start = xml.rfind("<ChrgsInf>").unwrap();
+        let end = xml.rfind("</ChrgsInf>").unwrap();
+        let xml = format!(
+            "{}{}{}",
+            &xml[..start],
+            xml[start..end].replace("BICFI", "Nm"),
+            &xml[end..]
+        );
+
+        assert_eq!(
+            mt103_from_pacs008(&xml).unwrap_err().to_string(),
+            "pacs.008 has 2 charge amounts but 1 charge agents with a BIC"
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "document is not a pacs.008 FIToFICstmrCdtTrf")]
+    fn test_mt103_from_pacs008_wrong_document() {
+        mt103_from_pacs008(
+            r#"<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.08"><BkToCstmrStmt/></Document>"#,
+        )
+        .unwrap();
+    }
+}
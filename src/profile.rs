@@ -0,0 +1,465 @@
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+// The way a bank lays out the narrative of its :86: fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InformationFormat {
+    // free text, nothing is decoded
+    Free,
+    // German banks: a three digit business transaction code (Geschäftsvorfallcode) followed
+    // by subfields that start with `?` and a two digit number, e.g. `166?00GUTSCHRIFT?20...`
+    Gvc,
+    // Dutch banks: SEPA codewords between slashes, e.g. `/TRTP/SEPA OVERBOEKING/NAME/...`
+    Codewords,
+}
+
+impl TryFrom<&str> for InformationFormat {
+    type Error = eyre::Error;
+
+    fn try_from(input: &str) -> Result<Self> {
+        match input {
+            "free" => Ok(Self::Free),
+            "gvc" => Ok(Self::Gvc),
+            "codewords" => Ok(Self::Codewords),
+            _ => Err(eyre!(
+                "Information Format is either missing or the value '{input}' is not valid"
+            )),
+        }
+    }
+}
+
+impl InformationFormat {
+    pub fn value(&self) -> String {
+        match self {
+            Self::Free => "free".to_string(),
+            Self::Gvc => "gvc".to_string(),
+            Self::Codewords => "codewords".to_string(),
+        }
+    }
+
+    // The details of the :86: of a transaction, `None` for free text or a narrative that is
+    // not laid out the way the format expects. The lines of an :86: are wrapped at 65
+    // characters rather than at a subfield, so they are joined without a separator
+    pub fn decode(&self, information: &str) -> Option<TransactionDetails> {
+        let information: String = information.lines().collect();
+
+        match self {
+            Self::Free => None,
+            Self::Gvc => decode_gvc(&information),
+            Self::Codewords => decode_codewords(&information),
+        }
+    }
+}
+
+// The structured content of an :86: as far as the bank fills it in
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionDetails {
+    pub transaction_code: Option<String>,
+    pub posting_text: Option<String>,
+    pub remittance_information: Option<String>,
+    pub end_to_end_reference: Option<String>,
+    pub counterparty_name: Option<String>,
+    pub counterparty_account: Option<String>,
+    pub counterparty_bank: Option<String>,
+}
+
+// The quirks of the MT940 of a bank. A profile is chosen explicitly through
+// `ParseOptions::profile` or else picked by the BIC of the basic header. The settings
+// are grouped by the part of the parser they change: the blocks, the tokenizer of block
+// 4, the :61: statement line and the :86: information to the account owner
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BankProfile {
+    pub name: String,
+    // BICs or the start of them, e.g. the four letter bank code
+    pub bics: Vec<String>,
+    // the user header (block 3) and the trailer (block 5) can be left out
+    pub optional_blocks: bool,
+    // a tag can be indented by spaces or tabs, as files that went through an editor often
+    // are. Off, a tag has to start the line and an indented one carries on the field before
+    pub indented_tags: bool,
+    // the identification code of a :61: can be the three digit business transaction code
+    // (Geschäftsvorfallcode) of German banks instead of a transaction type, e.g. `N020`
+    pub gvc_transaction_codes: bool,
+    // text past the 16 characters of a :61: reference on its first line is the
+    // supplementary details, ASN writes the counterparty after NONREF. Off, it is an error
+    pub reference_overflow: bool,
+    pub information_format: InformationFormat,
+}
+
+impl Default for BankProfile {
+    fn default() -> Self {
+        Self::new("Generic", &[])
+    }
+}
+
+impl BankProfile {
+    pub fn new(name: &str, bics: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            bics: bics.iter().map(|x| x.to_string()).collect(),
+            optional_blocks: false,
+            indented_tags: true,
+            gvc_transaction_codes: false,
+            reference_overflow: false,
+            information_format: InformationFormat::Free,
+        }
+    }
+
+    // the length of the longest BIC of the profile the BIC starts with
+    fn matches(&self, bic: &str) -> Option<usize> {
+        self.bics
+            .iter()
+            .filter(|x| !x.is_empty() && bic.starts_with(x.as_str()))
+            .map(String::len)
+            .max()
+    }
+}
+
+// Profiles are shared with the messages parsed with them, so that a message can refer to
+// its profile without copying it
+static REGISTERED_PROFILES: RwLock<Vec<Arc<BankProfile>>> = RwLock::new(Vec::new());
+static BUILT_IN_PROFILES: OnceLock<Vec<Arc<BankProfile>>> = OnceLock::new();
+static GENERIC_PROFILE: OnceLock<Arc<BankProfile>> = OnceLock::new();
+
+// Adds a profile for a bank that is not built in. Registered profiles take precedence
+// over the built in ones, the last one registered first
+pub fn register(profile: BankProfile) {
+    REGISTERED_PROFILES
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(0, Arc::new(profile));
+}
+
+// The registered profiles followed by the built in ones
+pub fn profiles() -> Vec<Arc<BankProfile>> {
+    let mut profiles = REGISTERED_PROFILES
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone();
+
+    profiles.extend(built_in_profiles().iter().cloned());
+    profiles
+}
+
+pub fn find(name: &str) -> Option<Arc<BankProfile>> {
+    profiles()
+        .into_iter()
+        .find(|x| x.name.eq_ignore_ascii_case(name))
+}
+
+// The profile with the most specific BIC the given BIC starts with
+pub fn detect(bic: &str) -> Option<Arc<BankProfile>> {
+    let registered = REGISTERED_PROFILES
+        .read()
+        .unwrap_or_else(PoisonError::into_inner);
+    let mut detected: Option<(usize, &Arc<BankProfile>)> = None;
+
+    for profile in registered.iter().chain(built_in_profiles()) {
+        if let Some(length) = profile.matches(bic) {
+            if detected.is_none_or(|(x, _)| length > x) {
+                detected = Some((length, profile));
+            }
+        }
+    }
+
+    detected.map(|(_, profile)| profile.clone())
+}
+
+// the profile of a bank without quirks, used when no profile matches
+pub fn generic() -> Arc<BankProfile> {
+    GENERIC_PROFILE
+        .get_or_init(|| Arc::new(BankProfile::default()))
+        .clone()
+}
+
+fn built_in_profiles() -> &'static [Arc<BankProfile>] {
+    BUILT_IN_PROFILES.get_or_init(|| {
+        [
+            BankProfile {
+                information_format: InformationFormat::Codewords,
+                ..BankProfile::new("ING", &["INGB", "INGD", "BBRU"])
+            },
+            BankProfile {
+                information_format: InformationFormat::Codewords,
+                ..BankProfile::new("ABN AMRO", &["ABNA"])
+            },
+            BankProfile {
+                information_format: InformationFormat::Codewords,
+                ..BankProfile::new("Rabobank", &["RABO"])
+            },
+            BankProfile {
+                reference_overflow: true,
+                ..BankProfile::new("ASN Bank", &["ASNB"])
+            },
+            BankProfile {
+                optional_blocks: true,
+                gvc_transaction_codes: true,
+                information_format: InformationFormat::Gvc,
+                ..BankProfile::new("Deutsche Bank", &["DEUT"])
+            },
+            BankProfile {
+                optional_blocks: true,
+                gvc_transaction_codes: true,
+                information_format: InformationFormat::Gvc,
+                ..BankProfile::new("Commerzbank", &["COBA", "DRES"])
+            },
+            BankProfile {
+                gvc_transaction_codes: true,
+                information_format: InformationFormat::Gvc,
+                ..BankProfile::new("Postbank", &["PBNK"])
+            },
+            BankProfile {
+                gvc_transaction_codes: true,
+                information_format: InformationFormat::Gvc,
+                ..BankProfile::new("HypoVereinsbank", &["HYVE"])
+            },
+            BankProfile::new("BNP Paribas", &["BNPA"]),
+            BankProfile::new("Société Générale", &["SOGE"]),
+            BankProfile::new("Santander", &["BSCH"]),
+            BankProfile::new("UniCredit", &["UNCR"]),
+            BankProfile::new("KBC", &["KRED"]),
+            BankProfile::new("Danske Bank", &["DABA"]),
+            BankProfile::new("Nordea", &["NDEA"]),
+        ]
+        .into_iter()
+        .map(Arc::new)
+        .collect()
+    })
+}
+
+fn decode_gvc(information: &str) -> Option<TransactionDetails> {
+    let transaction_code = information
+        .get(..3)
+        .filter(|x| x.chars().all(|c| c.is_ascii_digit()))?;
+    // the separator is `?` for nearly every bank but it is whatever follows the code
+    let separator = information[3..]
+        .chars()
+        .next()
+        .filter(|c| !c.is_ascii_alphanumeric())?;
+
+    let mut details = TransactionDetails {
+        transaction_code: Some(transaction_code.to_string()),
+        ..TransactionDetails::default()
+    };
+    let mut purpose = String::new();
+    let mut name = String::new();
+
+    for subfield in information[3..].split(separator).skip(1) {
+        let number = match subfield.get(..2).and_then(|x| x.parse::<u8>().ok()) {
+            Some(number) => number,
+            None => continue,
+        };
+        let value = &subfield[2..];
+
+        match number {
+            0 => details.posting_text = non_empty(value),
+            20..=29 | 60..=63 => purpose.push_str(value),
+            30 => details.counterparty_bank = non_empty(value),
+            31 => details.counterparty_account = non_empty(value),
+            32 | 33 => name.push_str(value),
+            _ => {}
+        }
+    }
+
+    // SEPA payments split the purpose further with keywords such as EREF+ and SVWZ+
+    let keywords = sepa_keywords(&purpose);
+
+    details.end_to_end_reference = keywords
+        .iter()
+        .find(|(keyword, _)| *keyword == "EREF")
+        .and_then(|(_, value)| non_empty(value));
+    details.remittance_information = match keywords.iter().find(|(keyword, _)| *keyword == "SVWZ") {
+        Some((_, value)) => non_empty(value),
+        None if keywords.is_empty() => non_empty(&purpose),
+        None => None,
+    };
+    details.counterparty_name = non_empty(&name);
+
+    Some(details)
+}
+
+const SEPA_KEYWORDS: &[&str] = &[
+    "EREF", "KREF", "MREF", "CRED", "DEBT", "COAM", "OAMT", "SVWZ", "ABWA", "ABWE",
+];
+
+fn sepa_keywords(purpose: &str) -> Vec<(&str, &str)> {
+    let mut starts: Vec<(usize, &str)> = SEPA_KEYWORDS
+        .iter()
+        .flat_map(|keyword| {
+            purpose
+                .match_indices(&format!("{keyword}+"))
+                .map(|(i, _)| (i, *keyword))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    starts.sort();
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, (start, keyword))| {
+            let end = starts.get(i + 1).map_or(purpose.len(), |(x, _)| *x);
+            (*keyword, purpose[start + keyword.len() + 1..end].trim())
+        })
+        .collect()
+}
+
+const CODEWORDS: &[&str] = &[
+    "TRTP", "IBAN", "BIC", "NAME", "REMI", "EREF", "MARF", "CSID", "CNTP", "ORDP", "BENM", "ULTD",
+    "ULTC", "PURP", "RTRN", "ADDR", "ISDT", "PREF",
+];
+
+fn decode_codewords(information: &str) -> Option<TransactionDetails> {
+    // the codewords and where their value starts and the codeword ends
+    let mut codewords: Vec<(&str, usize, usize)> = vec![];
+    let mut next = 0;
+
+    for (i, _) in information.match_indices('/') {
+        if i < next {
+            continue;
+        }
+
+        let codeword = CODEWORDS.iter().find(|codeword| {
+            information[i + 1..].starts_with(*codeword)
+                && information[i + 1 + codeword.len()..].starts_with('/')
+        });
+
+        if let Some(codeword) = codeword {
+            next = i + codeword.len() + 2;
+            codewords.push((codeword, i, next));
+        }
+    }
+
+    if codewords.first().map(|(_, start, _)| *start) != Some(0) {
+        return None;
+    }
+
+    let mut details = TransactionDetails::default();
+
+    for (i, (codeword, _, start)) in codewords.iter().enumerate() {
+        let end = codewords
+            .get(i + 1)
+            .map_or(information.len(), |(_, x, _)| *x);
+        let value = information[*start..end].trim_end_matches('/');
+
+        match *codeword {
+            "TRTP" => details.posting_text = non_empty(value),
+            "IBAN" => details.counterparty_account = non_empty(value),
+            "BIC" => details.counterparty_bank = non_empty(value),
+            "NAME" => details.counterparty_name = non_empty(value),
+            "EREF" => details.end_to_end_reference = non_empty(value),
+            // unstructured (USTD//text) or structured (STRD/CUR/reference) remittance information
+            "REMI" => {
+                let remittance = value
+                    .strip_prefix("USTD//")
+                    .or_else(|| value.strip_prefix("STRD/CUR/"))
+                    .unwrap_or(value);
+                details.remittance_information = non_empty(remittance);
+            }
+            // the counterparty as account/BIC/name/city
+            "CNTP" => {
+                let mut parts = value.split('/');
+                details.counterparty_account = parts.next().and_then(non_empty);
+                details.counterparty_bank = parts.next().and_then(non_empty);
+                details.counterparty_name = parts.next().and_then(non_empty);
+            }
+            _ => {}
+        }
+    }
+
+    Some(details)
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+
+    match value.is_empty() {
+        true => None,
+        false => Some(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("INGBNL2A").unwrap().name, "ING");
+        assert_eq!(detect("DEUTDEFFXXX").unwrap().name, "Deutsche Bank");
+        assert_eq!(detect("ASNBNL21").unwrap().name, "ASN Bank");
+        assert_eq!(detect("ZZZZNL2A"), None);
+    }
+
+    #[test]
+    fn test_register() {
+        register(BankProfile {
+            optional_blocks: true,
+            ..BankProfile::new("Deutsche Bank Frankfurt", &["DEUTDEFF"])
+        });
+
+        // the more specific BIC wins over the built in Deutsche Bank profile
+        assert_eq!(
+            detect("DEUTDEFF500").unwrap().name,
+            "Deutsche Bank Frankfurt"
+        );
+        assert_eq!(detect("DEUTDEDB").unwrap().name, "Deutsche Bank");
+        assert!(find("deutsche bank frankfurt").unwrap().optional_blocks);
+    }
+
+    #[test]
+    fn test_decode_gvc() {
+        let details = InformationFormat::Gvc
+            .decode(
+                "166?00SEPA-UEBERWEISUNG?100001?20EREF+INV-4711?21SVWZ+Rechnung 4711\n?30COBADEFFXXX?31DE89370400440532013000?32Max Mustermann?34000",
+            )
+            .unwrap();
+
+        assert_eq!(details.transaction_code.as_deref(), Some("166"));
+        assert_eq!(details.posting_text.as_deref(), Some("SEPA-UEBERWEISUNG"));
+        assert_eq!(details.end_to_end_reference.as_deref(), Some("INV-4711"));
+        assert_eq!(
+            details.remittance_information.as_deref(),
+            Some("Rechnung 4711")
+        );
+        assert_eq!(details.counterparty_bank.as_deref(), Some("COBADEFFXXX"));
+        assert_eq!(
+            details.counterparty_account.as_deref(),
+            Some("DE89370400440532013000")
+        );
+        assert_eq!(details.counterparty_name.as_deref(), Some("Max Mustermann"));
+
+        assert_eq!(InformationFormat::Gvc.decode("Interest"), None);
+    }
+
+    #[test]
+    fn test_decode_codewords() {
+        let details = InformationFormat::Codewords
+            .decode(
+                "/TRTP/SEPA OVERBOEKING/IBAN/NL44RABO0123456789/BIC/RABONL2U/NAME/\nJ. Jansen/REMI/USTD//Factuur 2020-001/EREF/NOTPROVIDED",
+            )
+            .unwrap();
+
+        assert_eq!(details.posting_text.as_deref(), Some("SEPA OVERBOEKING"));
+        assert_eq!(
+            details.counterparty_account.as_deref(),
+            Some("NL44RABO0123456789")
+        );
+        assert_eq!(details.counterparty_bank.as_deref(), Some("RABONL2U"));
+        assert_eq!(details.counterparty_name.as_deref(), Some("J. Jansen"));
+        assert_eq!(
+            details.remittance_information.as_deref(),
+            Some("Factuur 2020-001")
+        );
+        assert_eq!(details.end_to_end_reference.as_deref(), Some("NOTPROVIDED"));
+
+        let details = InformationFormat::Codewords
+            .decode("/CNTP/NL12INGB0001234567/INGBNL2A/Shop B.V./Amsterdam/")
+            .unwrap();
+        assert_eq!(details.counterparty_name.as_deref(), Some("Shop B.V."));
+
+        assert_eq!(InformationFormat::Free.decode("/NAME/J. Jansen"), None);
+        assert_eq!(InformationFormat::Codewords.decode("Interest"), None);
+    }
+}
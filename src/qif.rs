@@ -0,0 +1,95 @@
+use crate::mt940::MT940;
+use crate::statement::{Entry, Statement};
+
+// Converts an MT940 into a QIF bank account export
+pub fn qif_from_mt940(mt940: &MT940) -> String {
+    qif_from_statements(&[Statement::from(mt940)])
+}
+
+// Every statement gets an `!Account` header so several accounts can share a file.
+// Dates are written as MM/DD/YYYY which is what Quicken and most importers expect
+pub fn qif_from_statements(statements: &[Statement]) -> String {
+    let mut qif = String::new();
+
+    for statement in statements {
+        qif.push_str("!Account\n");
+        qif.push_str(&format!("N{}\n", statement.account));
+        qif.push_str("TBank\n");
+        qif.push_str("^\n");
+        qif.push_str("!Type:Bank\n");
+
+        for entry in &statement.entries {
+            qif.push_str(&transaction(statement, entry));
+        }
+    }
+
+    qif
+}
+
+fn transaction(statement: &Statement, entry: &Entry) -> String {
+    let mut lines = vec![
+        format!("D{}", entry.entry_date.format("%m/%d/%Y")),
+        format!(
+            "T{:.*}",
+            usize::from(statement.currency.exponent().unwrap_or(2)),
+            entry.signed_amount()
+        ),
+        "CX".to_string(),
+    ];
+
+    if entry.account_owner_reference != "NONREF" {
+        lines.push(format!("N{}", entry.account_owner_reference));
+    }
+
+    if let Some(supplementary_details) = &entry.supplementary_details {
+        lines.push(format!("P{supplementary_details}"));
+    }
+
+    if let Some(information) = &entry.information {
+        let memo = information
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        lines.push(format!("M{memo}"));
+    }
+
+    lines.push("^".to_string());
+
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::Result;
+
+    #[test]
+    fn test_qif_from_mt940() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let qif = qif_from_mt940(&mt940);
+        let lines: Vec<&str> = qif.lines().collect();
+
+        assert_eq!(
+            lines[..5],
+            [
+                "!Account",
+                "NDABADKKK/111111-11111111",
+                "TBank",
+                "^",
+                "!Type:Bank"
+            ]
+        );
+        assert_eq!(lines[5..9], ["D09/30/2009", "T0.23", "CX", "NInterest"]);
+        assert!(lines[9].starts_with("MFor your inform. IBAN no.: FI1111111111111111 DABADKKK"));
+        assert_eq!(lines[10], "^");
+        assert_eq!(
+            qif.matches("\n^\n").count(),
+            1 + mt940.text.transactions.len()
+        );
+        assert!(qif.contains("\nPNONREFHOLMENSKANAL\n"));
+        assert!(!qif.contains("NNONREF"));
+        Ok(())
+    }
+}
@@ -0,0 +1,349 @@
+use crate::mt940::MT940;
+use crate::utils::ParseOptions;
+use eyre::{eyre, Result};
+use std::collections::VecDeque;
+use std::io::{BufRead, ErrorKind};
+
+// A message that has been read from a stream of messages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    // the position of the message in the stream, from 0
+    pub index: usize,
+    // the byte in the input the message starts at
+    pub offset: u64,
+    // the message with CRLF line breaks
+    pub data: String,
+}
+
+impl Message {
+    pub fn mt940(&self) -> Result<MT940<'_>> {
+        MT940::new(&self.data)
+    }
+
+    pub fn mt940_with_options(&self, options: ParseOptions) -> Result<MT940<'_>> {
+        MT940::with_options(&self.data, options)
+    }
+}
+
+// Messages are a few KB at most, the limit only guards against a file that is not FIN
+pub const DEFAULT_MAX_MESSAGE_LEN: usize = 1024 * 1024;
+
+// Splits a stream of bytes into messages as they come in, without doing any IO itself.
+// A message starts at its basic header `{1:` as with `split_messages`, so only the
+// message being read is held in memory. Line breaks are written as CRLF whether they
+// came in as CRLF, LF or a stray CR, runs of CRs count as one line break
+#[derive(Debug)]
+pub struct MessageSplitter {
+    max_message_len: usize,
+    message: Vec<u8>,
+    // the byte in the input the message being read starts at
+    offset: u64,
+    // the number of bytes of input seen so far
+    position: u64,
+    index: usize,
+    pending_cr: bool,
+    // the message being read went over the limit and is being skipped
+    overflowed: bool,
+    // a `{1:` has been seen, what comes before the first one is not a message
+    started: bool,
+    ready: VecDeque<Result<Message>>,
+}
+
+impl Default for MessageSplitter {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_MESSAGE_LEN)
+    }
+}
+
+impl MessageSplitter {
+    pub fn new(max_message_len: usize) -> Self {
+        Self {
+            max_message_len,
+            message: vec![],
+            offset: 0,
+            position: 0,
+            index: 0,
+            pending_cr: false,
+            overflowed: false,
+            started: false,
+            ready: VecDeque::new(),
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        for byte in data {
+            match (*byte, self.pending_cr) {
+                (b'\r', _) => {
+                    self.pending_cr = true;
+                }
+                (b'\n', _) => {
+                    self.pending_cr = false;
+                    self.push_line_break();
+                }
+                (byte, pending_cr) => {
+                    if pending_cr {
+                        self.pending_cr = false;
+                        self.push_line_break();
+                    }
+
+                    self.push_byte(byte);
+                }
+            }
+
+            self.position += 1;
+        }
+    }
+
+    // The end of the input, what is left is the last message
+    pub fn finish(&mut self) {
+        if self.pending_cr {
+            self.pending_cr = false;
+            self.push_line_break();
+        }
+
+        self.complete(self.message.len(), self.position);
+    }
+
+    // the next message that has been read in full
+    pub fn next_message(&mut self) -> Option<Result<Message>> {
+        self.ready.pop_front()
+    }
+
+    fn push_line_break(&mut self) {
+        self.message.extend_from_slice(b"\r\n");
+        self.limit();
+    }
+
+    fn push_byte(&mut self, byte: u8) {
+        self.message.push(byte);
+
+        // the `{1:` of the next message, the `{` is two bytes back in the input
+        if self.message.ends_with(b"{1:") {
+            let start = self.message.len() - 3;
+            let offset = self.position - 2;
+
+            if start > 0 || self.overflowed {
+                self.complete(start, offset);
+            }
+
+            self.offset = offset;
+            self.started = true;
+        }
+
+        self.limit();
+    }
+
+    fn limit(&mut self) {
+        if self.message.len() <= self.max_message_len {
+            return;
+        }
+
+        // the last two bytes are kept as they could be the start of a `{1:`
+        self.overflowed = true;
+        self.message.drain(..self.message.len() - 2);
+    }
+
+    // The message is the first `end` bytes, whitespace between messages is dropped. What
+    // comes before the first message, e.g. the banner of a file, is an error of its own
+    // which does not take up an index. `next_offset` is the byte the next message starts at
+    fn complete(&mut self, end: usize, next_offset: u64) {
+        let data: Vec<u8> = self.message.drain(..end).collect();
+        let offset = self.offset;
+
+        if !self.started {
+            let overflowed = std::mem::take(&mut self.overflowed);
+
+            if overflowed || !data.iter().all(u8::is_ascii_whitespace) {
+                self.ready.push_back(Err(eyre!(
+                    "the input before byte {next_offset} is not a message, a message starts with `{{1:`"
+                )));
+            }
+            return;
+        }
+
+        let message = if self.overflowed {
+            self.overflowed = false;
+            Err(eyre!(
+                "message at byte {offset} is longer than the limit of {} bytes",
+                self.max_message_len
+            ))
+        } else if data.iter().all(u8::is_ascii_whitespace) {
+            return;
+        } else {
+            String::from_utf8(data)
+                .map(|data| Message {
+                    index: self.index,
+                    offset,
+                    data,
+                })
+                .map_err(|e| eyre!("message at byte {offset} is not valid UTF-8: {e}"))
+        };
+
+        self.index += 1;
+        self.ready.push_back(message);
+    }
+}
+
+// The `io::Error` stays the source of the report, so that a caller can tell a failing
+// input from an invalid message with `downcast_ref::<io::Error>()`
+pub(crate) fn read_error(e: std::io::Error) -> eyre::Report {
+    let message = format!("failed to read the messages: {e}");
+    eyre::Report::new(e).wrap_err(message)
+}
+
+// Reads the messages of a file one at a time, e.g. over a `BufReader<File>`. An error
+// for one message does not end the iteration, an error reading the input does
+pub struct MessageReader<R> {
+    reader: R,
+    splitter: MessageSplitter,
+    done: bool,
+}
+
+impl<R: BufRead> MessageReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_max_message_len(reader, DEFAULT_MAX_MESSAGE_LEN)
+    }
+
+    pub fn with_max_message_len(reader: R, max_message_len: usize) -> Self {
+        Self {
+            reader,
+            splitter: MessageSplitter::new(max_message_len),
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for MessageReader<R> {
+    type Item = Result<Message>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(message) = self.splitter.next_message() {
+                return Some(message);
+            }
+
+            if self.done {
+                return None;
+            }
+
+            let data = match self.reader.fill_buf() {
+                Ok(data) => data,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(read_error(e)));
+                }
+            };
+
+            if data.is_empty() {
+                self.splitter.finish();
+                self.done = true;
+                continue;
+            }
+
+            let len = data.len();
+            self.splitter.push(data);
+            self.reader.consume(len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::split_messages;
+    use std::io::BufReader;
+
+    fn read(data: &[u8], capacity: usize) -> Vec<Result<Message>> {
+        MessageReader::new(BufReader::with_capacity(capacity, data)).collect()
+    }
+
+    #[test]
+    fn test_message_reader() -> Result<()> {
+        let first = include_str!("./test_messages/basic_test_1.txt");
+        let second = include_str!("./test_messages/basic_test_4.txt");
+        let data = format!("\n{first}\n{second}\n");
+
+        // a buffer of one byte splits every line break and `{1:` over two reads
+        for capacity in [1, 7, 8192] {
+            let messages = read(data.as_bytes(), capacity)
+                .into_iter()
+                .collect::<Result<Vec<_>>>()?;
+
+            assert_eq!(messages.len(), 2);
+            assert_eq!(messages[1].index, 1);
+            assert_eq!(messages[1].offset, data.rfind("{1:").unwrap() as u64);
+
+            for (message, expected) in messages.iter().zip(split_messages(&data)) {
+                assert_eq!(message.mt940()?, MT940::new(expected)?);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_reader_line_breaks() -> Result<()> {
+        let message = include_str!("./test_messages/basic_test_1.txt").replace("\r\n", "\n");
+        let expected = message.trim_end().replace('\n', "\r\n");
+
+        for line_break in ["\r\n", "\n", "\r", "\r\r\n"] {
+            let data = message.replace('\n', line_break);
+            let messages = read(data.as_bytes(), 3);
+
+            assert_eq!(messages.len(), 1);
+            assert_eq!(messages[0].as_ref().unwrap().data.trim_end(), expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_message_reader_errors() {
+        let message = include_str!("./test_messages/basic_test_1.txt");
+        let long = format!("{{1:{}", "x".repeat(300));
+        let data = [long.as_bytes(), b"{1:\xff\xfe}", message.as_bytes()].concat();
+
+        let messages: Vec<Result<Message>> =
+            MessageReader::with_max_message_len(data.as_slice(), 256).collect();
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[0].as_ref().unwrap_err().to_string(),
+            "message at byte 0 is longer than the limit of 256 bytes"
+        );
+        assert!(messages[1]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .starts_with("message at byte 303 is not valid UTF-8"));
+        assert_eq!(messages[2].as_ref().unwrap().index, 2);
+        assert!(messages[2].as_ref().unwrap().mt940().is_ok());
+    }
+
+    #[test]
+    fn test_message_reader_leading_junk() {
+        let message = include_str!("./test_messages/basic_test_1.txt");
+        let expected = "the input before byte 17 is not a message, a message starts with `{1:`";
+
+        // a banner, also one longer than the limit, is reported once and takes no index
+        for banner in ["Statements 2020-01\n", &"=".repeat(300)] {
+            let data = format!("{banner}{message}");
+            let messages: Vec<Result<Message>> =
+                MessageReader::with_max_message_len(data.as_bytes(), 256).collect();
+            let expected = expected.replace("17", &banner.len().to_string());
+
+            assert_eq!(messages.len(), 2);
+            assert_eq!(messages[0].as_ref().unwrap_err().to_string(), expected);
+            assert_eq!(messages[1].as_ref().unwrap().index, 0);
+            assert_eq!(messages[1].as_ref().unwrap().offset, banner.len() as u64);
+        }
+
+        // input without any message is reported as a whole, blank input is no message
+        let messages = read(b"not a statement\n", 4);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(
+            messages[0].as_ref().unwrap_err().to_string(),
+            "the input before byte 16 is not a message, a message starts with `{1:`"
+        );
+        assert!(read(b" \r\n\n", 4).is_empty());
+    }
+}
@@ -0,0 +1,328 @@
+use crate::block::Text;
+use crate::utils::{BalanceType, CreditDebit};
+use iso_currency::Currency;
+use serde::{Deserialize, Serialize};
+
+// https://www2.swift.com/knowledgecentre/publications/us9m_20230720/?topic=mt940-network-validated-rules.htm
+
+// A rule of a message type that a parsed message did not keep to. Network validated rules
+// carry the error code SWIFT rejects the message with, usage rules are not checked by
+// the network and have none
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Violation {
+    pub rule: String,
+    pub error_code: Option<String>,
+    pub tags: Vec<String>,
+    pub message: String,
+}
+
+impl Violation {
+    fn new(rule: &Rule, tags: &[&str], message: String) -> Self {
+        Self {
+            rule: rule.id.to_string(),
+            error_code: rule.error_code.map(str::to_string),
+            tags: tags.iter().map(|x| x.to_string()).collect(),
+            message,
+        }
+    }
+}
+
+pub struct Rule {
+    pub id: &'static str,
+    pub error_code: Option<&'static str>,
+    pub description: &'static str,
+    check: fn(&Rule, &Text) -> Vec<Violation>,
+}
+
+impl Rule {
+    pub fn check(&self, text: &Text) -> Vec<Violation> {
+        (self.check)(self, text)
+    }
+}
+
+// The network validated rules (C) and the usage rules (U) of the MT940
+pub const MT940_RULES: &[Rule] = &[
+    Rule {
+        id: "C1",
+        error_code: Some("C24"),
+        description: "an :86: must follow a :61:, except for a single :86: at the end of the message",
+        check: information_follows_statement_line,
+    },
+    Rule {
+        id: "C2",
+        error_code: Some("C27"),
+        description: "the currency codes of :60a:, :62a:, :64: and :65: must start with the same two characters",
+        check: same_currency,
+    },
+    Rule {
+        id: "U1",
+        error_code: None,
+        description: "the first page of a statement (:28C: sequence number 1) opens with :60F:, later pages with :60M:",
+        check: opening_balance_matches_sequence_number,
+    },
+    Rule {
+        id: "U2",
+        error_code: None,
+        description: "the opening balance plus the statement lines must equal the closing balance",
+        check: balances_reconcile,
+    },
+    Rule {
+        id: "U3",
+        error_code: None,
+        description: "the closing balance cannot be dated before the opening balance, forward available balances must be dated after it",
+        check: balance_dates_in_order,
+    },
+];
+
+// Checks the text of an MT940 against all of its rules. A message can break several
+// rules at once, so all of them are reported rather than the first
+pub fn mt940_violations(text: &Text) -> Vec<Violation> {
+    MT940_RULES
+        .iter()
+        .flat_map(|rule| rule.check(text))
+        .collect()
+}
+
+// The statement level :86: of the text block can only be told apart from one that is the
+// last field, so only a repeated :86: is reported
+fn information_follows_statement_line(rule: &Rule, text: &Text) -> Vec<Violation> {
+    let mut violations = vec![];
+
+    for (i, transaction) in text.transactions.iter().enumerate() {
+        if transaction.tag_86.len() > 1 {
+            violations.push(Violation::new(
+                rule,
+                &["61", "86"],
+                format!(
+                    "statement line {} is followed by {} :86: fields instead of one",
+                    i + 1,
+                    transaction.tag_86.len()
+                ),
+            ));
+        }
+    }
+
+    if text.tag_86.len() > 1 {
+        violations.push(Violation::new(
+            rule,
+            &["86"],
+            format!(
+                "the message has {} :86: fields that do not follow a :61:",
+                text.tag_86.len()
+            ),
+        ));
+    }
+
+    violations
+}
+
+fn same_currency(rule: &Rule, text: &Text) -> Vec<Violation> {
+    let opening = text.tag_60.balance_data.currency;
+    let mut balances = vec![("62a", text.tag_62.balance_data.currency)];
+
+    if let Some(tag_64) = &text.tag_64 {
+        balances.push(("64", tag_64.balance_data.currency));
+    }
+
+    for tag_65 in &text.tag_65 {
+        balances.push(("65", tag_65.balance_data.currency));
+    }
+
+    balances
+        .into_iter()
+        .filter(|(_, currency)| currency.code()[..2] != opening.code()[..2])
+        .map(|(tag, currency)| {
+            Violation::new(
+                rule,
+                &["60a", tag],
+                format!(
+                    "the currency '{}' of :{tag}: does not match the currency '{}' of :60a:",
+                    currency.code(),
+                    opening.code()
+                ),
+            )
+        })
+        .collect()
+}
+
+fn opening_balance_matches_sequence_number(rule: &Rule, text: &Text) -> Vec<Violation> {
+    let sequence_number = text.tag_28c.sequence_number;
+    let balance_type = text.tag_60.balance_type;
+
+    let expected = match sequence_number {
+        1 => BalanceType::Final,
+        _ => BalanceType::Intermediary,
+    };
+
+    if balance_type == expected {
+        return vec![];
+    }
+
+    vec![Violation::new(
+        rule,
+        &["28C", "60a"],
+        format!(
+            "page {sequence_number} of the statement opens with :{}: instead of :{}:",
+            tag_60(balance_type),
+            tag_60(expected)
+        ),
+    )]
+}
+
+fn tag_60(balance_type: BalanceType) -> &'static str {
+    match balance_type {
+        BalanceType::Final => "60F",
+        BalanceType::Intermediary => "60M",
+    }
+}
+
+// compared in minor units of the currency so that the sum of the lines is exact
+fn balances_reconcile(rule: &Rule, text: &Text) -> Vec<Violation> {
+    let opening = &text.tag_60.balance_data;
+    let closing = &text.tag_62.balance_data;
+
+    let lines: i64 = text
+        .transactions
+        .iter()
+        .map(|x| {
+            let amount = minor_units(opening.currency, x.tag_61.amount);
+
+            match x.tag_61.debit_or_credit {
+                CreditDebit::Credit | CreditDebit::DebitReversal => amount,
+                CreditDebit::Debit | CreditDebit::CreditReversal => -amount,
+            }
+        })
+        .sum();
+
+    let expected = minor_units(opening.currency, opening.signed_amount()) + lines;
+    let actual = minor_units(opening.currency, closing.signed_amount());
+
+    if expected == actual {
+        return vec![];
+    }
+
+    let exponent = usize::from(opening.currency.exponent().unwrap_or(2));
+
+    vec![Violation::new(
+        rule,
+        &["60a", "61", "62a"],
+        format!(
+            "the opening balance and the statement lines add up to {:.*} but the closing balance is {:.*}",
+            exponent,
+            from_minor_units(opening.currency, expected),
+            exponent,
+            closing.signed_amount()
+        ),
+    )]
+}
+
+fn balance_dates_in_order(rule: &Rule, text: &Text) -> Vec<Violation> {
+    let opening = text.tag_60.balance_data.date;
+    let closing = text.tag_62.balance_data.date;
+    let mut violations = vec![];
+
+    if closing < opening {
+        violations.push(Violation::new(
+            rule,
+            &["60a", "62a"],
+            format!("the closing balance is dated {closing} which is before the opening balance on {opening}"),
+        ));
+    }
+
+    for tag_65 in &text.tag_65 {
+        let date = tag_65.balance_data.date;
+
+        if date <= closing {
+            violations.push(Violation::new(
+                rule,
+                &["62a", "65"],
+                format!("the forward available balance is dated {date} which is not after the closing balance on {closing}"),
+            ));
+        }
+    }
+
+    violations
+}
+
+fn minor_units(currency: Currency, amount: f64) -> i64 {
+    (amount * 10f64.powi(i32::from(currency.exponent().unwrap_or(2)))).round() as i64
+}
+
+fn from_minor_units(currency: Currency, amount: i64) -> f64 {
+    amount as f64 / 10f64.powi(i32::from(currency.exponent().unwrap_or(2)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mt940::MT940;
+    use eyre::Result;
+
+    fn violations(message: &str) -> Result<Vec<Violation>> {
+        Ok(MT940::new(message)?.violations())
+    }
+
+    fn rules(violations: &[Violation]) -> Vec<&str> {
+        violations.iter().map(|x| x.rule.as_str()).collect()
+    }
+
+    #[test]
+    fn test_no_violations() -> Result<()> {
+        assert!(violations(include_str!("./test_messages/basic_test_1.txt"))?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeated_information_to_account_owner() -> Result<()> {
+        let violations = violations(include_str!("./test_messages/basic_test_4.txt"))?;
+        let c1: Vec<&Violation> = violations.iter().filter(|x| x.rule == "C1").collect();
+
+        assert_eq!(c1.len(), 1);
+        assert_eq!(c1[0].error_code.as_deref(), Some("C24"));
+        assert_eq!(c1[0].tags, ["61", "86"]);
+        assert_eq!(
+            c1[0].message,
+            "statement line 1 is followed by 4 :86: fields instead of one"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_several_violations() -> Result<()> {
+        let message = include_str!("./test_messages/basic_test_1.txt")
+            .replace(":28C:3/1", ":28C:3/2")
+            .replace(
+                ":62F:C200103EUR379,29",
+                ":62F:C200102EUR379,29\r\n:64:C200103USD379,29",
+            );
+        let violations = violations(&message)?;
+
+        assert_eq!(rules(&violations), ["C2", "U1", "U3"]);
+        assert_eq!(
+            violations[0].message,
+            "the currency 'USD' of :64: does not match the currency 'EUR' of :60a:"
+        );
+        assert_eq!(
+            violations[1].message,
+            "page 2 of the statement opens with :60F: instead of :60M:"
+        );
+        assert_eq!(violations[2].error_code, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_balances_do_not_reconcile() -> Result<()> {
+        let message = include_str!("./test_messages/basic_test_1.txt").replace(
+            ":62F:C200103EUR379,29",
+            ":61:2001030103D79,29NTRFNONREF\r\n:62F:C200103EUR310,00",
+        );
+        let violations = violations(&message)?;
+
+        assert_eq!(rules(&violations), ["U2"]);
+        assert_eq!(
+            violations[0].message,
+            "the opening balance and the statement lines add up to 300.00 but the closing balance is 310.00"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,707 @@
+use crate::charset::Charset;
+use eyre::{eyre, Result};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// https://www2.swift.com/knowledgecentre/publications/us9m_20230720/?topic=con_31519.htm
+
+// The format of the tags we read and write, in the notation of the SWIFT user handbook:
+//   n digits, a upper case letters, c upper case letters and digits, d a decimal with a
+//   comma, x, y and z the character sets of the same name
+//   `16x` up to 16 characters, `3!a` exactly 3, `4*35x` up to 4 lines of up to 35
+//   `[...]` an optional part, anything else is written as is
+// The handbook puts parts on a new line, here that line break is written as `$`
+// formats shared by several tags
+pub const BALANCE: &str = "1!a6!n3!a15d";
+pub const CURRENCY_AMOUNT: &str = "3!a15d";
+pub const BIC: &str = "4!a2!a2!c[3!c]";
+
+pub const FORMAT_SPECS: &[(&str, &str)] = &[
+    // block 3
+    ("103", "3!a"),
+    ("108", "16x"),
+    ("113", "4!x"),
+    ("424", "16x"),
+    // block 4
+    ("13C", "/8c/4!n1!x4!n"),
+    ("20", "16x"),
+    ("21", "16x"),
+    ("23B", "4!c"),
+    ("23E", "4!c[/30x]"),
+    ("25", "35x"),
+    ("26T", "3!c"),
+    ("28C", "5n[/5n]"),
+    ("32A", "6!n3!a15d"),
+    ("33B", CURRENCY_AMOUNT),
+    ("36", "12d"),
+    ("50A", "[/34x$]4!a2!a2!c[3!c]"),
+    ("50F", "35x$4*35x"),
+    ("50K", "[/34x$]4*35x"),
+    ("52A", "[/1!a][/34x$]4!a2!a2!c[3!c]"),
+    ("52D", "[/1!a][/34x$]4*35x"),
+    ("53A", "[/1!a][/34x$]4!a2!a2!c[3!c]"),
+    ("53B", "[/1!a][/34x][$35x]"),
+    ("53D", "[/1!a][/34x$]4*35x"),
+    ("54A", "[/1!a][/34x$]4!a2!a2!c[3!c]"),
+    ("54B", "[/1!a][/34x][$35x]"),
+    ("54D", "[/1!a][/34x$]4*35x"),
+    ("56A", "[/1!a][/34x$]4!a2!a2!c[3!c]"),
+    ("56D", "[/1!a][/34x$]4*35x"),
+    ("57A", "[/1!a][/34x$]4!a2!a2!c[3!c]"),
+    ("57B", "[/1!a][/34x][$35x]"),
+    ("57D", "[/1!a][/34x$]4*35x"),
+    ("59", "[/34x$]4*35x"),
+    ("59A", "[/34x$]4!a2!a2!c[3!c]"),
+    ("59F", "[/34x$]4*35x"),
+    ("60F", BALANCE),
+    ("60M", BALANCE),
+    ("61", "6!n[4!n]2a[1!a]15d1!a3!c16x[//16x][$34x]"),
+    ("62F", BALANCE),
+    ("62M", BALANCE),
+    ("64", BALANCE),
+    ("65", BALANCE),
+    ("70", "4*35x"),
+    ("71A", "3!a"),
+    ("71F", CURRENCY_AMOUNT),
+    ("71G", CURRENCY_AMOUNT),
+    ("72", "6*35x"),
+    ("77B", "3*35x"),
+    ("77T", "9000z"),
+    ("86", "6*65x"),
+];
+
+pub fn format_spec(tag: &str) -> Option<&'static str> {
+    FORMAT_SPECS
+        .iter()
+        .find(|(x, _)| *x == tag)
+        .map(|(_, spec)| *spec)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharacterClass {
+    Numeric,
+    Alphabetic,
+    Alphanumeric,
+    Decimal,
+    Charset(Charset),
+    // any character but a line break, see `FormatSpec::layout`
+    Any,
+}
+
+impl TryFrom<char> for CharacterClass {
+    type Error = eyre::Error;
+
+    fn try_from(input: char) -> Result<Self> {
+        match input {
+            'n' => Ok(Self::Numeric),
+            'a' => Ok(Self::Alphabetic),
+            'c' => Ok(Self::Alphanumeric),
+            'd' => Ok(Self::Decimal),
+            'x' => Ok(Self::Charset(Charset::X)),
+            'y' => Ok(Self::Charset(Charset::Y)),
+            'z' => Ok(Self::Charset(Charset::Z)),
+            _ => Err(eyre!(
+                "Character Class is either missing or the value '{input}' is not valid"
+            )),
+        }
+    }
+}
+
+impl CharacterClass {
+    // a line break is never part of a class, it is written as `$` or comes between lines
+    pub fn contains(&self, c: char) -> bool {
+        match self {
+            Self::Numeric => c.is_ascii_digit(),
+            Self::Alphabetic => c.is_ascii_uppercase(),
+            Self::Alphanumeric => c.is_ascii_uppercase() || c.is_ascii_digit(),
+            Self::Decimal => c.is_ascii_digit() || c == ',',
+            Self::Charset(charset) => c != '\r' && c != '\n' && charset.contains(c),
+            Self::Any => c != '\r' && c != '\n',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Component {
+    // `/`, `//` or a line break (`$`)
+    Literal(String),
+    // `16x`, `3!a` or `4*35x`, each is a subfield of the parsed value
+    Characters {
+        class: CharacterClass,
+        length: usize,
+        fixed: bool,
+        lines: usize,
+        subfield: usize,
+    },
+    Optional(Vec<Component>),
+}
+
+// A parsed format specification, which splits a value into its subfields
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatSpec {
+    pub notation: String,
+    pub components: Vec<Component>,
+    pub subfield_count: usize,
+}
+
+impl FormatSpec {
+    pub fn new(notation: &str) -> Result<Self> {
+        let mut subfield_count = 0;
+        let mut chars = notation.chars().peekable();
+        let components = Self::components(&mut chars, &mut subfield_count, notation)?;
+
+        if chars.next().is_some() {
+            return Err(eyre!("format '{notation}' has an unmatched ']'"));
+        }
+
+        Ok(Self {
+            notation: notation.to_string(),
+            components,
+            subfield_count,
+        })
+    }
+
+    // the format of a tag from `FORMAT_SPECS`
+    pub fn for_tag(tag: &str) -> Result<&'static Self> {
+        Self::built_in(format_spec(tag).ok_or_else(|| eyre!("tag {tag} has no known format"))?)
+    }
+
+    // A format of `FORMAT_SPECS` or one of the shared formats such as `BALANCE`. These are
+    // parsed once rather than for every field
+    pub fn built_in(notation: &str) -> Result<&'static Self> {
+        Ok(&built_in_format(notation)?.0)
+    }
+
+    fn components(
+        chars: &mut std::iter::Peekable<std::str::Chars>,
+        subfield_count: &mut usize,
+        notation: &str,
+    ) -> Result<Vec<Component>> {
+        let mut components = vec![];
+
+        while let Some(&c) = chars.peek() {
+            match c {
+                '[' => {
+                    chars.next();
+                    let optional = Self::components(chars, subfield_count, notation)?;
+
+                    if chars.next() != Some(']') {
+                        return Err(eyre!("format '{notation}' has an unmatched '['"));
+                    }
+
+                    components.push(Component::Optional(optional));
+                }
+                ']' => break,
+                '$' => {
+                    chars.next();
+                    components.push(Component::Literal("\n".to_string()));
+                }
+                '0'..='9' => {
+                    let mut length = number(chars);
+                    let mut lines = 1;
+                    let mut fixed = false;
+
+                    if chars.next_if_eq(&'*').is_some() {
+                        lines = length;
+                        length = number(chars);
+                    } else if chars.next_if_eq(&'!').is_some() {
+                        fixed = true;
+                    }
+
+                    let class = chars
+                        .next()
+                        .ok_or_else(|| eyre!("format '{notation}' ends without a class"))?;
+
+                    if length == 0 || lines == 0 {
+                        return Err(eyre!("format '{notation}' has a zero length"));
+                    }
+
+                    components.push(Component::Characters {
+                        class: CharacterClass::try_from(class)?,
+                        length,
+                        fixed,
+                        lines,
+                        subfield: *subfield_count,
+                    });
+                    *subfield_count += 1;
+                }
+                _ => {
+                    chars.next();
+                    match components.last_mut() {
+                        Some(Component::Literal(literal)) if literal != "\n" => literal.push(c),
+                        _ => components.push(Component::Literal(c.to_string())),
+                    }
+                }
+            }
+        }
+
+        Ok(components)
+    }
+
+    // Splits a value into its subfields, in the order they appear in the notation. An
+    // optional part that is absent leaves its subfields as `None`
+    pub fn parse<'a>(&self, value: &'a str) -> Result<Vec<Option<&'a str>>> {
+        let mut subfields = vec![None; self.subfield_count];
+
+        if !walk(&[&self.components], value, 0, &mut subfields) {
+            return Err(eyre!(
+                "'{value}' does not match the format '{}'",
+                self.notation
+            ));
+        }
+
+        Ok(subfields
+            .into_iter()
+            .map(|x| x.map(|(start, end)| &value[start..end]))
+            .collect())
+    }
+
+    // The same format with the character sets of the free text lifted, which checks the
+    // number and length of the lines and the layout of the subfields only
+    pub fn layout(&self) -> Self {
+        fn lift(components: &[Component]) -> Vec<Component> {
+            components
+                .iter()
+                .map(|component| match component {
+                    Component::Characters {
+                        class: CharacterClass::Charset(_),
+                        length,
+                        fixed,
+                        lines,
+                        subfield,
+                    } => Component::Characters {
+                        class: CharacterClass::Any,
+                        length: *length,
+                        fixed: *fixed,
+                        lines: *lines,
+                        subfield: *subfield,
+                    },
+                    Component::Optional(optional) => Component::Optional(lift(optional)),
+                    component => component.clone(),
+                })
+                .collect()
+        }
+
+        Self {
+            notation: self.notation.clone(),
+            components: lift(&self.components),
+            subfield_count: self.subfield_count,
+        }
+    }
+
+    // The widest character set of the format, the n, a, c and d classes are all part of X
+    pub fn charset(&self) -> Charset {
+        fn widest(components: &[Component]) -> Charset {
+            components
+                .iter()
+                .map(|component| match component {
+                    Component::Characters {
+                        class: CharacterClass::Charset(charset),
+                        ..
+                    } => *charset,
+                    Component::Optional(optional) => widest(optional),
+                    _ => Charset::X,
+                })
+                .max_by_key(|charset| match charset {
+                    Charset::X => 0,
+                    Charset::Y => 1,
+                    Charset::Z => 2,
+                })
+                .unwrap_or(Charset::X)
+        }
+
+        widest(&self.components)
+    }
+}
+
+fn number(chars: &mut std::iter::Peekable<std::str::Chars>) -> usize {
+    let mut number = 0;
+
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        chars.next();
+        number = number * 10 + digit as usize;
+    }
+
+    number
+}
+
+// Matches the pending components against the value from `position`, backtracking
+// through the lengths of the subfields and the optional parts. `pending` is a stack of
+// the rest of each enclosing sequence, innermost first
+fn walk(
+    pending: &[&[Component]],
+    value: &str,
+    position: usize,
+    subfields: &mut Vec<Option<(usize, usize)>>,
+) -> bool {
+    let index = match pending.iter().position(|x| !x.is_empty()) {
+        Some(index) => index,
+        None => return position == value.len(),
+    };
+
+    let (component, rest) = pending[index].split_first().expect("not empty");
+    let mut next: Vec<&[Component]> = vec![rest];
+    next.extend_from_slice(&pending[index + 1..]);
+
+    match component {
+        Component::Literal(literal) => {
+            let remaining = &value[position..];
+
+            let length = if literal == "\n" {
+                if remaining.starts_with("\r\n") {
+                    2
+                } else if remaining.starts_with('\n') {
+                    1
+                } else {
+                    return false;
+                }
+            } else if remaining.starts_with(literal.as_str()) {
+                literal.len()
+            } else {
+                return false;
+            };
+
+            walk(&next, value, position + length, subfields)
+        }
+        Component::Optional(optional) => {
+            let mut with: Vec<&[Component]> = vec![optional];
+            with.extend_from_slice(&next);
+
+            if walk(&with, value, position, subfields) {
+                return true;
+            }
+
+            clear(optional, subfields);
+            walk(&next, value, position, subfields)
+        }
+        Component::Characters {
+            class,
+            length,
+            fixed,
+            lines,
+            subfield,
+        } => {
+            // a subfield stops before a literal that follows it, so 16x[//16x] splits at `//`
+            let stop = match next.iter().find_map(|x| x.first()) {
+                Some(Component::Literal(literal)) if literal != "\n" => Some(literal.as_str()),
+                Some(Component::Optional(optional)) => match optional.first() {
+                    Some(Component::Literal(literal)) if literal != "\n" => Some(literal.as_str()),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            for end in candidates(value, position, *class, *length, *fixed, *lines, stop) {
+                subfields[*subfield] = Some((position, end));
+
+                if walk(&next, value, end, subfields) {
+                    return true;
+                }
+            }
+
+            false
+        }
+    }
+}
+
+fn clear(components: &[Component], subfields: &mut Vec<Option<(usize, usize)>>) {
+    for component in components {
+        match component {
+            Component::Characters { subfield, .. } => subfields[*subfield] = None,
+            Component::Optional(optional) => clear(optional, subfields),
+            Component::Literal(_) => {}
+        }
+    }
+}
+
+// The positions a subfield starting at `position` could end at, longest first
+fn candidates(
+    value: &str,
+    position: usize,
+    class: CharacterClass,
+    length: usize,
+    fixed: bool,
+    lines: usize,
+    stop: Option<&str>,
+) -> Vec<usize> {
+    let mut ends = vec![];
+    let mut line_start = position;
+
+    for line in 0..lines {
+        if line > 0 {
+            let remaining = &value[line_start..];
+            line_start += if remaining.starts_with("\r\n") {
+                2
+            } else if remaining.starts_with('\n') {
+                1
+            } else {
+                break;
+            };
+        }
+
+        let mut line_ends = vec![];
+        for (count, (offset, c)) in value[line_start..].char_indices().enumerate() {
+            if count == length
+                || !class.contains(c)
+                || stop.is_some_and(|stop| value[line_start + offset..].starts_with(stop))
+            {
+                break;
+            }
+
+            line_ends.push((count + 1, line_start + offset + c.len_utf8()));
+        }
+
+        // a multi line subfield is made up of whole lines
+        if lines > 1 {
+            match line_ends.last() {
+                Some(&(_, end))
+                    if value[end..].is_empty()
+                        || value[end..].starts_with('\n')
+                        || value[end..].starts_with("\r\n") =>
+                {
+                    ends.push(end);
+                    line_start = end;
+                }
+                _ => break,
+            }
+            continue;
+        }
+
+        ends.extend(
+            line_ends
+                .into_iter()
+                .filter(|(count, _)| !fixed || *count == length)
+                .map(|(_, end)| end)
+                .filter(|end| {
+                    class != CharacterClass::Decimal || is_decimal(&value[position..*end])
+                }),
+        );
+    }
+
+    ends.reverse();
+    ends
+}
+
+// a SWIFT amount has a comma as decimal separator, which is required, and digits before it
+// the built in formats and their layouts
+static BUILT_IN_FORMATS: OnceLock<HashMap<&str, (FormatSpec, FormatSpec)>> = OnceLock::new();
+
+fn built_in_format(notation: &str) -> Result<&'static (FormatSpec, FormatSpec)> {
+    BUILT_IN_FORMATS
+        .get_or_init(|| {
+            FORMAT_SPECS
+                .iter()
+                .map(|(_, notation)| *notation)
+                .chain([BALANCE, CURRENCY_AMOUNT, BIC])
+                .filter_map(|notation| {
+                    let spec = FormatSpec::new(notation).ok()?;
+                    let layout = spec.layout();
+                    Some((notation, (spec, layout)))
+                })
+                .collect()
+        })
+        .get(notation)
+        .ok_or_else(|| eyre!("format '{notation}' is not a built in format"))
+}
+
+fn is_decimal(value: &str) -> bool {
+    value.matches(',').count() == 1 && !value.starts_with(',')
+}
+
+// Splits the value of a tag into the subfields of its format
+pub fn subfields<'a>(tag: &str, value: &'a str) -> Result<Vec<Option<&'a str>>> {
+    FormatSpec::for_tag(tag)?
+        .parse(value)
+        .map_err(|e| eyre!("tag {tag} {e}"))
+}
+
+// Checks the lines and subfields of a tag but not its character set. Banks send free text
+// with `&` or accented letters all the time, `charset::validate_message` reports those
+pub fn validate_layout(tag: &str, value: &str) -> Result<()> {
+    if let Some(notation) = format_spec(tag) {
+        built_in_format(notation)?
+            .1
+            .parse(value)
+            .map_err(|e| eyre!("tag {tag} {e}"))?;
+    }
+
+    Ok(())
+}
+
+// Checks the value of a tag against its format, tags without a known format pass
+pub fn validate(tag: &str, value: &str) -> Result<()> {
+    if format_spec(tag).is_some() {
+        subfields(tag, value)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_spec_notation() -> Result<()> {
+        let spec = FormatSpec::new("4!c[/30x]")?;
+
+        assert_eq!(spec.subfield_count, 2);
+        assert_eq!(
+            spec.components[1],
+            Component::Optional(vec![
+                Component::Literal("/".to_string()),
+                Component::Characters {
+                    class: CharacterClass::Charset(Charset::X),
+                    length: 30,
+                    fixed: false,
+                    lines: 1,
+                    subfield: 1,
+                }
+            ])
+        );
+        assert_eq!(FormatSpec::for_tag("77T")?.charset(), Charset::Z);
+        assert_eq!(FormatSpec::for_tag("86")?.charset(), Charset::X);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_spec_invalid_notation() {
+        assert!(FormatSpec::new("[16x").is_err());
+        assert!(FormatSpec::new("16x]").is_err());
+        assert!(FormatSpec::new("16q").is_err());
+        assert!(FormatSpec::new("0!a").is_err());
+        assert!(FormatSpec::for_tag("99").is_err());
+        assert!(FormatSpec::built_in("17x").is_err());
+    }
+
+    #[test]
+    fn test_built_in_formats() -> Result<()> {
+        for (tag, notation) in FORMAT_SPECS {
+            assert_eq!(FormatSpec::for_tag(tag)?, &FormatSpec::new(notation)?);
+        }
+
+        assert_eq!(FormatSpec::built_in(BIC)?.notation, BIC);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_spec_fixed_and_maximum_length() -> Result<()> {
+        assert_eq!(
+            subfields("20", "3996-11-11111111")?,
+            [Some("3996-11-11111111")]
+        );
+        assert!(subfields("20", "3996-11-111111112").is_err());
+        assert!(subfields("20", "").is_err());
+
+        assert_eq!(subfields("103", "CAD")?, [Some("CAD")]);
+        assert!(subfields("103", "CA").is_err());
+        assert!(subfields("103", "cad").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_spec_decimal() -> Result<()> {
+        assert_eq!(
+            subfields("32A", "200103EUR1000,50")?,
+            [Some("200103"), Some("EUR"), Some("1000,50")]
+        );
+        assert_eq!(subfields("36", "0,9236")?, [Some("0,9236")]);
+        assert!(subfields("36", "0.9236").is_err());
+        assert!(subfields("36", "1000").is_err());
+        assert!(subfields("36", ",5").is_err());
+        assert!(subfields("33B", "EUR1000,50,0").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_spec_optional() -> Result<()> {
+        assert_eq!(subfields("28C", "00001/001")?, [Some("00001"), Some("001")]);
+        assert_eq!(subfields("28C", "3")?, [Some("3"), None]);
+        assert!(subfields("28C", "000001/001").is_err());
+
+        assert_eq!(subfields("23E", "PHOB")?, [Some("PHOB"), None]);
+        assert_eq!(
+            subfields("23E", "PHOB/+44 20 7946 0000")?,
+            [Some("PHOB"), Some("+44 20 7946 0000")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_spec_stops_before_literal() -> Result<()> {
+        let statement_line = subfields("61", "0909250925DR583,92NMSC1110030403010139//1234")?;
+
+        assert_eq!(statement_line[7], Some("1110030403010139"));
+        assert_eq!(statement_line[8], Some("1234"));
+        assert_eq!(statement_line[9], None);
+
+        let statement_line =
+            subfields("61", "0910010930CR0,23FINTInterest\r\nTo be paid quarterly")?;
+
+        assert_eq!(statement_line[2], Some("CR"));
+        assert_eq!(statement_line[3], None);
+        assert_eq!(statement_line[7], Some("Interest"));
+        assert_eq!(statement_line[9], Some("To be paid quarterly"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_spec_lines() -> Result<()> {
+        assert_eq!(
+            subfields("59", "/DE89370400440532013000\nBENEFICIARY NAME\nSTREET 1")?,
+            [
+                Some("DE89370400440532013000"),
+                Some("BENEFICIARY NAME\nSTREET 1")
+            ]
+        );
+        assert_eq!(
+            subfields("59", "NAME\r\nSTREET 1")?,
+            [None, Some("NAME\r\nSTREET 1")]
+        );
+        assert!(subfields("70", "1\n2\n3\n4\n5").is_err());
+        assert!(subfields("70", &"X".repeat(36)).is_err());
+        assert!(subfields("70", "LINE\n\nLINE").is_err());
+
+        assert_eq!(
+            subfields("52A", "/D/12345678\nBARCGB22")?,
+            [
+                Some("D"),
+                Some("12345678"),
+                Some("BARC"),
+                Some("GB"),
+                Some("22"),
+                None
+            ]
+        );
+        assert_eq!(
+            subfields("57A", "DEUTDEFFXXX")?,
+            [
+                None,
+                None,
+                Some("DEUT"),
+                Some("DE"),
+                Some("FF"),
+                Some("XXX")
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_layout() {
+        assert!(validate("86", "Smith & Sons").is_err());
+        assert!(validate_layout("86", "Smith & Sons").is_ok());
+        assert!(validate_layout("86", &"X".repeat(66)).is_err());
+        assert!(validate_layout("86", "1\n2\n3\n4\n5\n6\n7").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("86", "Interest\nFor your inform.").is_ok());
+        assert!(validate("999", "anything goes").is_ok());
+        assert_eq!(
+            validate("71A", "SHAR").unwrap_err().to_string(),
+            "tag 71A 'SHAR' does not match the format '3!a'"
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use crate::block::Transaction;
+use crate::mt940::MT940;
+use crate::utils::{Balance, BalanceType, CreditDebit, TransactionType, TransactionTypeMarker};
+use chrono::{NaiveDate, NaiveDateTime};
+use eyre::{eyre, Result};
+use iso_currency::Currency;
+use serde::Serialize;
+
+// An owned statement that does not care where it came from. It is built from an
+// MT940 (FIN) or read from a camt.053/camt.052 (MX) document or a BAI2 file, and is what the exports work on
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Statement {
+    pub source: StatementSource,
+    pub reference: String,
+    pub account: String,
+    pub currency: Currency,
+    pub statement_number: Option<u32>,
+    pub sequence_number: Option<u32>,
+    pub creation_date_time: Option<NaiveDateTime>,
+    pub opening_balance: Option<StatementBalance>,
+    pub closing_balance: Option<StatementBalance>,
+    pub closing_available_balance: Option<Balance>,
+    pub forward_available_balances: Vec<Balance>,
+    pub entries: Vec<Entry>,
+    pub information: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StatementSource {
+    Mt940,
+    Camt053,
+    Camt052,
+    Bai2,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StatementBalance {
+    pub balance_type: BalanceType,
+    pub balance_data: Balance,
+}
+
+// A single booking, the equivalent of a :61: with its :86: or a camt `Ntry`
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Entry {
+    pub status: EntryStatus,
+    pub value_date: NaiveDate,
+    pub entry_date: NaiveDate,
+    pub debit_or_credit: CreditDebit,
+    pub funds_code: Option<char>,
+    pub amount: f64,
+    pub transaction_type_marker: TransactionTypeMarker,
+    pub transaction_type: Option<TransactionType>,
+    pub identification_code: String,
+    // the ISO 20022 domain, family and sub family code e.g. `PMNT/RCDT/ESCT`
+    pub bank_transaction_code: Option<String>,
+    pub account_owner_reference: String,
+    pub account_servicing_institution_reference: Option<String>,
+    pub supplementary_details: Option<String>,
+    pub information: Option<String>,
+}
+
+// MT940 only carries booked entries, camt.052 reports can also hold pending ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum EntryStatus {
+    Booked,
+    Pending,
+    Information,
+}
+
+impl EntryStatus {
+    pub fn value(&self) -> String {
+        match self {
+            Self::Booked => "BOOK".to_string(),
+            Self::Pending => "PDNG".to_string(),
+            Self::Information => "INFO".to_string(),
+        }
+    }
+}
+
+impl TryFrom<&str> for EntryStatus {
+    type Error = eyre::Error;
+
+    fn try_from(input: &str) -> Result<Self> {
+        match input {
+            "BOOK" => Ok(Self::Booked),
+            "PDNG" => Ok(Self::Pending),
+            "INFO" => Ok(Self::Information),
+            _ => Err(eyre!(
+                "Entry Status is either missing or the value '{input}' is not valid"
+            )),
+        }
+    }
+}
+
+impl Entry {
+    // credits are positive and debits negative, a reversal turns the sign of its mark
+    pub fn signed_amount(&self) -> f64 {
+        match self.debit_or_credit {
+            CreditDebit::Credit | CreditDebit::DebitReversal => self.amount,
+            CreditDebit::Debit | CreditDebit::CreditReversal => -self.amount,
+        }
+    }
+
+    // the SWIFT transaction type as it appears in :61: e.g. `NMSC`
+    pub fn transaction_type_code(&self) -> String {
+        format!(
+            "{}{}",
+            self.transaction_type_marker.value(),
+            self.identification_code
+        )
+    }
+}
+
+impl From<&MT940<'_>> for Statement {
+    fn from(mt940: &MT940) -> Self {
+        let text = &mt940.text;
+
+        let information = if text.tag_86.is_empty() {
+            None
+        } else {
+            Some(
+                text.tag_86
+                    .iter()
+                    .map(|x| x.information_to_account_owner.as_str())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )
+        };
+
+        Self {
+            source: StatementSource::Mt940,
+            reference: text.tag_20.transaction_reference_number.to_string(),
+            account: text.tag_25.account_identification.to_string(),
+            currency: text.tag_60.balance_data.currency,
+            statement_number: Some(text.tag_28c.statement_number),
+            sequence_number: Some(text.tag_28c.sequence_number),
+            creation_date_time: None,
+            opening_balance: Some(StatementBalance {
+                balance_type: text.tag_60.balance_type,
+                balance_data: text.tag_60.balance_data.clone(),
+            }),
+            closing_balance: Some(StatementBalance {
+                balance_type: text.tag_62.balance_type,
+                balance_data: text.tag_62.balance_data.clone(),
+            }),
+            closing_available_balance: text.tag_64.as_ref().map(|x| x.balance_data.clone()),
+            forward_available_balances: text
+                .tag_65
+                .iter()
+                .map(|x| x.balance_data.clone())
+                .collect(),
+            entries: text.transactions.iter().map(Entry::from).collect(),
+            information,
+        }
+    }
+}
+
+impl From<&Transaction<'_>> for Entry {
+    fn from(transaction: &Transaction) -> Self {
+        let statement_line = &transaction.tag_61;
+
+        Self {
+            status: EntryStatus::Booked,
+            value_date: statement_line.value_date,
+            entry_date: statement_line.entry_date,
+            debit_or_credit: statement_line.debit_or_credit,
+            funds_code: statement_line.funds_code,
+            amount: statement_line.amount,
+            transaction_type_marker: statement_line.transaction_type_marker,
+            transaction_type: statement_line.transaction_type,
+            identification_code: statement_line.identification_code.to_string(),
+            bank_transaction_code: None,
+            account_owner_reference: statement_line.account_owner_reference.to_string(),
+            account_servicing_institution_reference: statement_line
+                .account_servicing_institution_reference
+                .as_deref()
+                .map(str::to_string),
+            supplementary_details: statement_line
+                .supplementary_details
+                .as_deref()
+                .map(str::to_string),
+            information: transaction.information(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_from_mt940() -> Result<()> {
+        let mt940 = MT940::new(include_str!("./test_messages/basic_test_4.txt"))?;
+        let statement = Statement::from(&mt940);
+
+        assert_eq!(statement.source, StatementSource::Mt940);
+        assert_eq!(statement.reference, "3996-11-11111111");
+        assert_eq!(statement.account, "DABADKKK/111111-11111111");
+        assert_eq!(statement.currency, Currency::EUR);
+        assert_eq!(statement.statement_number, Some(1));
+        assert_eq!(statement.sequence_number, Some(1));
+        assert_eq!(
+            statement
+                .opening_balance
+                .as_ref()
+                .map(|x| x.balance_data.amount),
+            Some(54484.04)
+        );
+        assert_eq!(
+            statement.closing_balance.as_ref().map(|x| &x.balance_type),
+            Some(&BalanceType::Final)
+        );
+        assert_eq!(statement.entries.len(), mt940.text.transactions.len());
+
+        let entry = &statement.entries[0];
+        assert_eq!(entry.status, EntryStatus::Booked);
+        assert_eq!(entry.debit_or_credit, CreditDebit::Credit);
+        assert_eq!(entry.amount, 0.23);
+        assert_eq!(entry.identification_code, "INT");
+        assert_eq!(entry.transaction_type_code(), "FINT");
+        assert_eq!(entry.signed_amount(), 0.23);
+        assert_eq!(entry.account_owner_reference, "Interest");
+        assert_eq!(
+            entry.information.as_deref().map(|x| x.lines().next()),
+            Some(Some("For your inform. IBAN no.: FI1111111111111111"))
+        );
+        Ok(())
+    }
+}
@@ -1,41 +1,64 @@
+use crate::spec::{subfields, FormatSpec, BIC};
 use crate::utils::{
-    float_from_swift_amount, naive_date_from_swift_date, Balance, BalanceType, CreditDebit,
-    FundsCode, SanctionScreenType, TransactionType, ValidationFlag,
+    float_from_swift_amount, naive_date_from_swift_date, naive_date_from_swift_entry_date, owned,
+    Balance, BalanceType, CreditDebit, CurrencyAmount, ParseContext, SanctionScreenType,
+    TransactionType, TransactionTypeMarker, ValidationFlag, Warning,
 };
 use chrono::NaiveDate;
-use eyre::Result;
-use serde::Serialize;
+use eyre::{eyre, Result};
+use iso_currency::Currency;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 // Tag20
-#[derive(Debug, PartialEq, Eq, Serialize)]
+// 16x
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TransactionReferenceNumber<'a> {
-    pub transaction_reference_number: &'a str,
+    pub transaction_reference_number: Cow<'a, str>,
 }
 
 impl<'a> TransactionReferenceNumber<'a> {
-    pub fn new(value: &'a str) -> Self {
-        Self {
-            transaction_reference_number: value,
+    pub fn new(value: &'a str) -> Result<Self> {
+        let subfields = subfields("20", value)?;
+
+        Ok(Self {
+            transaction_reference_number: subfields[0].unwrap_or_default().into(),
+        })
+    }
+
+    pub fn into_owned(self) -> TransactionReferenceNumber<'static> {
+        TransactionReferenceNumber {
+            transaction_reference_number: owned(self.transaction_reference_number),
         }
     }
 }
 
 // Tag25
-#[derive(Debug, PartialEq, Eq, Serialize)]
+// 35x
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AccountIdentification<'a> {
-    pub account_identification: &'a str,
+    pub account_identification: Cow<'a, str>,
 }
 
 impl<'a> AccountIdentification<'a> {
-    pub fn new(account_identification: &'a str) -> Self {
-        Self {
-            account_identification,
+    pub fn new(account_identification: &'a str) -> Result<Self> {
+        let subfields = subfields("25", account_identification)?;
+
+        Ok(Self {
+            account_identification: subfields[0].unwrap_or_default().into(),
+        })
+    }
+
+    pub fn into_owned(self) -> AccountIdentification<'static> {
+        AccountIdentification {
+            account_identification: owned(self.account_identification),
         }
     }
 }
 
 // Tag28C
-#[derive(Debug, PartialEq, Eq, Serialize)]
+// 5n[/5n]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StatementNumber {
     pub statement_number: u32,
     pub sequence_number: u32,
@@ -43,237 +66,612 @@ pub struct StatementNumber {
 
 impl StatementNumber {
     pub fn new(value: &str) -> Result<Self> {
-        let statement_sequence_number = value
-            .split('/')
-            .map(|x| return x.strip_prefix('0').unwrap_or(x).parse::<u32>())
-            .collect::<Result<Vec<_>, _>>()?;
+        let subfields = subfields("28C", value)?;
 
+        // a statement that is not split over several messages may leave out the sequence number
         Ok(Self {
-            statement_number: statement_sequence_number[0],
-            sequence_number: statement_sequence_number[1],
+            statement_number: subfields[0].unwrap_or_default().parse::<u32>()?,
+            sequence_number: subfields[1].unwrap_or("1").parse::<u32>()?,
         })
     }
 }
 
 // Tag60F
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct OpeningBalance {
     pub balance_type: BalanceType,
     pub balance_data: Balance,
 }
 
 impl OpeningBalance {
-    pub fn new(balance_type: BalanceType, balance_data: &str) -> Result<Self> {
+    pub fn new(
+        balance_type: BalanceType,
+        balance_data: &str,
+        context: &mut ParseContext,
+    ) -> Result<Self> {
         Ok(Self {
             balance_type,
-            balance_data: Balance::new(balance_data)?,
+            balance_data: Balance::new(balance_data, context)?,
         })
     }
 }
 
 // Tag61
-#[derive(Debug, PartialEq, Serialize)]
+// 6!n[4!n]2a[1!a]15d1!a3!c16x[//16x]
+// [34x]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct StatementLine<'a> {
     pub value_date: NaiveDate,
     pub entry_date: NaiveDate,
     pub debit_or_credit: CreditDebit,
+    pub funds_code: Option<char>,
     pub amount: f64,
-    pub funds_code: FundsCode,
+    pub transaction_type_marker: TransactionTypeMarker,
     pub transaction_type: Option<TransactionType>,
-    pub account_owner_reference: &'a str,
-    pub account_servicing_institution_reference: Option<&'a str>,
-    pub supplementary_details: Option<&'a str>,
+    pub identification_code: Cow<'a, str>,
+    pub account_owner_reference: Cow<'a, str>,
+    pub account_servicing_institution_reference: Option<Cow<'a, str>>,
+    pub supplementary_details: Option<Cow<'a, str>>,
 }
 
 impl<'a> StatementLine<'a> {
-    pub fn new(value: &'a str) -> Result<Self> {
+    pub fn new(value: &'a str, context: &mut ParseContext) -> Result<Self> {
+        // supplementary details (subfield 9) are carried on the second line
+        let mut lines = value.lines();
+        let value = lines.next().unwrap_or_default();
+        let next_line = lines.find(|x| !x.trim().is_empty());
+
+        // the marks and codes are upper case, in lenient mode lower case ones are read as such
+        let upper_case;
+        let codes = if context.strict {
+            value
+        } else {
+            upper_case = value.to_ascii_uppercase();
+            upper_case.as_str()
+        };
+
+        let subfield = |start: usize, end: usize, name: &str| {
+            value
+                .get(start..end)
+                .ok_or_else(|| eyre!("statement line '{value}' is missing the {name}"))
+        };
+        let code = |start: usize, end: usize, name: &str| {
+            codes
+                .get(start..end)
+                .ok_or_else(|| eyre!("statement line '{value}' is missing the {name}"))
+        };
+
         // we will use this to track where in the string we
         // should start parsing from each time we get a value
         let mut index = 0;
 
-        let value_date = naive_date_from_swift_date(&value[index..index + 6])?;
+        // Subfield 1, Value Date (6!n)
+        let value_date =
+            naive_date_from_swift_date(subfield(index, index + 6, "value date")?, context)?;
         let mut entry_date = value_date;
 
         index += 6;
 
-        if value[index..index + 4].chars().all(char::is_numeric) {
-            entry_date = naive_date_from_swift_date(&value[index..index + 4])?;
-            index += 4;
+        // Subfield 2, Entry Date [4!n]
+        if let Some(date) = value.get(index..index + 4) {
+            if date.chars().all(|c| c.is_ascii_digit()) {
+                entry_date = naive_date_from_swift_entry_date(date, value_date, context)?;
+                index += 4;
+            }
         }
 
-        let debit_or_credit = CreditDebit::try_from(&value[index..index + 2])
-            .unwrap_or(CreditDebit::try_from(&value[index..=index])?);
+        // Subfield 3, Debit/Credit Mark (2a), a reversal is always written `RC` or `RD`. In
+        // `CR` and `DR` the `R` is the funds code, e.g. of an EUR statement
+        let mark = match codes.get(index..index + 2) {
+            Some(mark @ ("RC" | "RD")) => mark,
+            _ => code(index, index + 1, "debit/credit mark")?,
+        };
+        let debit_or_credit = CreditDebit::try_from(mark)?;
 
-        index += debit_or_credit.value().len();
+        index += mark.len();
 
-        let mut amount_string = String::new();
+        // Subfield 4, Funds Code [1!a], the third character of the currency code
+        let funds_code = codes[index..]
+            .chars()
+            .next()
+            .filter(char::is_ascii_uppercase);
 
-        for c in value[index..index + 15].chars().map(|x| x.to_string()) {
-            if c.parse::<u8>().is_ok() || c == "," {
-                amount_string.push_str(&c);
-            } else {
-                break;
-            }
+        if funds_code.is_some() {
+            index += 1;
         }
 
+        // Subfield 5, Amount (15d)
+        let amount_string: String = value[index..]
+            .chars()
+            .take(15)
+            .take_while(|c| c.is_ascii_digit() || *c == ',')
+            .collect();
+
         let amount: f64 = float_from_swift_amount(&amount_string)?;
 
         // float will truncate the 0 and so the len will be 1 char short, check the string instead!
-        index += amount_string.to_string().len();
+        index += amount_string.len();
 
-        let funds_code = FundsCode::try_from(&value[index..=index])?;
+        // Subfield 6, Transaction Type (1!a3!c)
+        let transaction_type_marker =
+            TransactionTypeMarker::try_from(code(index, index + 1, "transaction type")?)?;
 
         index += 1;
 
-        let transaction_type = if funds_code == FundsCode::SwiftTransfer {
+        let identification_code = code(index, index + 3, "identification code")?;
+
+        // for `S` the identification code is the SWIFT message type rather than a transaction
+        // type, German banks write their business transaction code (GVC) in its place
+        let numeric = identification_code.chars().all(|c| c.is_ascii_digit());
+        let transaction_type = if transaction_type_marker == TransactionTypeMarker::SwiftTransfer {
+            if !numeric {
+                return Err(eyre!(
+                    "Message Type is either missing or the value '{identification_code}' is not valid"
+                ));
+            }
+            None
+        } else if numeric && context.profile.gvc_transaction_codes {
             None
         } else {
-            Some(TransactionType::try_from(&value[index..index + 3])?)
+            Some(TransactionType::try_from(identification_code)?)
         };
 
-        if transaction_type.is_some() {
-            index += 3;
+        index += 3;
+
+        if codes[..index] != value[..index] {
+            context.warnings.push(Warning::new(format!(
+                "statement line '{value}' has lower case codes which were read as upper case"
+            )));
         }
 
-        let account_owner_reference = if value[index..].len() > 16 {
-            &value[index..index + 16]
-        } else {
-            &value[index..]
-        };
+        // Subfield 7, Reference for the Account Owner (16x) runs up to `//`
+        // Subfield 8, Reference of the Account Servicing Institution [//16x]
+        let (account_owner_reference, account_servicing_institution_reference) =
+            match value[index..].split_once("//") {
+                Some((owner, servicer)) => (owner, Some(servicer)),
+                None => (&value[index..], None),
+            };
+
+        if account_owner_reference.is_empty() {
+            return Err(eyre!(
+                "statement line '{value}' is missing the reference for the account owner"
+            ));
+        }
 
-        index += account_owner_reference.len();
+        // some banks carry on past the 16 characters on the first line (ASN adds the
+        // counterparty after NONREF), which their profile reads as the supplementary details
+        let (account_owner_reference, overflow) = split_reference(account_owner_reference);
+        let (account_servicing_institution_reference, overflow) =
+            match account_servicing_institution_reference {
+                Some(reference) if overflow.is_none() => {
+                    let (reference, overflow) = split_reference(reference);
+                    (Some(reference), overflow)
+                }
+                reference => (reference, overflow),
+            };
+
+        if let Some(overflow) = overflow {
+            if !context.profile.reference_overflow {
+                context.recover(eyre!(
+                    "statement line reference '{overflow}' exceeds 16 characters"
+                ))?;
+            }
+        }
 
-        let account_servicing_insitution_reference = if value[index..].starts_with("NONREF") {
-            index += 6;
-            Some("NONREF")
-        } else if value[index..].is_empty() {
-            None
-        } else {
-            Some(&value[index..])
+        // Subfield 9, Supplementary Details [34x]
+        let supplementary_details = match (next_line, overflow) {
+            (Some(_), Some(overflow)) => {
+                return Err(eyre!(
+                    "statement line reference '{overflow}' exceeds 16 characters"
+                ))
+            }
+            (Some(details), None) | (None, Some(details)) => Some(details),
+            (None, None) => None,
         };
 
-        let supplementary_details = if account_servicing_insitution_reference == Some("NONREF") {
-            Some(&value[index..])
-        } else {
-            None
-        };
+        if let Some(details) = supplementary_details {
+            if details.chars().count() > 34 {
+                return Err(eyre!(
+                    "Supplementary Details '{details}' is an unexpected length"
+                ));
+            }
+        }
 
         Ok(Self {
             value_date,
             entry_date,
             debit_or_credit,
-            amount,
             funds_code,
+            amount,
+            transaction_type_marker,
             transaction_type,
-            account_owner_reference,
-            account_servicing_institution_reference: account_servicing_insitution_reference,
-            supplementary_details,
+            identification_code: if identification_code == &value[index - 3..index] {
+                Cow::Borrowed(&value[index - 3..index])
+            } else {
+                Cow::Owned(identification_code.to_string())
+            },
+            account_owner_reference: account_owner_reference.into(),
+            account_servicing_institution_reference: account_servicing_institution_reference
+                .map(Cow::Borrowed),
+            supplementary_details: supplementary_details.map(Cow::Borrowed),
         })
     }
+
+    pub fn into_owned(self) -> StatementLine<'static> {
+        StatementLine {
+            value_date: self.value_date,
+            entry_date: self.entry_date,
+            debit_or_credit: self.debit_or_credit,
+            funds_code: self.funds_code,
+            amount: self.amount,
+            transaction_type_marker: self.transaction_type_marker,
+            transaction_type: self.transaction_type,
+            identification_code: owned(self.identification_code),
+            account_owner_reference: owned(self.account_owner_reference),
+            account_servicing_institution_reference: self
+                .account_servicing_institution_reference
+                .map(owned),
+            supplementary_details: self.supplementary_details.map(owned),
+        }
+    }
+}
+
+// split a 16x reference from anything that follows it on the same line
+fn split_reference(value: &str) -> (&str, Option<&str>) {
+    match value.char_indices().nth(16) {
+        Some((index, _)) => (&value[..index], Some(&value[index..])),
+        None => (value, None),
+    }
 }
 
 // Tag62F
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct BookedFunds {
     pub balance_type: BalanceType,
     pub balance_data: Balance,
 }
 
 impl BookedFunds {
-    pub fn new(balance_type: BalanceType, balance_data: &str) -> Result<Self> {
+    pub fn new(
+        balance_type: BalanceType,
+        balance_data: &str,
+        context: &mut ParseContext,
+    ) -> Result<Self> {
         Ok(Self {
             balance_type,
-            balance_data: Balance::new(balance_data)?,
+            balance_data: Balance::new(balance_data, context)?,
         })
     }
 }
 
 // Tag64
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct ClosingAvailableBalance {
     pub balance_data: Balance,
 }
 
 impl ClosingAvailableBalance {
-    pub fn new(value: &str) -> Result<Self> {
+    pub fn new(value: &str, context: &mut ParseContext) -> Result<Self> {
+        Ok(Self {
+            balance_data: Balance::new(value, context)?,
+        })
+    }
+}
+
+// Tag65
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ForwardAvailableBalance {
+    pub balance_data: Balance,
+}
+
+impl ForwardAvailableBalance {
+    pub fn new(value: &str, context: &mut ParseContext) -> Result<Self> {
         Ok(Self {
-            balance_data: Balance::new(value)?,
+            balance_data: Balance::new(value, context)?,
         })
     }
 }
 
 // Tag86
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct InformationToAccountOwner<'a> {
-    pub information_to_account_owner: &'a str,
+    pub information_to_account_owner: String,
+    pub lines: Vec<Cow<'a, str>>,
 }
 
 impl<'a> InformationToAccountOwner<'a> {
     pub fn new(value: &'a str) -> Self {
+        // blank lines only get through in lenient mode, they carry nothing
+        let lines: Vec<&str> = value.lines().filter(|x| !x.trim().is_empty()).collect();
+
         Self {
-            information_to_account_owner: value,
+            information_to_account_owner: lines.join("\n"),
+            lines: lines.into_iter().map(Cow::Borrowed).collect(),
+        }
+    }
+
+    pub fn into_owned(self) -> InformationToAccountOwner<'static> {
+        InformationToAccountOwner {
+            information_to_account_owner: self.information_to_account_owner,
+            lines: self.lines.into_iter().map(owned).collect(),
+        }
+    }
+}
+
+// Tag23E
+// 4!c[/30x]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionCode<'a> {
+    pub instruction_code: Cow<'a, str>,
+    pub additional_information: Option<Cow<'a, str>>,
+}
+
+impl<'a> InstructionCode<'a> {
+    pub fn new(value: &'a str) -> Result<Self> {
+        let subfields = subfields("23E", value)?;
+
+        Ok(Self {
+            instruction_code: subfields[0].unwrap_or_default().into(),
+            additional_information: subfields[1].map(Cow::Borrowed),
+        })
+    }
+
+    pub fn into_owned(self) -> InstructionCode<'static> {
+        InstructionCode {
+            instruction_code: owned(self.instruction_code),
+            additional_information: self.additional_information.map(owned),
+        }
+    }
+}
+
+// Tag32A
+// 6!n3!a15d
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ValueDateCurrencyAmount {
+    pub value_date: NaiveDate,
+    pub currency: Currency,
+    pub amount: f64,
+}
+
+impl ValueDateCurrencyAmount {
+    pub fn new(value: &str, context: &mut ParseContext) -> Result<Self> {
+        let value = context.upper_case("Value date, currency and amount", value);
+        let subfields = subfields("32A", &value)?;
+        let currency_amount = CurrencyAmount::new(&value[6..])?;
+
+        Ok(Self {
+            value_date: naive_date_from_swift_date(subfields[0].unwrap_or_default(), context)?,
+            currency: currency_amount.currency,
+            amount: currency_amount.amount,
+        })
+    }
+}
+
+// Tag50a, Tag52a, Tag53a, Tag54a, Tag56a, Tag57a and Tag59a
+// A: [/1!a][/34x] 4!a2!a2!c[3!c]
+// B: [/1!a][/34x] [35x]
+// D, K and no letter: [/34x] 4*35x
+// F: 35x 4*(1!n/33x)
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Party<'a> {
+    pub option: Option<char>,
+    pub party_identifier: Option<Cow<'a, str>>,
+    pub bic: Option<Cow<'a, str>>,
+    pub name: Option<Cow<'a, str>>,
+    pub address: Vec<Cow<'a, str>>,
+}
+
+impl<'a> Party<'a> {
+    pub fn new(option: Option<char>, value: &'a str) -> Result<Self> {
+        let mut lines = value.lines().peekable();
+
+        // option F always starts with the party identifier, the others only when it starts with `/`
+        let party_identifier = match lines.peek() {
+            Some(line) if line.starts_with('/') => lines.next().map(|x| &x[1..]),
+            Some(line) if option == Some('F') && !is_numbered_line(line) => lines.next(),
+            _ => None,
+        };
+
+        let mut bic = None;
+        let mut name = None;
+        let mut address = vec![];
+
+        match option {
+            Some('A') => {
+                let code = lines.next().unwrap_or_default();
+
+                if FormatSpec::built_in(BIC)?.parse(code).is_err() {
+                    return Err(eyre!("Party BIC '{code}' is not valid"));
+                }
+
+                bic = Some(code);
+            }
+            Some('B') => address.extend(lines),
+            // the name is on the `1/` lines, the address and country/town on the `2/` and `3/` lines
+            Some('F') => {
+                for line in lines {
+                    if !is_numbered_line(line) {
+                        return Err(eyre!("Party line '{line}' is not numbered"));
+                    }
+
+                    match (&line[..1], name) {
+                        ("1", None) => name = Some(&line[2..]),
+                        _ => address.push(&line[2..]),
+                    }
+                }
+            }
+            None | Some('D' | 'K') => {
+                name = lines.next();
+                address.extend(lines);
+            }
+            Some(option) => {
+                return Err(eyre!("Party option '{option}' is not valid"));
+            }
+        }
+
+        Ok(Self {
+            option,
+            party_identifier: party_identifier.map(Cow::Borrowed),
+            bic: bic.map(Cow::Borrowed),
+            name: name.map(Cow::Borrowed),
+            address: address.into_iter().map(Cow::Borrowed).collect(),
+        })
+    }
+
+    pub fn into_owned(self) -> Party<'static> {
+        Party {
+            option: self.option,
+            party_identifier: self.party_identifier.map(owned),
+            bic: self.bic.map(owned),
+            name: self.name.map(owned),
+            address: self.address.into_iter().map(owned).collect(),
+        }
+    }
+}
+
+fn is_numbered_line(line: &str) -> bool {
+    line.len() > 2 && line.as_bytes()[0].is_ascii_digit() && line.as_bytes()[1] == b'/'
+}
+
+// Tag70
+// 4*35x
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemittanceInformation<'a> {
+    pub lines: Vec<Cow<'a, str>>,
+}
+
+impl<'a> RemittanceInformation<'a> {
+    pub fn new(value: &'a str) -> Result<Self> {
+        Ok(Self {
+            lines: narrative_lines("70", value, "Remittance Information")?,
+        })
+    }
+
+    pub fn into_owned(self) -> RemittanceInformation<'static> {
+        RemittanceInformation {
+            lines: self.lines.into_iter().map(owned).collect(),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+// Tag72
+// 6*35x
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SenderToReceiverInformation<'a> {
+    pub lines: Vec<Cow<'a, str>>,
+}
+
+impl<'a> SenderToReceiverInformation<'a> {
+    pub fn new(value: &'a str) -> Result<Self> {
+        Ok(Self {
+            lines: narrative_lines("72", value, "Sender to Receiver Information")?,
+        })
+    }
+
+    pub fn into_owned(self) -> SenderToReceiverInformation<'static> {
+        SenderToReceiverInformation {
+            lines: self.lines.into_iter().map(owned).collect(),
+        }
+    }
+}
+
+// Tag77B
+// 3*35x
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegulatoryReporting<'a> {
+    pub lines: Vec<Cow<'a, str>>,
+}
+
+impl<'a> RegulatoryReporting<'a> {
+    pub fn new(value: &'a str) -> Result<Self> {
+        Ok(Self {
+            lines: narrative_lines("77B", value, "Regulatory Reporting")?,
+        })
+    }
+
+    pub fn into_owned(self) -> RegulatoryReporting<'static> {
+        RegulatoryReporting {
+            lines: self.lines.into_iter().map(owned).collect(),
+        }
+    }
+}
+
+// the lines of a `n*35x` field
+fn narrative_lines<'a>(tag: &str, value: &'a str, name: &str) -> Result<Vec<Cow<'a, str>>> {
+    if subfields(tag, value).is_err() {
+        return Err(eyre!("{name} '{value}' is an unexpected length"));
+    }
+
+    Ok(value.lines().map(Cow::Borrowed).collect())
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 // Tag103
 pub struct ServiceIdentifier<'a> {
-    pub service_identifier: &'a str,
+    pub service_identifier: Cow<'a, str>,
 }
 
 impl<'a> ServiceIdentifier<'a> {
-    pub fn new(value: &'a str) -> Self {
-        assert!(
-            value.len() == 3,
-            "ServiceIdentifier '{value}' is an unexpected length"
-        );
+    pub fn new(value: &'a str) -> Result<Self> {
+        let subfields = subfields("103", value)?;
 
-        Self {
-            service_identifier: value,
+        Ok(Self {
+            service_identifier: subfields[0].unwrap_or_default().into(),
+        })
+    }
+
+    pub fn into_owned(self) -> ServiceIdentifier<'static> {
+        ServiceIdentifier {
+            service_identifier: owned(self.service_identifier),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 // Tag113
 pub struct BankingPriority<'a> {
-    pub banking_priority: &'a str,
+    pub banking_priority: Cow<'a, str>,
 }
 
 impl<'a> BankingPriority<'a> {
-    pub fn new(value: &'a str) -> Self {
-        assert!(
-            value.len() == 4,
-            "BankingPriority '{value}' is an unexpected length"
-        );
+    pub fn new(value: &'a str) -> Result<Self> {
+        let subfields = subfields("113", value)?;
 
-        Self {
-            banking_priority: value,
+        Ok(Self {
+            banking_priority: subfields[0].unwrap_or_default().into(),
+        })
+    }
+
+    pub fn into_owned(self) -> BankingPriority<'static> {
+        BankingPriority {
+            banking_priority: owned(self.banking_priority),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 // Tag108
 pub struct MessageUserReference<'a> {
-    pub message_user_reference: &'a str,
+    pub message_user_reference: Cow<'a, str>,
 }
 
 impl<'a> MessageUserReference<'a> {
-    pub fn new(value: &'a str) -> Self {
-        assert!(
-            value.len() <= 16,
-            "MessageUserReference '{value}' is an unexpected length"
-        );
+    pub fn new(value: &'a str) -> Result<Self> {
+        let subfields = subfields("108", value)?;
 
-        Self {
-            message_user_reference: value,
+        Ok(Self {
+            message_user_reference: subfields[0].unwrap_or_default().into(),
+        })
+    }
+
+    pub fn into_owned(self) -> MessageUserReference<'static> {
+        MessageUserReference {
+            message_user_reference: owned(self.message_user_reference),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 // Tag119
 pub struct Validation {
     pub validation_flag: ValidationFlag,
@@ -287,29 +685,32 @@ impl Validation {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 // Tag424
 pub struct RelatedReference<'a> {
-    pub related_reference: &'a str,
+    pub related_reference: Cow<'a, str>,
 }
 
 impl<'a> RelatedReference<'a> {
-    pub fn new(value: &'a str) -> Self {
-        assert!(
-            value.len() <= 16,
-            "RelatedReference '{value}' is an unexpected length"
-        );
+    pub fn new(value: &'a str) -> Result<Self> {
+        let subfields = subfields("424", value)?;
 
-        Self {
-            related_reference: value,
+        Ok(Self {
+            related_reference: subfields[0].unwrap_or_default().into(),
+        })
+    }
+
+    pub fn into_owned(self) -> RelatedReference<'static> {
+        RelatedReference {
+            related_reference: owned(self.related_reference),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 // Tag111
 pub struct ServiceTypeIdentifier<'a> {
-    pub service_type_identifier: &'a str,
+    pub service_type_identifier: Cow<'a, str>,
 }
 
 impl<'a> ServiceTypeIdentifier<'a> {
@@ -320,15 +721,21 @@ impl<'a> ServiceTypeIdentifier<'a> {
         );
 
         Self {
-            service_type_identifier: value,
+            service_type_identifier: value.into(),
+        }
+    }
+
+    pub fn into_owned(self) -> ServiceTypeIdentifier<'static> {
+        ServiceTypeIdentifier {
+            service_type_identifier: owned(self.service_type_identifier),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 // Tag165
 pub struct PaymentReleaseInformationReceiver<'a> {
-    pub payment_release_information_receiver: &'a str,
+    pub payment_release_information_receiver: Cow<'a, str>,
 }
 
 impl<'a> PaymentReleaseInformationReceiver<'a> {
@@ -339,16 +746,22 @@ impl<'a> PaymentReleaseInformationReceiver<'a> {
         );
 
         Self {
-            payment_release_information_receiver: value,
+            payment_release_information_receiver: value.into(),
+        }
+    }
+
+    pub fn into_owned(self) -> PaymentReleaseInformationReceiver<'static> {
+        PaymentReleaseInformationReceiver {
+            payment_release_information_receiver: owned(self.payment_release_information_receiver),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 // Tag433
 pub struct SanctionsScreeningInformation<'a> {
     pub codeword: SanctionScreenType,
-    pub additional_information: &'a str, // this should be an option!
+    pub additional_information: Cow<'a, str>, // this should be an option!
 }
 
 impl<'a> SanctionsScreeningInformation<'a> {
@@ -358,26 +771,40 @@ impl<'a> SanctionsScreeningInformation<'a> {
 
         Ok(Self {
             codeword: SanctionScreenType::try_from(codeword)?,
-            additional_information,
+            additional_information: additional_information.into(),
         })
     }
+
+    pub fn into_owned(self) -> SanctionsScreeningInformation<'static> {
+        SanctionsScreeningInformation {
+            codeword: self.codeword,
+            additional_information: owned(self.additional_information),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 // Tag434
 pub struct PaymentControlsInformation<'a> {
-    pub codeword: &'a str,
-    pub additional_information: &'a str,
+    pub codeword: Cow<'a, str>,
+    pub additional_information: Cow<'a, str>,
 }
 
 impl<'a> PaymentControlsInformation<'a> {
     pub fn new(value: &'a str) -> Self {
         let codeword = &value[1..4];
-        let additional_information = &value[4..].strip_prefix('\\').unwrap_or("");
+        let additional_information = value[4..].strip_prefix('\\').unwrap_or("");
 
         Self {
-            codeword,
-            additional_information,
+            codeword: codeword.into(),
+            additional_information: additional_information.into(),
+        }
+    }
+
+    pub fn into_owned(self) -> PaymentControlsInformation<'static> {
+        PaymentControlsInformation {
+            codeword: owned(self.codeword),
+            additional_information: owned(self.additional_information),
         }
     }
 }
@@ -385,13 +812,14 @@ impl<'a> PaymentControlsInformation<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::*;
+    use crate::profile::find;
+    use crate::utils::ParseOptions;
     use iso_currency::Currency;
 
     #[test]
     fn test_transaction_reference_number() -> Result<()> {
         assert_eq!(
-            TransactionReferenceNumber::new("3996-11-11111111").transaction_reference_number,
+            TransactionReferenceNumber::new("3996-11-11111111")?.transaction_reference_number,
             "3996-11-11111111"
         ); // TODO do we need to parse this or is it just free text?
         Ok(())
@@ -400,7 +828,7 @@ mod tests {
     #[test]
     fn test_account_identification() -> Result<()> {
         assert_eq!(
-            AccountIdentification::new("DABADKKK/111111-11111111").account_identification,
+            AccountIdentification::new("DABADKKK/111111-11111111")?.account_identification,
             "DABADKKK/111111-11111111"
         );
         Ok(())
@@ -415,9 +843,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_statement_number_without_sequence_number() -> Result<()> {
+        let statement = StatementNumber::new("235")?;
+
+        assert_eq!(statement.statement_number, 235);
+        assert_eq!(statement.sequence_number, 1);
+        assert!(StatementNumber::new("235/1/2").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_opening_balance() -> Result<()> {
-        let opening_balance = OpeningBalance::new(BalanceType::Final, "C090924EUR54484,04")?;
+        let opening_balance = OpeningBalance::new(
+            BalanceType::Final,
+            "C090924EUR54484,04",
+            &mut ParseContext::default(),
+        )?;
 
         assert_eq!(
             opening_balance.balance_data.credit_or_debit,
@@ -434,7 +876,11 @@ mod tests {
 
     #[test]
     fn test_booked_funds() -> Result<()> {
-        let booked_funds = BookedFunds::new(BalanceType::Final, "C090924EUR54484,04")?;
+        let booked_funds = BookedFunds::new(
+            BalanceType::Final,
+            "C090924EUR54484,04",
+            &mut ParseContext::default(),
+        )?;
 
         assert_eq!(
             booked_funds.balance_data.credit_or_debit,
@@ -451,7 +897,8 @@ mod tests {
 
     #[test]
     fn test_closing_available_funds() -> Result<()> {
-        let closing_available_funds = ClosingAvailableBalance::new("C090924EUR54484,04")?;
+        let closing_available_funds =
+            ClosingAvailableBalance::new("C090924EUR54484,04", &mut ParseContext::default())?;
 
         assert_eq!(
             closing_available_funds.balance_data.credit_or_debit,
@@ -466,6 +913,19 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_forward_available_balance() -> Result<()> {
+        let forward_available_balance =
+            ForwardAvailableBalance::new("C091001EUR53189,31", &mut ParseContext::default())?;
+
+        assert_eq!(
+            forward_available_balance.balance_data.date,
+            NaiveDate::from_ymd(2009, 10, 1)
+        );
+        assert_eq!(forward_available_balance.balance_data.amount, 53189.31);
+        Ok(())
+    }
+
     #[test]
     fn test_information_to_account_owner() -> Result<()> {
         assert_eq!(
@@ -477,25 +937,305 @@ mod tests {
 
     #[test]
     fn test_statement_line() -> Result<()> {
-        let sl = StatementLine::new("0909290929DR55,00NMSC0000000000000269//1234")?;
+        let sl = StatementLine::new(
+            "0909290929DR55,00NMSC0000000000000269//1234",
+            &mut ParseContext::default(),
+        )?;
 
         assert_eq!(sl.value_date, NaiveDate::from_ymd(2009, 9, 29));
+        assert_eq!(sl.entry_date, NaiveDate::from_ymd(2009, 9, 29));
+        assert_eq!(sl.amount, 55.0);
         assert_eq!(
-            sl.entry_date,
-            NaiveDate::from_ymd(chrono::Utc::now().year(), 9, 29)
+            sl.transaction_type_marker,
+            TransactionTypeMarker::NonSwiftTransfer
         );
-        assert_eq!(sl.amount, 55.0);
-        assert_eq!(sl.funds_code, FundsCode::NonSwiftTransfer);
         assert_eq!(sl.transaction_type, Some(TransactionType::MSC));
+        assert_eq!(sl.identification_code, "MSC");
         assert_eq!(sl.account_owner_reference, "0000000000000269");
-        assert_eq!(sl.account_servicing_institution_reference, Some("//1234"));
+        assert_eq!(
+            sl.account_servicing_institution_reference.as_deref(),
+            Some("1234")
+        );
         assert_eq!(sl.supplementary_details, None);
         Ok(())
     }
 
+    #[test]
+    fn test_statement_line_value_date() -> Result<()> {
+        let sl = StatementLine::new("091001C0,23FINTInterest", &mut ParseContext::default())?;
+
+        assert_eq!(sl.value_date, NaiveDate::from_ymd_opt(2009, 10, 1).unwrap());
+        assert_eq!(sl.entry_date, sl.value_date);
+        assert_eq!(sl.debit_or_credit, CreditDebit::Credit);
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_line_entry_date_before_value_date() -> Result<()> {
+        let sl = StatementLine::new("0910010930CR0,23FINTInterest", &mut ParseContext::default())?;
+
+        assert_eq!(sl.value_date, NaiveDate::from_ymd_opt(2009, 10, 1).unwrap());
+        assert_eq!(sl.entry_date, NaiveDate::from_ymd_opt(2009, 9, 30).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_line_entry_date_year_rollover() -> Result<()> {
+        let sl = StatementLine::new("1001021231D62,60NCHGNONREF", &mut ParseContext::default())?;
+
+        assert_eq!(sl.value_date, NaiveDate::from_ymd_opt(2010, 1, 2).unwrap());
+        assert_eq!(
+            sl.entry_date,
+            NaiveDate::from_ymd_opt(2009, 12, 31).unwrap()
+        );
+
+        let sl = StatementLine::new("0912300102D62,60NCHGNONREF", &mut ParseContext::default())?;
+
+        assert_eq!(
+            sl.value_date,
+            NaiveDate::from_ymd_opt(2009, 12, 30).unwrap()
+        );
+        assert_eq!(sl.entry_date, NaiveDate::from_ymd_opt(2010, 1, 2).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_line_amount() -> Result<()> {
+        let sl = StatementLine::new(
+            "0910010930C123456789012,45FINTInterest",
+            &mut ParseContext::default(),
+        )?;
+
+        assert_eq!(sl.amount, 123456789012.45);
+        assert_eq!(
+            sl.transaction_type_marker,
+            TransactionTypeMarker::FirstAdvice
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_line_funds_code() -> Result<()> {
+        let sl = StatementLine::new("0910010930CD1000,NTRFNONREF", &mut ParseContext::default())?;
+
+        assert_eq!(sl.debit_or_credit, CreditDebit::Credit);
+        assert_eq!(sl.funds_code, Some('D'));
+        assert_eq!(sl.amount, 1000.0);
+        assert_eq!(
+            sl.transaction_type_marker,
+            TransactionTypeMarker::NonSwiftTransfer
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_line_funds_code_after_reversal() -> Result<()> {
+        let sl = StatementLine::new("0910010930RDR1000,NTRFNONREF", &mut ParseContext::default())?;
+
+        assert_eq!(sl.debit_or_credit, CreditDebit::DebitReversal);
+        assert_eq!(sl.funds_code, Some('R'));
+        assert_eq!(sl.amount, 1000.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_line_without_funds_code() -> Result<()> {
+        let sl = StatementLine::new("0910010930D1000,NTRFNONREF", &mut ParseContext::default())?;
+
+        assert_eq!(sl.funds_code, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_line_swift_transfer() -> Result<()> {
+        let sl = StatementLine::new(
+            "0910010930D1000,S103PAYMENT REF//BANK REF",
+            &mut ParseContext::default(),
+        )?;
+
+        assert_eq!(
+            sl.transaction_type_marker,
+            TransactionTypeMarker::SwiftTransfer
+        );
+        assert_eq!(sl.transaction_type, None);
+        assert_eq!(sl.identification_code, "103");
+        assert_eq!(sl.account_owner_reference, "PAYMENT REF");
+        assert_eq!(
+            sl.account_servicing_institution_reference.as_deref(),
+            Some("BANK REF")
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "Message Type is either missing or the value 'MSC' is not valid")]
+    fn test_statement_line_swift_transfer_message_type() {
+        StatementLine::new(
+            "0910010930D1000,SMSCPAYMENT REF",
+            &mut ParseContext::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_statement_line_references() -> Result<()> {
+        let sl = StatementLine::new(
+            "0910010930DR62,60NCHGFees according//to advice",
+            &mut ParseContext::default(),
+        )?;
+
+        assert_eq!(sl.transaction_type, Some(TransactionType::CHG));
+        assert_eq!(sl.account_owner_reference, "Fees according");
+        assert_eq!(
+            sl.account_servicing_institution_reference.as_deref(),
+            Some("to advice")
+        );
+        assert_eq!(sl.supplementary_details, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_line_no_servicing_institution_reference() -> Result<()> {
+        let sl = StatementLine::new(
+            "0909300930DR265,41NMSC0000101603533361",
+            &mut ParseContext::default(),
+        )?;
+
+        assert_eq!(sl.account_owner_reference, "0000101603533361");
+        assert_eq!(sl.account_servicing_institution_reference, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_line_nonref() -> Result<()> {
+        let sl = StatementLine::new(
+            "0909290929D55,00NMSCNONREF//1234",
+            &mut ParseContext::default(),
+        )?;
+
+        assert_eq!(sl.account_owner_reference, "NONREF");
+        assert_eq!(
+            sl.account_servicing_institution_reference.as_deref(),
+            Some("1234")
+        );
+        Ok(())
+    }
+
+    fn asn_context() -> ParseContext {
+        ParseContext::with_options(ParseOptions {
+            profile: find("ASN Bank").map(|x| (*x).clone()),
+            ..ParseOptions::default()
+        })
+    }
+
+    #[test]
+    fn test_statement_line_reference_overflow() -> Result<()> {
+        let sl = StatementLine::new(
+            "0909290929DR55,00NMSC0000000000000269NONREFHOLMENSKANAL",
+            &mut asn_context(),
+        )?;
+
+        assert_eq!(sl.account_owner_reference, "0000000000000269");
+        assert_eq!(sl.account_servicing_institution_reference, None);
+        assert_eq!(
+            sl.supplementary_details.as_deref(),
+            Some("NONREFHOLMENSKANAL")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_line_reference_overflow_without_profile() -> Result<()> {
+        let value = "0909290929DR55,00NMSC0000000000000269NONREF";
+        let error = StatementLine::new(value, &mut ParseContext::default()).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "statement line reference 'NONREF' exceeds 16 characters"
+        );
+
+        // in lenient mode it is read as the supplementary details with a warning
+        let mut context = ParseContext::with_options(ParseOptions {
+            strict: false,
+            ..ParseOptions::default()
+        });
+        let sl = StatementLine::new(value, &mut context)?;
+
+        assert_eq!(sl.supplementary_details.as_deref(), Some("NONREF"));
+        assert_eq!(context.warnings.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "statement line reference 'NONREF' exceeds 16 characters")]
+    fn test_statement_line_reference_overflow_with_supplementary_details() {
+        StatementLine::new(
+            "0909290929DR55,00NMSC0000000000000269NONREF\nHOLMENSKANAL",
+            &mut asn_context(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_statement_line_gvc_transaction_code() -> Result<()> {
+        let value = "2001030103D50,00N020NONREF";
+        let mut context = ParseContext::with_options(ParseOptions {
+            profile: find("Deutsche Bank").map(|x| (*x).clone()),
+            ..ParseOptions::default()
+        });
+        let sl = StatementLine::new(value, &mut context)?;
+
+        assert_eq!(sl.transaction_type, None);
+        assert_eq!(sl.identification_code, "020");
+        assert_eq!(sl.account_owner_reference, "NONREF");
+
+        // without a German profile it is not a transaction type
+        assert!(StatementLine::new(value, &mut ParseContext::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "is missing the reference for the account owner")]
+    fn test_statement_line_missing_account_owner_reference() {
+        StatementLine::new("0909290929DR55,00NMSC//1234", &mut ParseContext::default()).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "is missing the identification code")]
+    fn test_statement_line_missing_identification_code() {
+        StatementLine::new("0909290929DR55,00NMS", &mut ParseContext::default()).unwrap();
+    }
+
+    #[test]
+    fn test_statement_line_reversal_mark() -> Result<()> {
+        let sl = StatementLine::new("0909290929RC55,00NMSCNONREF", &mut ParseContext::default())?;
+
+        assert_eq!(sl.debit_or_credit, CreditDebit::CreditReversal);
+        assert_eq!(sl.amount, 55.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_line_supplementary_details() -> Result<()> {
+        let sl = StatementLine::new(
+            "0909290929DR55,00NMSC0000000000000269//1234\r\nFEES CHARGED",
+            &mut ParseContext::default(),
+        )?;
+
+        assert_eq!(
+            sl.account_servicing_institution_reference.as_deref(),
+            Some("1234")
+        );
+        assert_eq!(sl.supplementary_details.as_deref(), Some("FEES CHARGED"));
+        Ok(())
+    }
+
     #[test]
     fn test_statement_line_credit() -> Result<()> {
-        let sl = StatementLine::new("0909290929C55,00NMSC0000000000000269//1234")?;
+        let sl = StatementLine::new(
+            "0909290929C55,00NMSC0000000000000269//1234",
+            &mut ParseContext::default(),
+        )?;
 
         assert_eq!(sl.debit_or_credit, CreditDebit::Credit);
         Ok(())
@@ -503,7 +1243,10 @@ mod tests {
 
     #[test]
     fn test_statement_line_debit() -> Result<()> {
-        let sl = StatementLine::new("0909290929D55,00NMSC0000000000000269//1234")?;
+        let sl = StatementLine::new(
+            "0909290929D55,00NMSC0000000000000269//1234",
+            &mut ParseContext::default(),
+        )?;
 
         assert_eq!(sl.debit_or_credit, CreditDebit::Debit);
         Ok(())
@@ -511,35 +1254,74 @@ mod tests {
 
     #[test]
     fn test_statement_line_credit_reversal() -> Result<()> {
-        let sl = StatementLine::new("0909290929CR55,00NMSC0000000000000269//1234")?;
+        let sl = StatementLine::new(
+            "0909290929RC55,00NMSC0000000000000269//1234",
+            &mut ParseContext::default(),
+        )?;
 
         assert_eq!(sl.debit_or_credit, CreditDebit::CreditReversal);
+        assert_eq!(sl.funds_code, None);
         Ok(())
     }
 
     #[test]
     fn test_statement_line_debit_reversal() -> Result<()> {
-        let sl = StatementLine::new("0909290929DR55,00NMSC0000000000000269//1234")?;
+        let sl = StatementLine::new(
+            "0909290929RDR55,00NMSC0000000000000269//1234",
+            &mut ParseContext::default(),
+        )?;
 
         assert_eq!(sl.debit_or_credit, CreditDebit::DebitReversal);
+        assert_eq!(sl.funds_code, Some('R'));
+        Ok(())
+    }
+
+    #[test]
+    fn test_statement_line_mark_and_funds_code() -> Result<()> {
+        let sl = StatementLine::new(
+            "0909290929CR55,00NMSC0000000000000269//1234",
+            &mut ParseContext::default(),
+        )?;
+
+        assert_eq!(sl.debit_or_credit, CreditDebit::Credit);
+        assert_eq!(sl.funds_code, Some('R'));
+        assert_eq!(sl.amount, 55.0);
+
+        let sl = StatementLine::new(
+            "0909290929DR55,00NMSC0000000000000269//1234",
+            &mut ParseContext::default(),
+        )?;
+
+        assert_eq!(sl.debit_or_credit, CreditDebit::Debit);
+        assert_eq!(sl.funds_code, Some('R'));
         Ok(())
     }
 
     #[test]
     #[should_panic(expected = "Credit Debit is either missing or the value '5' is not valid")]
     fn test_statement_line_missing_credit_or_debit() {
-        StatementLine::new("090929092955,00NMSC0000000000000269//1234").unwrap();
+        StatementLine::new(
+            "090929092955,00NMSC0000000000000269//1234",
+            &mut ParseContext::default(),
+        )
+        .unwrap();
     }
 
     #[test]
-    #[should_panic(expected = "Funds Code is either missing or the value 'M' is not valid")]
-    fn test_statement_line_missing_funds_code() {
-        StatementLine::new("0909290929DR55,00MSC0000000000000269//1234").unwrap();
+    #[should_panic(
+        expected = "Transaction Type Marker is either missing or the value 'M' is not valid"
+    )]
+    fn test_statement_line_missing_transaction_type_marker() {
+        StatementLine::new(
+            "0909290929DR55,00MSC0000000000000269//1234",
+            &mut ParseContext::default(),
+        )
+        .unwrap();
     }
 
     #[test]
     fn test_service_identifier() -> Result<()> {
-        let si = ServiceIdentifier::new("CAD");
+        let si = ServiceIdentifier::new("CAD")?;
 
         assert_eq!(si.service_identifier, "CAD");
         assert_eq!(si.service_identifier.len(), 3);
@@ -548,7 +1330,7 @@ mod tests {
 
     #[test]
     fn test_banking_priority() -> Result<()> {
-        let bp = BankingPriority::new("xxxx");
+        let bp = BankingPriority::new("xxxx")?;
 
         assert_eq!(bp.banking_priority, "xxxx");
         assert_eq!(bp.banking_priority.len(), 4);
@@ -557,7 +1339,7 @@ mod tests {
 
     #[test]
     fn test_message_user_reference() -> Result<()> {
-        let mur = MessageUserReference::new("xxxx");
+        let mur = MessageUserReference::new("xxxx")?;
 
         assert_eq!(mur.message_user_reference, "xxxx");
         assert!(mur.message_user_reference.len() <= 16);
@@ -566,7 +1348,7 @@ mod tests {
 
     #[test]
     fn test_related_reference() -> Result<()> {
-        let rr = RelatedReference::new("PQAB1234");
+        let rr = RelatedReference::new("PQAB1234")?;
 
         assert_eq!(rr.related_reference, "PQAB1234");
         assert!(rr.related_reference.len() <= 16);
@@ -608,4 +1390,78 @@ mod tests {
         assert_eq!(ssi.additional_information, "");
         Ok(())
     }
+
+    #[test]
+    fn test_instruction_code() -> Result<()> {
+        let instruction_code = InstructionCode::new("PHOB/+44 20 7946 0000")?;
+
+        assert_eq!(instruction_code.instruction_code, "PHOB");
+        assert_eq!(
+            instruction_code.additional_information.as_deref(),
+            Some("+44 20 7946 0000")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_value_date_currency_amount() -> Result<()> {
+        let tag_32a =
+            ValueDateCurrencyAmount::new("200103EUR1000,50", &mut ParseContext::default())?;
+
+        assert_eq!(
+            tag_32a.value_date,
+            NaiveDate::from_ymd_opt(2020, 1, 3).unwrap()
+        );
+        assert_eq!(tag_32a.currency, Currency::EUR);
+        assert_eq!(tag_32a.amount, 1000.5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_party_option_a() -> Result<()> {
+        let party = Party::new(Some('A'), "/DE89370400440532013000\nDEUTDEFFXXX")?;
+
+        assert_eq!(
+            party.party_identifier.as_deref(),
+            Some("DE89370400440532013000")
+        );
+        assert_eq!(party.bic.as_deref(), Some("DEUTDEFFXXX"));
+        assert_eq!(party.name, None);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "Party BIC 'DEUTDEF' is not valid")]
+    fn test_party_option_a_invalid_bic() {
+        Party::new(Some('A'), "DEUTDEF").unwrap();
+    }
+
+    #[test]
+    fn test_party_option_f() -> Result<()> {
+        let party = Party::new(
+            Some('F'),
+            "/12345678\n1/JOHN SMITH\n2/1 HIGH STREET\n3/GB/LONDON",
+        )?;
+
+        assert_eq!(party.party_identifier.as_deref(), Some("12345678"));
+        assert_eq!(party.name.as_deref(), Some("JOHN SMITH"));
+        assert_eq!(party.address, vec!["1 HIGH STREET", "GB/LONDON"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_party_option_k() -> Result<()> {
+        let party = Party::new(Some('K'), "JOHN SMITH\n1 HIGH STREET")?;
+
+        assert_eq!(party.party_identifier, None);
+        assert_eq!(party.name.as_deref(), Some("JOHN SMITH"));
+        assert_eq!(party.address, vec!["1 HIGH STREET"]);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "Remittance Information 'a\nb\nc\nd\ne' is an unexpected length")]
+    fn test_remittance_information_too_many_lines() {
+        RemittanceInformation::new("a\nb\nc\nd\ne").unwrap();
+    }
 }
@@ -1,21 +1,36 @@
+use crate::profile::{generic, BankProfile};
+use crate::spec::{FormatSpec, BALANCE, CURRENCY_AMOUNT};
 use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
 use eyre::{eyre, Result};
 use iso3166_1::alpha2; // country
 use iso_currency::Currency;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::Arc;
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SwiftType {
+    Mt103,
     Mt940,
 }
 
+impl SwiftType {
+    pub fn value(&self) -> String {
+        match self {
+            Self::Mt103 => "103".to_string(),
+            Self::Mt940 => "940".to_string(),
+        }
+    }
+}
+
 impl TryFrom<&str> for SwiftType {
     type Error = eyre::Error;
 
     fn try_from(input: &str) -> Result<Self> {
         match input {
+            "103" => Ok(Self::Mt103),
             "940" => Ok(Self::Mt940),
-            _ => return Err(eyre!(
+            _ => Err(eyre!(
                 "Swift Type is either missing or the value '{input}' is not valid"
             )),
         }
@@ -24,7 +39,7 @@ impl TryFrom<&str> for SwiftType {
 
 #[allow(clippy::upper_case_acronyms)]
 #[cfg(not(tarpaulin_include))]
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TransactionType {
     BNK,
     BOE,
@@ -148,14 +163,14 @@ impl TryFrom<&str> for TransactionType {
             "UWC" => Ok(Self::UWC),
             "VDA" => Ok(Self::VDA),
             "WAR" => Ok(Self::WAR),
-            _ => return Err(eyre!(
+            _ => Err(eyre!(
                 "Transaction Type is either missing or the value '{input}' is not valid"
             )),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IO {
     Input,
     Output,
@@ -169,14 +184,14 @@ impl TryFrom<&str> for IO {
         match input {
             "I" => Ok(Self::Input),
             "O" => Ok(Self::Output),
-            _ => return Err(eyre!(
+            _ => Err(eyre!(
                 "IO is either missing or the value '{input}' is not valid"
             )),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ApplicationId {
     F,
     A,
@@ -192,14 +207,14 @@ impl TryFrom<&str> for ApplicationId {
             "F" => Ok(Self::F),
             "A" => Ok(Self::A),
             "L" => Ok(Self::L),
-            _ => return Err(eyre!(
+            _ => Err(eyre!(
                 "Application Id is either missing or the value '{input}' is not valid"
             )),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ServiceId {
     FinGpa,
     AckNak,
@@ -213,14 +228,14 @@ impl TryFrom<&str> for ServiceId {
         match input {
             "21" => Ok(Self::FinGpa),
             "01" => Ok(Self::AckNak),
-            _ => return Err(eyre!(
+            _ => Err(eyre!(
                 "Service Id is either missing or the value '{input}' is not valid"
             )),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CreditDebit {
     Credit,
     Debit,
@@ -233,8 +248,8 @@ impl CreditDebit {
         match self {
             Self::Credit => "C".to_string(),
             Self::Debit => "D".to_string(),
-            Self::CreditReversal => "CR".to_string(),
-            Self::DebitReversal => "DR".to_string(),
+            Self::CreditReversal => "RC".to_string(),
+            Self::DebitReversal => "RD".to_string(),
         }
     }
 }
@@ -245,31 +260,41 @@ impl TryFrom<&str> for CreditDebit {
     #[cfg(not(tarpaulin_include))]
     fn try_from(input: &str) -> Result<Self> {
         match input {
-            "CR" | "RC" => Ok(Self::CreditReversal),
-            "DR" | "RD" => Ok(Self::DebitReversal),
+            "RC" => Ok(Self::CreditReversal),
+            "RD" => Ok(Self::DebitReversal),
             "C" => Ok(Self::Credit),
             "D" => Ok(Self::Debit),
-            _ => return Err(eyre!(
+            _ => Err(eyre!(
                 "Credit Debit is either missing or the value '{input}' is not valid"
             )),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BalanceType {
     Final,
     Intermediary,
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
-pub enum FundsCode {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransactionTypeMarker {
     SwiftTransfer,
     NonSwiftTransfer,
     FirstAdvice,
 }
 
-impl TryFrom<&str> for FundsCode {
+impl TransactionTypeMarker {
+    pub fn value(&self) -> String {
+        match self {
+            Self::SwiftTransfer => "S".to_string(),
+            Self::NonSwiftTransfer => "N".to_string(),
+            Self::FirstAdvice => "F".to_string(),
+        }
+    }
+}
+
+impl TryFrom<&str> for TransactionTypeMarker {
     type Error = eyre::Error;
 
     #[cfg(not(tarpaulin_include))]
@@ -278,15 +303,89 @@ impl TryFrom<&str> for FundsCode {
             "S" => Ok(Self::SwiftTransfer),
             "N" => Ok(Self::NonSwiftTransfer),
             "F" => Ok(Self::FirstAdvice),
-            _ => return Err(eyre!(
-                "Funds Code is either missing or the value '{input}' is not valid"
+            _ => Err(eyre!(
+                "Transaction Type Marker is either missing or the value '{input}' is not valid"
             )),
         }
     }
 }
 
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BankOperationCode {
+    CRED,
+    CRTS,
+    SPAY,
+    SPRI,
+    SSTD,
+}
+
+impl BankOperationCode {
+    pub fn value(&self) -> String {
+        match self {
+            Self::CRED => "CRED".to_string(),
+            Self::CRTS => "CRTS".to_string(),
+            Self::SPAY => "SPAY".to_string(),
+            Self::SPRI => "SPRI".to_string(),
+            Self::SSTD => "SSTD".to_string(),
+        }
+    }
+}
+
+impl TryFrom<&str> for BankOperationCode {
+    type Error = eyre::Error;
+
+    #[cfg(not(tarpaulin_include))]
+    fn try_from(input: &str) -> Result<Self> {
+        match input {
+            "CRED" => Ok(Self::CRED),
+            "CRTS" => Ok(Self::CRTS),
+            "SPAY" => Ok(Self::SPAY),
+            "SPRI" => Ok(Self::SPRI),
+            "SSTD" => Ok(Self::SSTD),
+            _ => Err(eyre!(
+                "Bank Operation Code is either missing or the value '{input}' is not valid"
+            )),
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DetailsOfCharges {
+    BEN,
+    OUR,
+    SHA,
+}
+
+impl DetailsOfCharges {
+    pub fn value(&self) -> String {
+        match self {
+            Self::BEN => "BEN".to_string(),
+            Self::OUR => "OUR".to_string(),
+            Self::SHA => "SHA".to_string(),
+        }
+    }
+}
+
+impl TryFrom<&str> for DetailsOfCharges {
+    type Error = eyre::Error;
+
+    #[cfg(not(tarpaulin_include))]
+    fn try_from(input: &str) -> Result<Self> {
+        match input {
+            "BEN" => Ok(Self::BEN),
+            "OUR" => Ok(Self::OUR),
+            "SHA" => Ok(Self::SHA),
+            _ => Err(eyre!(
+                "Details of Charges is either missing or the value '{input}' is not valid"
+            )),
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ValidationFlag {
     REMIT,
     RFDD,
@@ -302,7 +401,7 @@ impl TryFrom<&str> for ValidationFlag {
             "REMIT" => Ok(Self::REMIT),
             "RFDD" => Ok(Self::RFDD),
             "STP" => Ok(Self::STP),
-            _ => return Err(eyre!(
+            _ => Err(eyre!(
                 "Validation Flag value is either missing or the value '{input}' is not valid"
             )),
         }
@@ -310,7 +409,7 @@ impl TryFrom<&str> for ValidationFlag {
 }
 
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SanctionScreenType {
     AOK,
     FPO,
@@ -326,61 +425,98 @@ impl TryFrom<&str> for SanctionScreenType {
             "AOK" => Ok(Self::AOK),
             "FPO" => Ok(Self::FPO),
             "NOK" => Ok(Self::NOK),
-            _ => return Err(eyre!(
+            _ => Err(eyre!(
                 "Sanction Screen Type is either missing or the value '{input}' is not valid"
             )),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BusinessIdentifierCode<'a> {
-    pub business_party_prefix: &'a str,
-    pub country_code: &'a str,
-    pub business_party_suffix: &'a str,
+    pub business_party_prefix: Cow<'a, str>,
+    pub country_code: Cow<'a, str>,
+    pub business_party_suffix: Cow<'a, str>,
 }
 
 impl<'a> BusinessIdentifierCode<'a> {
     fn new(input: &'a str) -> Result<Self> {
-        let business_party_prefix = &input[..4];
-        let country_code = alpha2(&input[4..6])
+        let invalid = || {
+            eyre!("Business Identifier Code is either missing or the value '{input}' is not valid")
+        };
+
+        let business_party_prefix = input.get(..4).ok_or_else(invalid)?;
+        let country_code = input.get(4..6).ok_or_else(invalid)?;
+        let country_code = alpha2(country_code)
             .ok_or_else(|| {
-                eyre!(
-                    "Country code is either missing or the value '{}' is not valid",
-                    &input[4..6]
-                )
+                eyre!("Country code is either missing or the value '{country_code}' is not valid")
             })?
             .alpha2;
-        let business_party_suffix = &input[6..];
+        let business_party_suffix = input.get(6..).ok_or_else(invalid)?;
 
         Ok(Self {
-            business_party_prefix,
-            country_code,
-            business_party_suffix,
+            business_party_prefix: business_party_prefix.into(),
+            country_code: country_code.into(),
+            business_party_suffix: business_party_suffix.into(),
         })
     }
+
+    pub fn into_owned(self) -> BusinessIdentifierCode<'static> {
+        BusinessIdentifierCode {
+            business_party_prefix: owned(self.business_party_prefix),
+            country_code: owned(self.country_code),
+            business_party_suffix: owned(self.business_party_suffix),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LogicalTerminalAddress<'a> {
     pub bic_code: BusinessIdentifierCode<'a>,
-    pub terminal_code: &'a str, // try to make this a char?
-    pub branch_code: &'a str,
+    pub terminal_code: Cow<'a, str>, // try to make this a char?
+    pub branch_code: Cow<'a, str>,
 }
 
 impl<'a> LogicalTerminalAddress<'a> {
     pub fn new(input: &'a str) -> Result<Self> {
-        let bic_code = BusinessIdentifierCode::new(&input[..8])?;
+        let invalid = || {
+            eyre!("Logical Terminal Address is either missing or the value '{input}' is not valid")
+        };
+
+        let bic_code = BusinessIdentifierCode::new(input.get(..8).ok_or_else(invalid)?)?;
 
         Ok(Self {
             bic_code,
-            terminal_code: &input[8..9],
-            branch_code: &input[9..],
+            terminal_code: input.get(8..9).ok_or_else(invalid)?.into(),
+            branch_code: input.get(9..).ok_or_else(invalid)?.into(),
         })
     }
+
+    // the BIC of the address, the primary office `XXX` branch is left off
+    pub fn bic(&self) -> String {
+        let bic = format!(
+            "{}{}{}",
+            self.bic_code.business_party_prefix,
+            self.bic_code.country_code,
+            self.bic_code.business_party_suffix
+        );
+
+        match self.branch_code.as_ref() {
+            "XXX" | "" => bic,
+            branch_code => format!("{bic}{branch_code}"),
+        }
+    }
+
+    pub fn into_owned(self) -> LogicalTerminalAddress<'static> {
+        LogicalTerminalAddress {
+            bic_code: self.bic_code.into_owned(),
+            terminal_code: owned(self.terminal_code),
+            branch_code: owned(self.branch_code),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Balance {
     pub credit_or_debit: CreditDebit,
     pub date: NaiveDate,
@@ -389,16 +525,17 @@ pub struct Balance {
 }
 
 impl Balance {
-    pub fn new(input: &str) -> Result<Self> {
-        let credit_or_debit = CreditDebit::try_from(&input[..1])?;
-        let date = naive_date_from_swift_date(&input[1..7])?;
-        let currency = Currency::from_code(&input[7..10]).ok_or_else(|| {
-            eyre!(
-                "currency code is either missing or the value '{}' is not valid",
-                &input[7..10]
-            )
+    pub fn new(input: &str, context: &mut ParseContext) -> Result<Self> {
+        let input = context.upper_case("Balance", input);
+        let subfields = FormatSpec::built_in(BALANCE)?.parse(&input)?;
+        let code = subfields[2].unwrap_or_default();
+
+        let credit_or_debit = CreditDebit::try_from(subfields[0].unwrap_or_default())?;
+        let date = naive_date_from_swift_date(subfields[1].unwrap_or_default(), context)?;
+        let currency = Currency::from_code(code).ok_or_else(|| {
+            eyre!("currency code is either missing or the value '{code}' is not valid")
         })?;
-        let amount = float_from_swift_amount(&input[10..])?;
+        let amount = float_from_swift_amount(subfields[3].unwrap_or_default())?;
 
         Ok(Self {
             credit_or_debit,
@@ -407,41 +544,86 @@ impl Balance {
             amount,
         })
     }
+
+    // a debit balance is negative
+    pub fn signed_amount(&self) -> f64 {
+        match self.credit_or_debit {
+            CreditDebit::Credit | CreditDebit::DebitReversal => self.amount,
+            CreditDebit::Debit | CreditDebit::CreditReversal => -self.amount,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+// A currency and amount e.g. `EUR1000,00` as used by :33B:, :71F: and :71G:
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurrencyAmount {
+    pub currency: Currency,
+    pub amount: f64,
+}
+
+impl CurrencyAmount {
+    pub fn new(input: &str) -> Result<Self> {
+        let subfields = FormatSpec::built_in(CURRENCY_AMOUNT)?.parse(input)?;
+        let code = subfields[0].unwrap_or_default();
+        let currency = Currency::from_code(code).ok_or_else(|| {
+            eyre!("currency code is either missing or the value '{code}' is not valid")
+        })?;
+        let amount = float_from_swift_amount(subfields[1].unwrap_or_default())?;
+
+        Ok(Self { currency, amount })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MessageInputReference<'a> {
     pub date: NaiveDate,
-    pub lt_identifier: &'a str,
-    pub branch_code: &'a str,
+    pub lt_identifier: Cow<'a, str>,
+    pub branch_code: Cow<'a, str>,
     pub session_number: i16,
     pub sequence_number: i16,
 }
 
 impl<'a> MessageInputReference<'a> {
-    pub fn new(input: &'a str) -> Result<Self> {
-        let date = naive_date_from_swift_date(&input[..6])?;
-        let lt_identifier = &input[6..18];
-        let branch_code = &input[18..21];
-        let session_number = input[21..25].parse::<i16>()?;
-        let sequence_number = input[25..].parse::<i16>()?;
+    pub fn new(input: &'a str, context: &mut ParseContext) -> Result<Self> {
+        let invalid = || {
+            eyre!("Message Input Reference is either missing or the value '{input}' is not valid")
+        };
+        let field = |start: usize, end: usize| input.get(start..end).ok_or_else(invalid);
+
+        let date = naive_date_from_swift_date(field(0, 6)?, context)?;
+        let lt_identifier = field(6, 18)?;
+        let branch_code = field(18, 21)?;
+        let session_number = field(21, 25)?.parse::<i16>().map_err(|_| invalid())?;
+        let sequence_number = field(25, input.len())?
+            .parse::<i16>()
+            .map_err(|_| invalid())?;
 
         Ok(Self {
             date,
-            lt_identifier,
-            branch_code,
+            lt_identifier: lt_identifier.into(),
+            branch_code: branch_code.into(),
             session_number,
             sequence_number,
         })
     }
+
+    pub fn into_owned(self) -> MessageInputReference<'static> {
+        MessageInputReference {
+            date: self.date,
+            lt_identifier: owned(self.lt_identifier),
+            branch_code: owned(self.branch_code),
+            session_number: self.session_number,
+            sequence_number: self.sequence_number,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Serialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AddressInformation<'a> {
     pub time_of_crediting: NaiveTime,
     pub time_of_debiting: NaiveTime,
-    pub country_code: &'a str,
-    pub internal_posting_reference: &'a str,
+    pub country_code: Cow<'a, str>,
+    pub internal_posting_reference: Cow<'a, str>,
 }
 
 impl<'a> AddressInformation<'a> {
@@ -464,56 +646,292 @@ impl<'a> AddressInformation<'a> {
         Ok(Self {
             time_of_crediting,
             time_of_debiting,
-            country_code,
-            internal_posting_reference,
+            country_code: country_code.into(),
+            internal_posting_reference: internal_posting_reference.into(),
         })
     }
+
+    pub fn into_owned(self) -> AddressInformation<'static> {
+        AddressInformation {
+            time_of_crediting: self.time_of_crediting,
+            time_of_debiting: self.time_of_debiting,
+            country_code: owned(self.country_code),
+            internal_posting_reference: owned(self.internal_posting_reference),
+        }
+    }
 }
 
-pub fn naive_time_from_swift_time(time: &str) -> Result<chrono::NaiveTime> {
-    Ok(chrono::NaiveTime::from_hms(
-        time[..2].parse::<u32>()?,
-        time[2..4].parse::<u32>()?,
-        time[4..].parse::<u32>()?,
-    ))
+// How two digit years and dates that do not exist (e.g. 090230) are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DatePolicy {
+    // YY below the pivot is 20YY, anything else is 19YY
+    pub century_pivot: u8,
+    // when set, YY is resolved to the year closest to this date instead of using the pivot
+    pub reference_date: Option<NaiveDate>,
+    pub invalid_dates: InvalidDates,
+}
+
+impl Default for DatePolicy {
+    fn default() -> Self {
+        Self {
+            century_pivot: 80,
+            reference_date: None,
+            invalid_dates: InvalidDates::Error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InvalidDates {
+    Error,
+    // use the closest valid date and record a warning
+    Warn,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Warning {
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+// How closely a message has to follow the SWIFT format. In lenient mode unknown tags are
+// kept in `unknown_tags` and problems that can be worked around (lower case codes, blank
+// lines, an overlong :86:) are recorded as warnings instead of failing the message.
+// Without a profile the bank is detected from the BIC of the basic header
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseOptions {
+    pub strict: bool,
+    pub date_policy: DatePolicy,
+    pub profile: Option<BankProfile>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            strict: true,
+            date_policy: DatePolicy::default(),
+            profile: None,
+        }
+    }
+}
+
+// Settings and collected warnings that are passed through the parsers of a message
+#[derive(Debug)]
+pub struct ParseContext {
+    pub strict: bool,
+    pub date_policy: DatePolicy,
+    pub profile: Arc<BankProfile>,
+    pub warnings: Vec<Warning>,
+}
+
+impl Default for ParseContext {
+    fn default() -> Self {
+        Self::with_options(ParseOptions::default())
+    }
 }
 
-pub fn naive_date_from_swift_date(date: &str) -> Result<NaiveDate> {
-    if date.len() == 4 {
-        Ok(NaiveDate::from_ymd(
-            chrono::Utc::now().year(),
-            date[..2].parse::<u32>()?,
-            date[2..].parse::<u32>()?,
-        ))
-    } else if date.len() == 6 {
-        return Ok(NaiveDate::from_ymd(
-            2000 + date[..2].parse::<i32>()?,
-            date[2..4].parse::<u32>()?,
-            date[4..6].parse::<u32>()?,
-        ))
+impl ParseContext {
+    pub fn new(date_policy: DatePolicy) -> Self {
+        Self::with_options(ParseOptions {
+            date_policy,
+            ..ParseOptions::default()
+        })
+    }
+
+    pub fn with_options(options: ParseOptions) -> Self {
+        Self {
+            strict: options.strict,
+            date_policy: options.date_policy,
+            profile: options.profile.map_or_else(generic, Arc::new),
+            warnings: vec![],
+        }
+    }
+
+    // fails the message in strict mode, in lenient mode the error becomes a warning
+    pub fn recover(&mut self, error: eyre::Error) -> Result<()> {
+        if self.strict {
+            return Err(error);
+        }
+
+        self.warnings.push(Warning::new(error.to_string()));
+        Ok(())
+    }
+
+    // Codes and currencies are upper case. In lenient mode a value without free text, e.g.
+    // a balance, is read as upper case when it is not
+    pub fn upper_case<'b>(&mut self, name: &str, value: &'b str) -> Cow<'b, str> {
+        if self.strict || !value.chars().any(|c| c.is_ascii_lowercase()) {
+            return Cow::Borrowed(value);
+        }
+
+        self.warnings.push(Warning::new(format!(
+            "{name} '{value}' has lower case codes which were read as upper case"
+        )));
+        Cow::Owned(value.to_ascii_uppercase())
+    }
+}
+
+pub fn naive_time_from_swift_time(time: &str) -> Result<NaiveTime> {
+    let invalid = || eyre!("Invalid swift time '{time}' provided");
+
+    NaiveTime::from_hms_opt(
+        number(time, 0..2).ok_or_else(invalid)?,
+        number(time, 2..4).ok_or_else(invalid)?,
+        number(time, 4..time.len()).ok_or_else(invalid)?,
+    )
+    .ok_or_else(invalid)
+}
+
+pub fn naive_date_from_swift_date(date: &str, context: &mut ParseContext) -> Result<NaiveDate> {
+    let invalid = || eyre!("Invalid swift date provided: '{date}'");
+
+    if date.len() == 6 {
+        let year = year_from_swift_year(
+            number(date, 0..2).ok_or_else(invalid)?,
+            &context.date_policy,
+        );
+        naive_date_from_ymd(
+            year,
+            number(date, 2..4).ok_or_else(invalid)?,
+            number(date, 4..6).ok_or_else(invalid)?,
+            date,
+            context,
+        )
     } else if date.len() == 8 {
-        return Ok(NaiveDate::from_ymd(
-            date[..4].parse::<i32>()?,
-            date[4..6].parse::<u32>()?,
-            date[6..8].parse::<u32>()?,
-        ))
+        naive_date_from_ymd(
+            number(date, 0..4).ok_or_else(invalid)?,
+            number(date, 4..6).ok_or_else(invalid)?,
+            number(date, 6..8).ok_or_else(invalid)?,
+            date,
+            context,
+        )
     } else {
-        return Err(eyre!("Invalid swift date provided"))
+        Err(eyre!("Invalid swift date provided"))
+    }
+}
+
+// An entry date (MMDD) has no year, so use the year that puts it closest to the value date.
+// This also covers the turn of the year, e.g. an entry on 1231 for a value date of 100102
+pub fn naive_date_from_swift_entry_date(
+    date: &str,
+    value_date: NaiveDate,
+    context: &mut ParseContext,
+) -> Result<NaiveDate> {
+    if date.len() != 4 {
+        return Err(eyre!("Invalid swift date provided"));
+    }
+
+    let month = date[..2].parse::<u32>()?;
+    let day = date[2..].parse::<u32>()?;
+
+    match (value_date.year() - 1..=value_date.year() + 1)
+        .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+        .min_by_key(|entry_date| (*entry_date - value_date).num_days().abs())
+    {
+        Some(entry_date) => Ok(entry_date),
+        None => naive_date_from_ymd(value_date.year(), month, day, date, context),
     }
 }
 
-pub fn naive_date_time_from_swift_date_time(date_time: &str) -> Result<NaiveDateTime> {
+// YYMMDDHHMMSS followed by the milliseconds, e.g. the balance checkpoint of a user header
+pub fn naive_date_time_from_swift_date_time(
+    date_time: &str,
+    context: &mut ParseContext,
+) -> Result<NaiveDateTime> {
+    let invalid = || {
+        eyre!("Swift date time is either missing or the value '{date_time}' is not valid")
+    };
+
+    let time = NaiveTime::from_hms_milli_opt(
+        number(date_time, 6..8).ok_or_else(invalid)?,
+        number(date_time, 8..10).ok_or_else(invalid)?,
+        number(date_time, 10..12).ok_or_else(invalid)?,
+        number(date_time, 12..date_time.len()).ok_or_else(invalid)?,
+    )
+    .ok_or_else(invalid)?;
+    let date = date_time.get(..6).ok_or_else(invalid)?;
+
     Ok(NaiveDateTime::new(
-        naive_date_from_swift_date(&date_time[..6])?,
-        NaiveTime::from_hms_milli(
-            date_time[6..8].parse::<u32>()?,
-            date_time[8..10].parse::<u32>()?,
-            date_time[10..12].parse::<u32>()?,
-            date_time[12..].parse::<u32>()?,
-        ),
+        naive_date_from_swift_date(date, context)?,
+        time,
     ))
 }
 
+// the digits in `range` of the value, `None` when they are not all there
+fn number<T: std::str::FromStr>(value: &str, range: std::ops::Range<usize>) -> Option<T> {
+    value
+        .get(range)
+        .filter(|x| x.bytes().all(|x| x.is_ascii_digit()))
+        .and_then(|x| x.parse().ok())
+}
+
+fn year_from_swift_year(year: i32, policy: &DatePolicy) -> i32 {
+    match policy.reference_date {
+        Some(reference_date) => {
+            let century = reference_date.year() - reference_date.year().rem_euclid(100);
+
+            [century - 100, century, century + 100]
+                .into_iter()
+                .map(|century| century + year)
+                .min_by_key(|candidate| (candidate - reference_date.year()).abs())
+                .unwrap_or(century + year)
+        }
+        None if year < i32::from(policy.century_pivot) => 2000 + year,
+        None => 1900 + year,
+    }
+}
+
+fn naive_date_from_ymd(
+    year: i32,
+    month: u32,
+    day: u32,
+    input: &str,
+    context: &mut ParseContext,
+) -> Result<NaiveDate> {
+    if let Some(date) = NaiveDate::from_ymd_opt(year, month, day) {
+        return Ok(date);
+    }
+
+    match context.date_policy.invalid_dates {
+        InvalidDates::Error => Err(eyre!("Invalid swift date provided: '{input}'")),
+        InvalidDates::Warn => {
+            let month = month.clamp(1, 12);
+            let date = (1..=day.clamp(1, 31))
+                .rev()
+                .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+                .ok_or_else(|| eyre!("Invalid swift date provided: '{input}'"))?;
+
+            context.warnings.push(Warning::new(format!(
+                "invalid date '{input}' was replaced with {date}"
+            )));
+
+            Ok(date)
+        }
+    }
+}
+
+// two letters of the country, two check digits and 11 to 30 letters and digits
+pub fn is_iban(account: &str) -> bool {
+    let bytes = account.as_bytes();
+
+    (15..=34).contains(&bytes.len())
+        && bytes[..2].iter().all(u8::is_ascii_uppercase)
+        && bytes[2..4].iter().all(u8::is_ascii_digit)
+        && bytes[4..]
+            .iter()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+// detaches a value from the message it was parsed from, used by the `into_owned` methods
+pub fn owned(value: Cow<str>) -> Cow<'static, str> {
+    Cow::Owned(value.into_owned())
+}
+
 pub fn float_from_swift_amount(amount: &str) -> Result<f64> {
     Ok(amount.replace(',', ".").parse::<f64>()?)
 }
@@ -544,7 +962,7 @@ mod tests {
 
     #[test]
     fn test_date_long_year() -> Result<()> {
-        let date = naive_date_from_swift_date("20090924")?;
+        let date = naive_date_from_swift_date("20090924", &mut ParseContext::default())?;
 
         assert_eq!(date.year(), 2009);
         assert_eq!(date.month(), 9);
@@ -554,7 +972,7 @@ mod tests {
 
     #[test]
     fn test_date_short_year() -> Result<()> {
-        let date = naive_date_from_swift_date("090924")?;
+        let date = naive_date_from_swift_date("090924", &mut ParseContext::default())?;
 
         assert_eq!(date.year(), 2009);
         assert_eq!(date.month(), 9);
@@ -564,14 +982,180 @@ mod tests {
 
     #[test]
     fn test_date_no_year() -> Result<()> {
-        let date = naive_date_from_swift_date("0924").unwrap();
+        let date = naive_date_from_swift_entry_date(
+            "0924",
+            NaiveDate::from_ymd_opt(2009, 9, 25).unwrap(),
+            &mut ParseContext::default(),
+        )?;
 
-        assert_eq!(date.year(), chrono::Utc::now().year());
+        assert_eq!(date.year(), 2009);
         assert_eq!(date.month(), 9);
         assert_eq!(date.day(), 24);
         Ok(())
     }
 
+    #[test]
+    fn test_date_no_year_next_year() -> Result<()> {
+        let date = naive_date_from_swift_entry_date(
+            "0102",
+            NaiveDate::from_ymd_opt(2009, 12, 30).unwrap(),
+            &mut ParseContext::default(),
+        )?;
+
+        assert_eq!(date, NaiveDate::from_ymd_opt(2010, 1, 2).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_no_year_previous_year() -> Result<()> {
+        let date = naive_date_from_swift_entry_date(
+            "1231",
+            NaiveDate::from_ymd_opt(2010, 1, 2).unwrap(),
+            &mut ParseContext::default(),
+        )?;
+
+        assert_eq!(date, NaiveDate::from_ymd_opt(2009, 12, 31).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_no_year_leap_day() -> Result<()> {
+        let date = naive_date_from_swift_entry_date(
+            "0229",
+            NaiveDate::from_ymd_opt(2012, 3, 1).unwrap(),
+            &mut ParseContext::default(),
+        )?;
+
+        assert_eq!(date, NaiveDate::from_ymd_opt(2012, 2, 29).unwrap());
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid swift date provided")]
+    fn test_date_no_year_bad_data() {
+        naive_date_from_swift_entry_date(
+            "0230",
+            NaiveDate::from_ymd_opt(2012, 3, 1).unwrap(),
+            &mut ParseContext::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid swift date provided")]
+    fn test_date_no_year_without_value_date() {
+        naive_date_from_swift_date("0924", &mut ParseContext::default()).unwrap();
+    }
+
+    #[test]
+    fn test_date_short_year_before_pivot() -> Result<()> {
+        let date = naive_date_from_swift_date("790924", &mut ParseContext::default())?;
+
+        assert_eq!(date.year(), 2079);
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_short_year_after_pivot() -> Result<()> {
+        let date = naive_date_from_swift_date("980924", &mut ParseContext::default())?;
+
+        assert_eq!(date.year(), 1998);
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_short_year_custom_pivot() -> Result<()> {
+        let mut context = ParseContext::new(DatePolicy {
+            century_pivot: 50,
+            ..DatePolicy::default()
+        });
+
+        assert_eq!(
+            naive_date_from_swift_date("490924", &mut context)?.year(),
+            2049
+        );
+        assert_eq!(
+            naive_date_from_swift_date("500924", &mut context)?.year(),
+            1950
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_short_year_reference_date() -> Result<()> {
+        let mut context = ParseContext::new(DatePolicy {
+            reference_date: NaiveDate::from_ymd_opt(2026, 10, 18),
+            ..DatePolicy::default()
+        });
+
+        assert_eq!(
+            naive_date_from_swift_date("980924", &mut context)?.year(),
+            1998
+        );
+        assert_eq!(
+            naive_date_from_swift_date("090924", &mut context)?.year(),
+            2009
+        );
+        assert_eq!(
+            naive_date_from_swift_date("700924", &mut context)?.year(),
+            2070
+        );
+        assert_eq!(
+            naive_date_from_swift_date("770924", &mut context)?.year(),
+            1977
+        );
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid swift date provided: '090230'")]
+    fn test_date_invalid() {
+        naive_date_from_swift_date("090230", &mut ParseContext::default()).unwrap();
+    }
+
+    #[test]
+    fn test_date_invalid_warning() -> Result<()> {
+        let mut context = ParseContext::new(DatePolicy {
+            invalid_dates: InvalidDates::Warn,
+            ..DatePolicy::default()
+        });
+
+        let date = naive_date_from_swift_date("090230", &mut context)?;
+
+        assert_eq!(date, NaiveDate::from_ymd_opt(2009, 2, 28).unwrap());
+        assert_eq!(
+            context.warnings,
+            vec![Warning::new(
+                "invalid date '090230' was replaced with 2009-02-28".to_string()
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_no_year_invalid_warning() -> Result<()> {
+        let mut context = ParseContext::new(DatePolicy {
+            invalid_dates: InvalidDates::Warn,
+            ..DatePolicy::default()
+        });
+
+        let date = naive_date_from_swift_entry_date(
+            "0230",
+            NaiveDate::from_ymd_opt(2012, 3, 1).unwrap(),
+            &mut context,
+        )?;
+
+        assert_eq!(date, NaiveDate::from_ymd_opt(2012, 2, 29).unwrap());
+        assert_eq!(context.warnings.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid swift time '246000' provided")]
+    fn test_time_invalid() {
+        naive_time_from_swift_time("246000").unwrap();
+    }
+
     #[test]
     fn test_time() -> Result<()> {
         let time = naive_time_from_swift_time("121413").unwrap();
@@ -585,12 +1169,14 @@ mod tests {
     #[test]
     #[should_panic(expected = "Invalid swift date provided")]
     fn test_date_bad_data() {
-        naive_date_from_swift_date("").unwrap();
+        naive_date_from_swift_date("", &mut ParseContext::default()).unwrap();
     }
 
     #[test]
     fn test_datetime() -> Result<()> {
-        let datetime = naive_date_time_from_swift_date_time("18071715301204").unwrap();
+        let datetime =
+            naive_date_time_from_swift_date_time("18071715301204", &mut ParseContext::default())
+                .unwrap();
         assert_eq!(datetime.year(), 2018);
         assert_eq!(datetime.month(), 7);
         assert_eq!(datetime.day(), 17);
@@ -600,6 +1186,18 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_datetime_bad_data() {
+        for date_time in ["2001", "200103", "2001031530", "200103153012", "2001é3153012"] {
+            assert_eq!(
+                naive_date_time_from_swift_date_time(date_time, &mut ParseContext::default())
+                    .unwrap_err()
+                    .to_string(),
+                format!("Swift date time is either missing or the value '{date_time}' is not valid")
+            );
+        }
+    }
+
     #[test]
     fn test_business_identifier_code() -> Result<()> {
         let bic_code = BusinessIdentifierCode::new("ASNBNL21")?;
@@ -624,6 +1222,8 @@ mod tests {
     fn test_credit_or_debit() -> Result<()> {
         assert_eq!(CreditDebit::try_from("C")?, CreditDebit::Credit);
         assert_eq!(CreditDebit::try_from("D")?, CreditDebit::Debit);
+        assert_eq!(CreditDebit::try_from("RC")?, CreditDebit::CreditReversal);
+        assert_eq!(CreditDebit::try_from("RD")?, CreditDebit::DebitReversal);
         Ok(())
     }
 
@@ -633,6 +1233,12 @@ mod tests {
         CreditDebit::try_from("A").unwrap();
     }
 
+    #[test]
+    #[should_panic(expected = "Credit Debit is either missing or the value 'CR' is not valid")]
+    fn test_credit_or_debit_funds_code() {
+        CreditDebit::try_from("CR").unwrap();
+    }
+
     #[test]
     fn test_currency_code() -> Result<()> {
         assert_eq!(Currency::from_code("EUR").unwrap(), Currency::EUR);
@@ -640,25 +1246,36 @@ mod tests {
     }
 
     #[test]
-    fn test_funds_code() -> Result<()> {
-        assert_eq!(FundsCode::try_from("S")?, FundsCode::SwiftTransfer);
-        assert_eq!(FundsCode::try_from("N")?, FundsCode::NonSwiftTransfer);
-        assert_eq!(FundsCode::try_from("F")?, FundsCode::FirstAdvice);
+    fn test_transaction_type_marker() -> Result<()> {
+        assert_eq!(
+            TransactionTypeMarker::try_from("S")?,
+            TransactionTypeMarker::SwiftTransfer
+        );
+        assert_eq!(
+            TransactionTypeMarker::try_from("N")?,
+            TransactionTypeMarker::NonSwiftTransfer
+        );
+        assert_eq!(
+            TransactionTypeMarker::try_from("F")?,
+            TransactionTypeMarker::FirstAdvice
+        );
         Ok(())
     }
 
     #[test]
-    #[should_panic(expected = "Funds Code is either missing or the value 'T' is not valid")]
-    fn test_funds_code_bad_data() {
-        FundsCode::try_from("T").unwrap();
+    #[should_panic(
+        expected = "Transaction Type Marker is either missing or the value 'T' is not valid"
+    )]
+    fn test_transaction_type_marker_bad_data() {
+        TransactionTypeMarker::try_from("T").unwrap();
     }
 
     #[test]
     fn test_balance() -> Result<()> {
-        let balance = Balance::new("C090930EUR53189,31")?;
+        let balance = Balance::new("C090930EUR53189,31", &mut ParseContext::default())?;
 
         assert_eq!(balance.credit_or_debit, CreditDebit::Credit);
-        assert_eq!(balance.date, NaiveDate::from_ymd(2009, 9, 30));
+        assert_eq!(balance.date, NaiveDate::from_ymd_opt(2009, 9, 30).unwrap());
         assert_eq!(balance.currency, Currency::EUR);
         assert_eq!(balance.amount, 53189.31);
         Ok(())
@@ -666,7 +1283,10 @@ mod tests {
 
     #[test]
     fn test_message_input_reference() -> Result<()> {
-        let mir = MessageInputReference::new("120811BANKBEBBAXXX2222123456")?;
+        let mir = MessageInputReference::new(
+            "120811BANKBEBBAXXX2222123456",
+            &mut ParseContext::default(),
+        )?;
         assert_eq!(mir.date.year(), 2012);
         assert_eq!(mir.date.month(), 8);
         assert_eq!(mir.date.day(), 11);
@@ -0,0 +1,121 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use eyre::{eyre, Result};
+use iso_currency::Currency;
+use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+// Helpers shared by the ISO 20022 (MX) messages
+
+pub fn amount(
+    writer: &mut Writer<Cursor<Vec<u8>>>,
+    name: &str,
+    currency: &str,
+    amount: f64,
+) -> Result<()> {
+    writer.write_event(Event::Start(
+        BytesStart::new(name).with_attributes([("Ccy", currency)]),
+    ))?;
+    writer.write_event(Event::Text(BytesText::new(&amount.to_string())))?;
+    end(writer, name)
+}
+
+pub fn element(writer: &mut Writer<Cursor<Vec<u8>>>, name: &str, text: &str) -> Result<()> {
+    start(writer, name)?;
+    writer.write_event(Event::Text(BytesText::new(text)))?;
+    end(writer, name)
+}
+
+pub fn start(writer: &mut Writer<Cursor<Vec<u8>>>, name: &str) -> Result<()> {
+    writer.write_event(Event::Start(BytesStart::new(name)))?;
+    Ok(())
+}
+
+pub fn end(writer: &mut Writer<Cursor<Vec<u8>>>, name: &str) -> Result<()> {
+    writer.write_event(Event::End(BytesEnd::new(name)))?;
+    Ok(())
+}
+
+// The text of every element without children keyed by its path below the document
+// e.g. `GrpHdr/MsgId`, and of every attribute as `<path>@<name>`. Namespace prefixes
+// are dropped and repeated elements keep the order of the document
+pub fn leaf_values(xml: &str) -> Result<BTreeMap<String, Vec<String>>> {
+    let mut reader = Reader::from_str(xml);
+
+    let mut path: Vec<String> = vec![];
+    let mut text = String::new();
+    let mut leaf = false;
+    let mut values: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => {
+                path.push(String::from_utf8(e.local_name().as_ref().to_vec())?);
+
+                for attribute in e.attributes() {
+                    let attribute = attribute?;
+
+                    if attribute.key.as_namespace_binding().is_some() {
+                        continue;
+                    }
+
+                    let name = String::from_utf8(attribute.key.local_name().as_ref().to_vec())?;
+                    values
+                        .entry(format!("{}@{name}", path[1..].join("/")))
+                        .or_default()
+                        .push(attribute.unescape_value()?.to_string());
+                }
+
+                text.clear();
+                leaf = true;
+            }
+            Event::Text(e) => text.push_str(&e.unescape()?),
+            Event::CData(e) => text.push_str(std::str::from_utf8(&e.into_inner())?),
+            Event::End(_) => {
+                if leaf {
+                    values
+                        .entry(path.get(1..).unwrap_or_default().join("/"))
+                        .or_default()
+                        .push(std::mem::take(&mut text));
+                }
+
+                path.pop();
+                leaf = false;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(values)
+}
+
+pub fn format_date_time(date_time: NaiveDateTime) -> String {
+    date_time.format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+pub fn amount_from_iso(amount: &str) -> Result<f64> {
+    amount
+        .parse::<f64>()
+        .map_err(|_| eyre!("Invalid amount provided: '{amount}'"))
+}
+
+// an ISODate `2009-09-30` or the date of an ISODateTime `2009-09-30T08:30:00+02:00`
+pub fn date_from_iso(date: &str) -> Result<NaiveDate> {
+    date.get(..10)
+        .and_then(|x| NaiveDate::parse_from_str(x, "%Y-%m-%d").ok())
+        .ok_or_else(|| eyre!("Invalid ISO date provided: '{date}'"))
+}
+
+pub fn date_time_from_iso(date_time: &str) -> Result<NaiveDateTime> {
+    date_time
+        .get(..19)
+        .and_then(|x| NaiveDateTime::parse_from_str(x, "%Y-%m-%dT%H:%M:%S").ok())
+        .ok_or_else(|| eyre!("Invalid ISO date time provided: '{date_time}'"))
+}
+
+pub fn currency_from_code(code: &str) -> Result<Currency> {
+    Currency::from_code(code)
+        .ok_or_else(|| eyre!("currency code is either missing or the value '{code}' is not valid"))
+}